@@ -0,0 +1,296 @@
+//! A headless client library for [Connect6 Online](https://github.com/yescallop/c6ol),
+//! for writing engines and scripted opponents in plain Rust.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), c6ol_bot::Error> {
+//! use c6ol_bot::Client;
+//! use c6ol_core::game::{Move, Point};
+//!
+//! let mut client = Client::connect("ws://localhost:8086/ws").await?;
+//! let id = client.start(Box::from(*b"passcode")).await?;
+//! println!("Started game {}", String::from_utf8_lossy(&id));
+//!
+//! client.play(Move::Place(Point::new(0, 0), None)).await?;
+//! while !client.record().is_ended() {
+//!     let mov = client.on_move().await?;
+//!     println!("{mov:?}");
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use c6ol_core::{
+    game::{Move, MoveError, Record, Stone},
+    protocol::{ClientMessage, GameId, Passcode, Request, ServerMessage},
+};
+use futures_util::{SinkExt, StreamExt};
+use thiserror::Error;
+use tokio::net::TcpStream;
+use tokio_tungstenite::{tungstenite::Message, MaybeTlsStream, WebSocketStream};
+
+pub use tokio_tungstenite::tungstenite;
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// An error that occurs when talking to the server.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// The connection is closed, with the reason given by the server.
+    #[error("connection closed: {0}")]
+    Closed(String),
+    /// The server sent a move that is illegal on the local record.
+    #[error("illegal move from server: {0}")]
+    IllegalMove(MoveError),
+    /// The server sent a message that failed to decode.
+    #[error("malformed server message")]
+    Malformed,
+    /// The server sent a message that is unexpected at this point.
+    #[error("unexpected server message")]
+    Unexpected,
+    /// An error occurred in the WebSocket connection.
+    #[error(transparent)]
+    WebSocket(#[from] tungstenite::Error),
+}
+
+/// A client connected to the server.
+///
+/// The client keeps a local copy of the game record, updated
+/// with every message received from the server.
+pub struct Client {
+    socket: Socket,
+    record: Record,
+    stone: Option<Stone>,
+    game_id: Option<GameId>,
+}
+
+impl Client {
+    /// Connects to the WebSocket endpoint at the given URL,
+    /// e.g., `ws://localhost:8086/ws`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection fails.
+    pub async fn connect(url: &str) -> Result<Self, Error> {
+        let (socket, _) = tokio_tungstenite::connect_async(url).await?;
+        Ok(Self {
+            socket,
+            record: Record::new(),
+            stone: None,
+            game_id: None,
+        })
+    }
+
+    /// Returns the local copy of the game record.
+    #[must_use]
+    pub fn record(&self) -> &Record {
+        &self.record
+    }
+
+    /// Returns the stone we play, or `None` if not authenticated.
+    #[must_use]
+    pub fn stone(&self) -> Option<Stone> {
+        self.stone
+    }
+
+    /// Returns the ID of the game, or `None` if not started or joined.
+    #[must_use]
+    pub fn game_id(&self) -> Option<GameId> {
+        self.game_id
+    }
+
+    /// Starts a new game as Black, returning its ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the game fails to start.
+    pub async fn start(&mut self, passcode: Passcode) -> Result<GameId, Error> {
+        self.start_with(ClientMessage::Start(passcode)).await
+    }
+
+    /// Starts a new game as Black, reserving White's seat
+    /// with a different passcode, and returns its ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the game fails to start, e.g., if the passcodes are equal.
+    pub async fn start_reserved(
+        &mut self,
+        passcode: Passcode,
+        passcode_white: Passcode,
+    ) -> Result<GameId, Error> {
+        self.start_with(ClientMessage::StartReserved(passcode, passcode_white))
+            .await
+    }
+
+    async fn start_with(&mut self, msg: ClientMessage) -> Result<GameId, Error> {
+        self.send(msg).await?;
+        let ServerMessage::Started(_, Some(id)) = self.recv().await? else {
+            return Err(Error::Unexpected);
+        };
+        self.recv_record().await?;
+        Ok(id)
+    }
+
+    /// Joins an existing game as a spectator, receiving its record.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the game fails to be joined, e.g., if it does not exist.
+    pub async fn join(&mut self, id: GameId) -> Result<(), Error> {
+        self.send(ClientMessage::Join(id)).await?;
+        self.game_id = Some(id);
+        self.recv_record().await
+    }
+
+    /// Authenticates in a joined game, returning the stone we play.
+    ///
+    /// Passing a new passcode takes the seat of White if it is vacant.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if authentication fails, e.g., with a wrong passcode.
+    pub async fn authenticate(&mut self, passcode: Passcode) -> Result<Stone, Error> {
+        self.send(ClientMessage::Start(passcode)).await?;
+        loop {
+            match self.recv().await? {
+                ServerMessage::Started(stone, None) => return Ok(stone),
+                ServerMessage::Started(_, Some(_)) => return Err(Error::Unexpected),
+                _ => {}
+            }
+        }
+    }
+
+    /// Sends a message to the server.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the message fails to be sent.
+    pub async fn send(&mut self, msg: ClientMessage) -> Result<(), Error> {
+        self.socket
+            .send(Message::Binary(msg.encode().into()))
+            .await?;
+        Ok(())
+    }
+
+    /// Receives the next message from the server, updating the local record.
+    ///
+    /// This method is cancel-safe, and can be used in `tokio::select!`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection is closed, or if the message
+    /// fails to decode or cannot be applied to the local record.
+    pub async fn recv(&mut self) -> Result<ServerMessage, Error> {
+        let msg = loop {
+            let Some(msg) = self.socket.next().await else {
+                return Err(Error::Closed(String::new()));
+            };
+            match msg? {
+                Message::Binary(data) => {
+                    break ServerMessage::decode(&data).ok_or(Error::Malformed)?
+                }
+                Message::Close(frame) => {
+                    let reason = frame.map(|frame| frame.reason.to_string());
+                    return Err(Error::Closed(reason.unwrap_or_default()));
+                }
+                _ => {}
+            }
+        };
+
+        match &msg {
+            ServerMessage::Started(stone, id) => {
+                self.stone = Some(*stone);
+                if id.is_some() {
+                    self.game_id = *id;
+                }
+            }
+            ServerMessage::Record(record) => self.record = (**record).clone(),
+            ServerMessage::Move(mov) => self.record.try_move(*mov).map_err(Error::IllegalMove)?,
+            ServerMessage::Retract => {
+                // Drop the retracted move instead of keeping it in the future.
+                let moves = self.record.moves();
+                let mut record = Record::new();
+                _ = record.make_moves(&moves[..moves.len().saturating_sub(1)]);
+                self.record = record;
+            }
+            ServerMessage::Request(..)
+            | ServerMessage::Cursor(..)
+            | ServerMessage::Farewell(..)
+            | ServerMessage::TransferCode(_)
+            | ServerMessage::Passcode(_)
+            | ServerMessage::RetractCooldown(_) => {}
+        }
+        Ok(msg)
+    }
+
+    /// Requests a one-time code for moving our seat to another connection,
+    /// skipping any other messages until it arrives.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if sending or receiving a message fails.
+    pub async fn transfer_code(&mut self) -> Result<Passcode, Error> {
+        self.send(ClientMessage::Transfer).await?;
+        loop {
+            if let ServerMessage::TransferCode(code) = self.recv().await? {
+                return Ok(code);
+            }
+        }
+    }
+
+    /// Waits for the next move, skipping any other messages.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if receiving a message fails, see [`Self::recv`].
+    pub async fn on_move(&mut self) -> Result<Move, Error> {
+        loop {
+            if let ServerMessage::Move(mov) = self.recv().await? {
+                return Ok(mov);
+            }
+        }
+    }
+
+    /// Requests to make a move.
+    ///
+    /// The local record is not updated until the server accepts
+    /// the move, which is then received by [`Self::on_move`].
+    /// A draw is requested rather than made directly.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the message fails to be sent.
+    pub async fn play(&mut self, mov: Move) -> Result<(), Error> {
+        let msg = match mov {
+            Move::Place(p1, p2) => ClientMessage::Place(p1, p2),
+            Move::Pass => ClientMessage::Pass,
+            Move::Win(p, dir) => ClientMessage::ClaimWin(p, dir),
+            Move::Draw => ClientMessage::Request(Request::Draw),
+            Move::Resign(_) => ClientMessage::Resign(None),
+        };
+        self.send(msg).await
+    }
+
+    /// Receives the record of the game, skipping any requests and the cooldown.
+    async fn recv_record(&mut self) -> Result<(), Error> {
+        loop {
+            match self.recv().await? {
+                ServerMessage::Record(_) => return Ok(()),
+                ServerMessage::Request(..) | ServerMessage::RetractCooldown(_) => {}
+                _ => return Err(Error::Unexpected),
+            }
+        }
+    }
+
+    /// Closes the connection.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection fails to be closed cleanly.
+    pub async fn close(mut self) -> Result<(), Error> {
+        self.socket.close(None).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,362 @@
+#![allow(missing_docs)]
+
+use anyhow::{bail, Context};
+use base64::prelude::*;
+use c6ol_core::game::{GameResult, Move, Point, Record, RecordDecoder, Stone, WinBy};
+use clap::{Parser, Subcommand, ValueEnum};
+use std::{
+    fs,
+    io::{self, Read, Write},
+    iter,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+/// The prefix of an analyze link, followed by a Base64 record.
+const LINK_PREFIX: &str = "#analyze,";
+/// Maximum number of points rendered on each axis, beyond which the board is cropped.
+const MAX_RENDER_SIZE: i32 = 39;
+
+/// Command-line tool for Connect6 Online records
+#[derive(Debug, Parser)]
+#[command(version, about, long_about = None)]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Print the status and the moves of a record
+    Inspect(Input),
+    /// Convert a record to another encoding
+    Convert {
+        #[command(flatten)]
+        input: Input,
+
+        /// Encode the output in the given encoding
+        #[arg(long, value_enum)]
+        to: Encoding,
+
+        /// Include future moves in the output, prefixed with the move index
+        #[arg(long)]
+        to_all: bool,
+    },
+    /// Render the board of a record as text
+    Render {
+        #[command(flatten)]
+        input: Input,
+
+        /// Leave a margin of the given width around the stones,
+        /// cropping the board to at most 39 points on each axis
+        #[arg(long, default_value_t = 2)]
+        margin: i16,
+    },
+    /// Check the records in archives, reporting the first error in each
+    Validate {
+        /// Read archives from the given files, or from standard input if `-`
+        #[arg(name = "PATH", default_value = "-")]
+        paths: Vec<PathBuf>,
+
+        /// The records include future moves, prefixed with the move index
+        #[arg(long)]
+        all: bool,
+    },
+    /// Concatenate archives, dropping invalid and duplicate records
+    Concat {
+        /// Read archives from the given files, or from standard input if `-`
+        #[arg(name = "PATH", default_value = "-")]
+        paths: Vec<PathBuf>,
+
+        /// The records include future moves, prefixed with the move index
+        #[arg(long)]
+        all: bool,
+    },
+}
+
+/// A record to read.
+#[derive(Debug, clap::Args)]
+struct Input {
+    /// Read the record from the given file, or from standard input if `-`
+    #[arg(name = "PATH", default_value = "-")]
+    path: PathBuf,
+
+    /// Decode the input in the given encoding, detected if omitted
+    #[arg(long, value_enum)]
+    from: Option<Encoding>,
+
+    /// The record includes future moves, prefixed with the move index,
+    /// as saved in local storage
+    #[arg(long)]
+    all: bool,
+}
+
+/// An encoding of records.
+///
+/// An archive holds records in a text encoding, one per line.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Encoding {
+    /// The compact binary format
+    Binary,
+    /// The binary format in Base64
+    Base64,
+    /// An analyze link, with the binary format in Base64
+    Link,
+}
+
+fn main() -> anyhow::Result<ExitCode> {
+    let args = Args::parse();
+
+    match args.command {
+        Command::Inspect(input) => inspect(&input.read()?),
+        Command::Convert { input, to, to_all } => {
+            let record = input.read()?;
+            let mut buf = vec![];
+            record.encode(&mut buf, to_all);
+
+            let mut stdout = io::stdout().lock();
+            match to {
+                Encoding::Binary => stdout.write_all(&buf)?,
+                Encoding::Base64 => writeln!(stdout, "{}", BASE64_STANDARD.encode(buf))?,
+                Encoding::Link => {
+                    writeln!(stdout, "{LINK_PREFIX}{}", BASE64_STANDARD.encode(buf))?;
+                }
+            }
+        }
+        Command::Render { input, margin } => render(&input.read()?, margin),
+        Command::Validate { paths, all } => {
+            let mut failed = 0;
+            for path in &paths {
+                for (line, res) in read_archive(path, all)? {
+                    if let Err(err) = res {
+                        println!("{}:{line}: {err:#}", path.display());
+                        failed += 1;
+                    }
+                }
+            }
+            if failed > 0 {
+                eprintln!("{failed} invalid records");
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+        Command::Concat { paths, all } => {
+            let mut seen = vec![];
+            let mut stdout = io::stdout().lock();
+            for path in &paths {
+                for (line, res) in read_archive(path, all)? {
+                    match res {
+                        Ok(record) => {
+                            // Compare normalized moves, so that a claim written
+                            // from either end is the same.
+                            let key: (usize, Vec<Move>) = (
+                                record.move_index(),
+                                record.moves().iter().map(|mov| mov.normalized()).collect(),
+                            );
+                            if seen.contains(&key) {
+                                continue;
+                            }
+                            let mut buf = vec![];
+                            record.encode(&mut buf, all);
+                            writeln!(stdout, "{}", BASE64_STANDARD.encode(buf))?;
+                            seen.push(key);
+                        }
+                        Err(err) => eprintln!("{}:{line}: skipped: {err:#}", path.display()),
+                    }
+                }
+            }
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Reads a file, or standard input if the path is `-`.
+fn read_file(path: &Path) -> anyhow::Result<Vec<u8>> {
+    let mut buf = vec![];
+    if path == Path::new("-") {
+        io::stdin().read_to_end(&mut buf)?;
+    } else {
+        buf = fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    }
+    Ok(buf)
+}
+
+impl Input {
+    fn read(&self) -> anyhow::Result<Record> {
+        let buf = read_file(&self.path)?;
+        let encoding = self.from.unwrap_or_else(|| detect_encoding(&buf));
+
+        let buf = match encoding {
+            Encoding::Binary => buf,
+            Encoding::Base64 | Encoding::Link => {
+                let text = String::from_utf8(buf).context("input is not text")?;
+                decode_text(text.trim())?
+            }
+        };
+        decode_record(&buf, self.all)
+    }
+}
+
+/// Detects the encoding of an input.
+fn detect_encoding(buf: &[u8]) -> Encoding {
+    let Ok(text) = str::from_utf8(buf) else {
+        return Encoding::Binary;
+    };
+    let text = text.trim();
+    if text.contains(LINK_PREFIX) {
+        Encoding::Link
+    } else if !text.is_empty() && BASE64_STANDARD.decode(text).is_ok() {
+        Encoding::Base64
+    } else {
+        Encoding::Binary
+    }
+}
+
+/// Decodes a record in Base64, which may be part of an analyze link.
+fn decode_text(text: &str) -> anyhow::Result<Vec<u8>> {
+    let text = match text.find(LINK_PREFIX) {
+        Some(i) => &text[i + LINK_PREFIX.len()..],
+        None => text,
+    };
+    BASE64_STANDARD.decode(text).context("malformed Base64")
+}
+
+/// Decodes a record, reporting the first move that is malformed or illegal.
+fn decode_record(mut buf: &[u8], all: bool) -> anyhow::Result<Record> {
+    let mut decoder = RecordDecoder::new(&mut buf, all).context("missing move index")?;
+    while !buf.is_empty() {
+        let rest = buf;
+        if decoder.decode(&mut buf, 1) {
+            continue;
+        }
+
+        // Decode the move again to tell why it failed.
+        let n = decoder.len() + 1;
+        match Move::decode(&mut &rest[..], decoder.is_empty()) {
+            Some(mov) => bail!("illegal move {n}: {}", format_move(mov)),
+            None if decoder.is_checksummed() => bail!("malformed move {n} or wrong checksum"),
+            None => bail!("malformed move {n}"),
+        }
+    }
+
+    if decoder.is_checksummed() {
+        decoder
+            .finish()
+            .context("missing checksum or move index out of range")
+    } else {
+        decoder.finish().context("move index out of range")
+    }
+}
+
+/// Reads an archive, returning the line number and the result of decoding
+/// each record in it. Blank lines are skipped.
+fn read_archive(path: &Path, all: bool) -> anyhow::Result<Vec<(usize, anyhow::Result<Record>)>> {
+    let Ok(text) = String::from_utf8(read_file(path)?) else {
+        bail!("{} is not text", path.display());
+    };
+    Ok(text
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            let res = decode_text(line.trim()).and_then(|buf| decode_record(&buf, all));
+            (i + 1, res)
+        })
+        .collect())
+}
+
+fn format_point(p: Point) -> String {
+    format!("({}, {})", p.x, p.y)
+}
+
+fn format_move(mov: Move) -> String {
+    match mov {
+        Move::Place(p1, p2) => iter::once(p1)
+            .chain(p2)
+            .map(format_point)
+            .collect::<Vec<_>>()
+            .join(" "),
+        Move::Pass => "pass".into(),
+        Move::Win(p, dir) => format!("win at {} towards {dir:?}", format_point(p)),
+        Move::Draw => "draw".into(),
+        Move::Resign(stone) => format!("{stone:?} resigns"),
+    }
+}
+
+fn inspect(record: &Record) {
+    let status = match record.result() {
+        None => format!("{:?} to play", record.turn().unwrap()),
+        Some(GameResult::Draw) => "drawn".into(),
+        Some(GameResult::Win(stone, WinBy::Resignation)) => {
+            format!("{:?} resigned", stone.opposite())
+        }
+        Some(GameResult::Win(stone, WinBy::Row)) => format!("{stone:?} won"),
+    };
+
+    println!("Moves: {}", record.moves().len());
+    println!("Index: {}", record.move_index());
+    println!("Status: {status}");
+
+    for turn in record.turns() {
+        let marker = if turn.index == record.move_index() {
+            "> "
+        } else {
+            "  "
+        };
+        let (n, stone, mov) = (turn.index + 1, turn.stone, format_move(turn.mov));
+        println!("{marker}{n:>3}. {stone:?}: {mov}");
+    }
+}
+
+fn render(record: &Record, margin: i16) {
+    let points: Vec<Point> = record.moves()[..record.move_index()]
+        .iter()
+        .flat_map(|&mov| match mov {
+            Move::Place(p1, p2) => iter::once(p1).chain(p2).collect(),
+            _ => vec![],
+        })
+        .collect();
+
+    let x_min = points.iter().map(|p| p.x).min().unwrap_or(0);
+    let x_max = points.iter().map(|p| p.x).max().unwrap_or(0);
+    let y_min = points.iter().map(|p| p.y).min().unwrap_or(0);
+    let y_max = points.iter().map(|p| p.y).max().unwrap_or(0);
+
+    // Crop around the center of the stones if there are too many points.
+    let axis = |min: i16, max: i16| {
+        let (min, max) = (i32::from(min), i32::from(max));
+        let size = (max - min + 1 + i32::from(margin) * 2).clamp(1, MAX_RENDER_SIZE);
+        let start =
+            ((min + max) / 2 - size / 2).clamp(i32::from(i16::MIN), i32::from(i16::MAX) - size + 1);
+        start as i16..=(start + size - 1) as i16
+    };
+    let xs = axis(x_min, x_max);
+    let ys = axis(y_min, y_max);
+
+    // The stones placed in the previous move are in uppercase.
+    let prev: Vec<Point> = match record.prev_move() {
+        Some(Move::Place(p1, p2)) => iter::once(p1).chain(p2).collect(),
+        _ => vec![],
+    };
+
+    print!("      ");
+    for x in xs.clone() {
+        print!("{x:>3}");
+    }
+    println!();
+
+    for y in ys {
+        print!("{y:>6}");
+        for x in xs.clone() {
+            let p = Point::new(x, y);
+            let c = match record.stone_at(p) {
+                Some(Stone::Black) if prev.contains(&p) => 'X',
+                Some(Stone::Black) => 'x',
+                Some(Stone::White) if prev.contains(&p) => 'O',
+                Some(Stone::White) => 'o',
+                None => '.',
+            };
+            print!("{c:>3}");
+        }
+        println!();
+    }
+}
@@ -1,15 +1,21 @@
-use crate::{Confirm, WinClaim, ANALYZE_PREFIX};
+use crate::{
+    common_move_count, describe_result,
+    game_view::{Gestures, DEFAULT_PINCH_ZOOM_DIST, DEFAULT_SWIPE_UNDO_DIST},
+    Confirm, RequestLogEntry, WinClaim, ANALYZE_PREFIX, COMPARE_PREFIX, WATCH_SUFFIX,
+};
 use base64::prelude::*;
 use c6ol_core::{
-    game::{Move, Record, Stone},
-    protocol::Request,
+    game::{Record, Stone},
+    protocol::{GameId, Request, JOIN_CODE_TTL, MAX_FAREWELL_LEN, TRANSFER_CODE_TTL},
 };
 use leptos::{
-    either::{Either, EitherOf5},
-    html,
+    either::{Either, EitherOf7},
+    ev, html,
     prelude::*,
+    task::spawn_local,
 };
-use serde::{Deserialize, Serialize};
+use std::mem;
+use wasm_bindgen_futures::JsFuture;
 
 trait DialogImpl {
     type RetVal;
@@ -18,13 +24,36 @@ trait DialogImpl {
         None
     }
 
-    fn inner_view(self) -> impl IntoView;
+    fn inner_view(self, ret: Ret<Self::RetVal>) -> impl IntoView;
 }
 
-macro_rules! ret {
-    ($($val:tt)+) => {
-        ron::to_string(&Self::RetVal::$($val)+).unwrap()
-    };
+/// Handle for setting the return value of a dialog, which is passed
+/// to the return handler when the dialog closes.
+///
+/// A dialog closed without its return value set returns the default one.
+struct Ret<T> {
+    slot: StoredValue<Option<RetVal>, LocalStorage>,
+    wrap: fn(T) -> RetVal,
+}
+
+impl<T> Clone for Ret<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Ret<T> {}
+
+impl<T: Clone + 'static> Ret<T> {
+    /// Sets the return value.
+    fn set(self, val: T) {
+        self.slot.set_value(Some((self.wrap)(val)));
+    }
+
+    /// Returns a click handler for a submit button that sets the return value.
+    fn on(self, val: T) -> impl Fn(ev::MouseEvent) + 'static {
+        move |_| self.set(val.clone())
+    }
 }
 
 macro_rules! dialogs {
@@ -63,24 +92,36 @@ macro_rules! dialogs {
                         dialog_ref.get().unwrap().show_modal().unwrap();
                     });
 
-                    let (ret_val_from_str, class, inner_view) = match self {
+                    let slot = StoredValue::new_local(None::<RetVal>);
+
+                    let (default_ret_val, class, inner_view) = match self {
                         $(
                             Dialog::$name(dialog) => (
-                                (|s| RetVal::$name(ron::from_str(s).unwrap_or_default()))
-                                    as fn(&str) -> RetVal,
+                                (|| RetVal::$name(Default::default())) as fn() -> RetVal,
                                 dialog.class(),
-                                $either_type::$either_variant(dialog.inner_view()),
+                                $either_type::$either_variant(
+                                    dialog.inner_view(Ret { slot, wrap: RetVal::$name }),
+                                ),
                             ),
                         )+
                     };
 
                     let on_close = move |_| {
-                        let dialog = dialog_ref.get().unwrap();
-                        on_return(id, ret_val_from_str(&dialog.return_value()));
+                        let ret_val = slot.write_value().take().unwrap_or_else(default_ret_val);
+                        on_return(id, ret_val);
                     };
 
+                    // A submit button may have set the return value
+                    // before the form failed to validate.
+                    let on_cancel = move |_: ev::Event| slot.set_value(None);
+
                     view! {
-                        <dialog node_ref=dialog_ref class=class on:close=on_close>
+                        <dialog
+                            node_ref=dialog_ref
+                            class=class
+                            on:cancel=on_cancel
+                            on:close=on_close
+                        >
                             <form method="dialog">{inner_view}</form>
                         </dialog>
                     }
@@ -91,62 +132,196 @@ macro_rules! dialogs {
 }
 
 dialogs! {
-    EitherType = EitherOf5,
+    EitherType = EitherOf7,
     MainMenu => A,
     OnlineMenu => B,
     Join => C,
     GameMenu => D,
-    Confirm => E,
+    RequestLog => E,
+    Gestures => F,
+    Confirm => G,
 }
 
+/// An open dialog with a unique ID.
 #[derive(Clone)]
-pub struct MainMenuDialog;
+pub struct DialogEntry {
+    pub id: u32,
+    pub dialog: Dialog,
+}
+
+/// Stack of open dialogs, topmost last.
+///
+/// Only the topmost dialog is interactive, as each is shown modal.
+#[derive(Default)]
+pub struct DialogStack {
+    entries: Vec<DialogEntry>,
+    next_id: u32,
+}
+
+impl DialogStack {
+    /// Returns the open dialogs, topmost last.
+    pub fn entries(&self) -> &[DialogEntry] {
+        &self.entries
+    }
+
+    /// Tests if no dialog is open.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Opens a dialog on top of the others.
+    pub fn push(&mut self, dialog: Dialog) {
+        let id = self.next_id;
+        self.next_id = id.wrapping_add(1);
+        self.entries.push(DialogEntry { id, dialog });
+    }
+
+    /// Removes the dialog with the given ID after it is closed.
+    ///
+    /// This need not be the topmost one, because another dialog can be opened
+    /// before the `close` event of the previous one fires (see comments at
+    /// `on_hover` in `game_view.rs`). Returns `None` if it was already
+    /// removed, for example by `retain` or `clear`.
+    pub fn remove(&mut self, id: u32) -> Option<Dialog> {
+        let i = self.entries.iter().rposition(|entry| entry.id == id)?;
+        Some(self.entries.remove(i).dialog)
+    }
+
+    /// Removes the dialogs not satisfying the predicate,
+    /// returning whether any was removed.
+    pub fn retain(&mut self, mut f: impl FnMut(&Dialog) -> bool) -> bool {
+        let len = self.entries.len();
+        self.entries.retain(|entry| f(&entry.dialog));
+        self.entries.len() != len
+    }
+
+    /// Removes all dialogs.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[derive(Clone)]
+pub struct MainMenuDialog {
+    /// Recent online games played with our stones, most recent first.
+    pub recent_games: Vec<(String, Stone)>,
+}
 
-#[derive(Debug, Default, Deserialize, Serialize)]
+#[derive(Clone, Debug, Default)]
 pub enum MainMenuRetVal {
     #[default]
     Offline,
+    PassAndPlay,
+    #[cfg(feature = "online")]
     Online,
+    Rejoin(String),
 }
 
 impl DialogImpl for MainMenuDialog {
     type RetVal = MainMenuRetVal;
 
-    fn inner_view(self) -> impl IntoView {
+    fn inner_view(self, ret: Ret<Self::RetVal>) -> impl IntoView {
+        let recent_view = (!self.recent_games.is_empty()).then(|| {
+            view! {
+                <p class="title">"Recent Games"</p>
+                <div class="menu-btn-group">
+                    {self
+                        .recent_games
+                        .into_iter()
+                        .map(|(id, stone)| {
+                            let text = format!("{id} ({stone:?})");
+                            view! {
+                                <button on:click=ret.on(Self::RetVal::Rejoin(id))>{text}</button>
+                            }
+                        })
+                        .collect_view()}
+                </div>
+            }
+        });
+
         view! {
             <p class="title">"Main Menu"</p>
             <div class="menu-btn-group">
                 <button>"Play Offline"</button>
+                <button on:click=ret.on(Self::RetVal::PassAndPlay)>"Pass & Play"</button>
                 {
                     #[cfg(feature = "online")]
-                    view! { <button value=ret!(Online)>"Play Online"</button> }
+                    view! { <button on:click=ret.on(Self::RetVal::Online)>"Play Online"</button> }
                 }
             </div>
+            {recent_view}
         }
     }
 }
 
+/// Extracts a game ID, or the fragment of a game or record link, from text.
+fn game_id_from_text(text: &str) -> Option<String> {
+    let text = text.trim();
+    let id = match text.rsplit_once('#') {
+        Some((_, fragment))
+            if fragment.starts_with(ANALYZE_PREFIX) || fragment.starts_with(COMPARE_PREFIX) =>
+        {
+            return Some(fragment.into());
+        }
+        Some((_, fragment)) => fragment,
+        None => text,
+    };
+    let plain_id = id.strip_suffix(WATCH_SUFFIX).unwrap_or(id);
+    (plain_id.len() == mem::size_of::<GameId>()
+        && plain_id.bytes().all(|b| b.is_ascii_alphanumeric()))
+    .then(|| id.into())
+}
+
 #[derive(Clone)]
 pub struct OnlineMenuDialog;
 
-#[derive(Debug, Default, Deserialize, Serialize)]
+#[derive(Clone, Debug, Default)]
 pub enum OnlineMenuRetVal {
     #[default]
     Cancel,
-    Start(String),
+    /// Starts a game with the passcode, reserving White's seat
+    /// with the second one if it is non-empty.
+    Start(String, String),
     Join(String),
 }
 
 impl DialogImpl for OnlineMenuDialog {
     type RetVal = OnlineMenuRetVal;
 
-    fn inner_view(self) -> impl IntoView {
+    fn inner_view(self, ret: Ret<Self::RetVal>) -> impl IntoView {
         let start_checked = RwSignal::new(true);
         let passcode = RwSignal::new(String::new());
+        let passcode_white = RwSignal::new(String::new());
         let game_id = RwSignal::new(String::new());
 
+        // Offer to join the game whose ID or link is on the clipboard (if any).
+        let pasted = RwSignal::new(None::<String>);
+        Effect::new(move || {
+            spawn_local(async move {
+                let promise = window().navigator().clipboard().read_text();
+                // Reading fails if the user denies the permission.
+                if let Ok(text) = JsFuture::from(promise).await {
+                    // The dialog may have been closed meanwhile.
+                    _ = pasted.try_set(text.as_string().as_deref().and_then(game_id_from_text));
+                }
+            });
+        });
+
         view! {
             <p class="title">"Play Online"</p>
+            {move || {
+                pasted
+                    .get()
+                    .map(|id| {
+                        view! {
+                            <p>
+                                <button formnovalidate on:click=ret.on(Self::RetVal::Join(id))>
+                                    "Paste & Join"
+                                </button>
+                            </p>
+                        }
+                    })
+            }}
             <div class="radio-group">
                 <input
                     type="radio"
@@ -177,6 +352,15 @@ impl DialogImpl for OnlineMenuDialog {
                                 placeholder="Yours, not shared"
                                 bind:value=passcode
                             />
+                            <br />
+                            <label for="passcode-white">"White's: "</label>
+                            <input
+                                type="text"
+                                id="passcode-white"
+                                autocomplete="off"
+                                placeholder="Optional, reserves"
+                                bind:value=passcode_white
+                            />
                         },
                     )
                 } else {
@@ -187,9 +371,9 @@ impl DialogImpl for OnlineMenuDialog {
                                 type="text"
                                 id="game-id"
                                 required
-                                pattern="[0-9A-Za-z]{10}"
+                                pattern="[0-9A-Za-z]{10}|[0-9]{6}"
                                 autocomplete="on"
-                                placeholder="10 alphanumerics"
+                                placeholder="Or 6-digit join code"
                                 bind:value=game_id
                             />
                         },
@@ -197,14 +381,20 @@ impl DialogImpl for OnlineMenuDialog {
                 }
             }}
             <div class="btn-group reversed">
-                <button value=move || {
-                    if start_checked.get() {
-                        ret!(Start(passcode.get()))
-                    } else {
-                        ret!(Join(game_id.get()))
-                    }
+                <button on:click=move |_| {
+                    ret.set(
+                        if start_checked.get() {
+                            Self::RetVal::Start(passcode.get(), passcode_white.get())
+                        } else {
+                            Self::RetVal::Join(game_id.get())
+                        },
+                    );
                 }>{move || if start_checked.get() { "Start" } else { "Join" }}</button>
-                <button formnovalidate>"Cancel"</button>
+                // Set explicitly to override a return value set
+                // by the other button before the form failed to validate.
+                <button formnovalidate on:click=ret.on(Self::RetVal::Cancel)>
+                    "Cancel"
+                </button>
             </div>
         }
     }
@@ -213,7 +403,7 @@ impl DialogImpl for OnlineMenuDialog {
 #[derive(Clone)]
 pub struct JoinDialog;
 
-#[derive(Debug, Default, Deserialize, Serialize)]
+#[derive(Clone, Debug, Default)]
 pub enum JoinRetVal {
     #[default]
     ViewOnly,
@@ -223,7 +413,7 @@ pub enum JoinRetVal {
 impl DialogImpl for JoinDialog {
     type RetVal = JoinRetVal;
 
-    fn inner_view(self) -> impl IntoView {
+    fn inner_view(self, ret: Ret<Self::RetVal>) -> impl IntoView {
         let passcode = RwSignal::new(String::new());
 
         view! {
@@ -238,8 +428,12 @@ impl DialogImpl for JoinDialog {
                 bind:value=passcode
             />
             <div class="btn-group reversed">
-                <button value=move || ret!(Join(passcode.get()))>"Join"</button>
-                <button formnovalidate>"View Only"</button>
+                <button on:click=move |_| {
+                    ret.set(Self::RetVal::Join(passcode.get()));
+                }>"Join"</button>
+                <button formnovalidate on:click=ret.on(Self::RetVal::ViewOnly)>
+                    "View Only"
+                </button>
             </div>
         }
     }
@@ -253,9 +447,19 @@ pub struct GameMenuDialog {
     pub record: ReadSignal<Record>,
     pub win_claim: ReadSignal<Option<WinClaim>>,
     pub requests: ReadSignal<[Option<Stone>; Request::VALUES.len()]>,
+    pub heatmap: ReadSignal<bool>,
+    pub setup: ReadSignal<bool>,
+    pub split: ReadSignal<bool>,
+    pub sync_split: ReadSignal<bool>,
+    pub follow_live: ReadSignal<bool>,
+    pub share_cursor: ReadSignal<bool>,
+    pub show_shared_cursors: ReadSignal<bool>,
+    pub compare_with: ReadSignal<Option<Record>>,
+    pub double_tap: ReadSignal<bool>,
+    pub has_request_log: bool,
 }
 
-#[derive(Debug, Default, Deserialize, Serialize)]
+#[derive(Clone, Debug, Default)]
 pub enum GameMenuRetVal {
     #[default]
     Resume,
@@ -269,6 +473,21 @@ pub enum GameMenuRetVal {
     Resign,
     Submit,
     Draw,
+    Rotate,
+    Flip,
+    Heatmap,
+    Setup,
+    Split,
+    SyncSplit,
+    FollowLive,
+    ShareCursor,
+    ShowCursors,
+    DoubleTap,
+    Gestures,
+    JoinCode,
+    Transfer,
+    Requests,
+    ExportGif,
 }
 
 impl DialogImpl for GameMenuDialog {
@@ -278,7 +497,7 @@ impl DialogImpl for GameMenuDialog {
         Some("game-menu")
     }
 
-    fn inner_view(self) -> impl IntoView {
+    fn inner_view(self, ret: Ret<Self::RetVal>) -> impl IntoView {
         let Self {
             game_id,
             stone,
@@ -286,18 +505,39 @@ impl DialogImpl for GameMenuDialog {
             record,
             win_claim,
             requests,
+            heatmap,
+            setup,
+            split,
+            sync_split,
+            follow_live,
+            share_cursor,
+            show_shared_cursors,
+            compare_with,
+            double_tap,
+            has_request_log,
         } = self;
 
+        let comparing = game_id.starts_with(COMPARE_PREFIX);
+        let analyzing = game_id.starts_with(ANALYZE_PREFIX) || comparing;
+
         let info_view = view! {
             {if game_id == "local" {
                 Either::Left("Offline")
-            } else if game_id.starts_with(ANALYZE_PREFIX) {
+            } else if game_id == "pass" {
+                Either::Left("Pass & Play")
+            } else if comparing {
+                Either::Left("Comparing")
+            } else if analyzing {
                 Either::Left("Analyzing")
             } else {
-                let href = format!("#{game_id}");
+                let id = game_id.strip_suffix(WATCH_SUFFIX).unwrap_or(&game_id).to_owned();
+                let href = format!("#{id}");
+                let watch_href = format!("#{id}{WATCH_SUFFIX}");
                 Either::Right(
                     view! {
-                        <a href=href>{game_id}</a>
+                        <a href=href>{id}</a>
+                        " "
+                        <a href=watch_href>"Watch"</a>
                         <br />
                         {if let Some(stone) = stone {
                             format!("Playing {stone:?}")
@@ -313,22 +553,32 @@ impl DialogImpl for GameMenuDialog {
                 if let Some(stone) = record.turn() {
                     return format!("{stone:?} to Play");
                 }
-                match record.prev_move().unwrap() {
-                    Move::Draw => "Game Drawn".into(),
-                    Move::Resign(stone) => format!("{stone:?} Resigned"),
-                    Move::Win(p, _) => {
-                        let stone = record.stone_at(p).unwrap();
-                        format!("{stone:?} Won")
-                    }
-                    _ => unreachable!(),
-                }
+                describe_result(&record).unwrap()
+            }}
+            {move || {
+                let other = compare_with.read();
+                let record = record.read();
+                let other = other.as_ref()?;
+                let common = common_move_count(&record, other);
+                Some(
+                    view! {
+                        <br />
+                        {if common == record.moves().len() && common == other.moves().len() {
+                            "Identical".into()
+                        } else {
+                            format!("Diverging at Move {}", common + 1)
+                        }}
+                    },
+                )
             }}
             <br />
+            // Leaving an online game would disconnect from it, so open a new tab.
+            // Otherwise, going back in the history returns to the game.
             <a
-                target="_blank"
+                target=if online { "_blank" } else { "_self" }
                 href=move || {
                     let mut buf = vec![];
-                    record.read().encode(&mut buf, false);
+                    record.read().encode_with_checksum(&mut buf, false);
                     format!("#{ANALYZE_PREFIX}{}", BASE64_STANDARD.encode(buf))
                 }
             >
@@ -337,7 +587,26 @@ impl DialogImpl for GameMenuDialog {
         };
 
         let join_btn_or_ctrl_view = if online && stone.is_none() {
-            Either::Left(view! { <button value=ret!(Join)>"Join"</button> })
+            let no_past = move || !record.read().has_past();
+            let no_future = move || !record.read().has_future();
+
+            Either::Left(view! {
+                <button on:click=ret.on(Self::RetVal::Join)>"Join"</button>
+                <div class="btn-group">
+                    <button on:click=ret.on(Self::RetVal::Undo) disabled=no_past>
+                        "Undo"
+                    </button>
+                    <button on:click=ret.on(Self::RetVal::Redo) disabled=no_future>
+                        "Redo"
+                    </button>
+                </div>
+                <button
+                    class:pushed=move || follow_live.get()
+                    on:click=ret.on(Self::RetVal::FollowLive)
+                >
+                    "Follow Live"
+                </button>
+            })
         } else {
             let alt_pushed = RwSignal::new(false);
 
@@ -380,7 +649,7 @@ impl DialogImpl for GameMenuDialog {
                     <div class="btn-group">
                         {alt_btn(false)}
                         <button
-                            value=ret!(Undo)
+                            on:click=ret.on(Self::RetVal::Undo)
                             disabled=move || no_past() || who_requested(Retract) == User
                             class:prominent=move || who_requested(Retract) == Opponent
                         >
@@ -389,7 +658,7 @@ impl DialogImpl for GameMenuDialog {
                         {(!online)
                             .then(|| {
                                 view! {
-                                    <button value=ret!(Redo) disabled=no_future>
+                                    <button on:click=ret.on(Self::RetVal::Redo) disabled=no_future>
                                         "Redo"
                                     </button>
                                 }
@@ -398,13 +667,13 @@ impl DialogImpl for GameMenuDialog {
                     <div class="btn-group">
                         <button
                             class:pushed=move || win_claim.read().is_some()
-                            value=ret!(ClaimWin)
+                            on:click=ret.on(Self::RetVal::ClaimWin)
                             disabled=ended
                         >
                             "Claim Win"
                         </button>
                         <button
-                            value=ret!(Submit)
+                            on:click=ret.on(Self::RetVal::Submit)
                             disabled=move || {
                                 ended()
                                     || (record.read().turn() != stone
@@ -422,7 +691,7 @@ impl DialogImpl for GameMenuDialog {
                     <div class="btn-group">
                         {alt_btn(true)}
                         <button
-                            value=ret!(Home)
+                            on:click=ret.on(Self::RetVal::Home)
                             disabled=move || no_past() || who_requested(Reset) == User
                             class:prominent=move || who_requested(Reset) == Opponent
                         >
@@ -431,7 +700,7 @@ impl DialogImpl for GameMenuDialog {
                         {(!online)
                             .then(|| {
                                 view! {
-                                    <button value=ret!(End) disabled=no_future>
+                                    <button on:click=ret.on(Self::RetVal::End) disabled=no_future>
                                         "End"
                                     </button>
                                 }
@@ -439,13 +708,13 @@ impl DialogImpl for GameMenuDialog {
                     </div>
                     <div class="btn-group">
                         <button
-                            value=ret!(Draw)
+                            on:click=ret.on(Self::RetVal::Draw)
                             disabled=move || ended() || who_requested(Draw) == User
                             class:prominent=move || who_requested(Draw) == Opponent
                         >
                             "Draw"
                         </button>
-                        <button value=ret!(Resign) disabled=ended>
+                        <button on:click=ret.on(Self::RetVal::Resign) disabled=ended>
                             "Resign"
                         </button>
                     </div>
@@ -465,22 +734,243 @@ impl DialogImpl for GameMenuDialog {
             <p class="title">"Game Menu"</p>
             <p style="font-family: monospace;">{info_view}</p>
             <div class="menu-btn-group">
-                <button value=ret!(MainMenu)>"Main Menu"</button>
+                <button on:click=ret.on(Self::RetVal::MainMenu)>"Main Menu"</button>
                 {join_btn_or_ctrl_view}
+                <div class="btn-group">
+                    <button on:click=ret.on(Self::RetVal::Rotate)>"Rotate"</button>
+                    <button on:click=ret.on(Self::RetVal::Flip)>"Flip"</button>
+                    {analyzing
+                        .then(|| {
+                            view! {
+                                <button
+                                    class:pushed=move || heatmap.get()
+                                    on:click=ret.on(Self::RetVal::Heatmap)
+                                >
+                                    "Heatmap"
+                                </button>
+                                <button
+                                    class:pushed=move || setup.get()
+                                    on:click=ret.on(Self::RetVal::Setup)
+                                >
+                                    "Setup"
+                                </button>
+                            }
+                        })}
+                </div>
+                {comparing
+                    .then(|| {
+                        view! {
+                            <div class="btn-group">
+                                <button
+                                    class:pushed=move || split.get()
+                                    on:click=ret.on(Self::RetVal::Split)
+                                >
+                                    "Split View"
+                                </button>
+                                <button
+                                    class:pushed=move || sync_split.get()
+                                    disabled=move || !split.get()
+                                    on:click=ret.on(Self::RetVal::SyncSplit)
+                                >
+                                    "Sync"
+                                </button>
+                            </div>
+                        }
+                    })}
+                {online
+                    .then(|| {
+                        view! {
+                            <div class="btn-group">
+                                {stone
+                                    .is_some()
+                                    .then(|| {
+                                        view! {
+                                            <button
+                                                class:pushed=move || share_cursor.get()
+                                                on:click=ret.on(Self::RetVal::ShareCursor)
+                                            >
+                                                "Share Cursor"
+                                            </button>
+                                        }
+                                    })}
+                                <button
+                                    class:pushed=move || show_shared_cursors.get()
+                                    on:click=ret.on(Self::RetVal::ShowCursors)
+                                >
+                                    "Show Cursors"
+                                </button>
+                            </div>
+                        }
+                    })}
+                <div class="btn-group">
+                    <button
+                        class:pushed=move || double_tap.get()
+                        on:click=ret.on(Self::RetVal::DoubleTap)
+                    >
+                        "Double Tap"
+                    </button>
+                    <button on:click=ret.on(Self::RetVal::Gestures)>"Gestures"</button>
+                </div>
+                {(online && stone.is_some())
+                    .then(|| {
+                        view! {
+                            <div class="btn-group">
+                                <button on:click=ret.on(Self::RetVal::JoinCode)>"Join Code"</button>
+                                <button on:click=ret.on(Self::RetVal::Transfer)>"Transfer"</button>
+                            </div>
+                        }
+                    })}
+                {has_request_log
+                    .then(|| {
+                        view! {
+                            <button on:click=ret.on(Self::RetVal::Requests)>"Requests"</button>
+                        }
+                    })}
+                <button
+                    on:click=ret.on(Self::RetVal::ExportGif)
+                    disabled=move || record.read().move_index() == 0
+                >
+                    "Export GIF"
+                </button>
                 <button autofocus>"Resume"</button>
             </div>
         }
     }
 }
 
+#[derive(Clone)]
+pub struct RequestLogDialog {
+    pub log: ReadSignal<Vec<RequestLogEntry>>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub enum RequestLogRetVal {
+    #[default]
+    Close,
+    Back,
+}
+
+impl DialogImpl for RequestLogDialog {
+    type RetVal = RequestLogRetVal;
+
+    fn inner_view(self, ret: Ret<Self::RetVal>) -> impl IntoView {
+        let entry_view = |entry: RequestLogEntry| {
+            let RequestLogEntry {
+                stone,
+                req,
+                outcome,
+                ..
+            } = entry;
+            let outcome = match outcome {
+                Some(outcome) => format!("{outcome:?}"),
+                None => "Pending".into(),
+            };
+            view! {
+                {format!("{stone:?}: {req:?} ({outcome})")}
+                <br />
+            }
+        };
+
+        view! {
+            <p class="title">"Requests"</p>
+            <p>{move || self.log.get().into_iter().map(entry_view).collect_view()}</p>
+            <div class="btn-group reversed">
+                <button autofocus>"Close"</button>
+                <button on:click=ret.on(Self::RetVal::Back)>"Back"</button>
+            </div>
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct GesturesDialog(pub Gestures);
+
+#[derive(Clone, Debug, Default)]
+pub enum GesturesRetVal {
+    #[default]
+    Back,
+    Save(Gestures),
+}
+
+impl DialogImpl for GesturesDialog {
+    type RetVal = GesturesRetVal;
+
+    fn inner_view(self, ret: Ret<Self::RetVal>) -> impl IntoView {
+        let Gestures {
+            pinch_zoom_dist,
+            swipe_undo_dist,
+        } = self.0;
+        let pinch_zoom_dist = RwSignal::new(pinch_zoom_dist.to_string());
+        let swipe_undo = RwSignal::new(swipe_undo_dist.is_some());
+        let swipe_undo_dist = RwSignal::new(
+            swipe_undo_dist
+                .unwrap_or(DEFAULT_SWIPE_UNDO_DIST)
+                .to_string(),
+        );
+
+        // Falls back to the default for a value that fails to parse.
+        let gestures = move || Gestures {
+            pinch_zoom_dist: pinch_zoom_dist
+                .read()
+                .parse()
+                .unwrap_or(DEFAULT_PINCH_ZOOM_DIST),
+            swipe_undo_dist: swipe_undo.get().then(|| {
+                swipe_undo_dist
+                    .read()
+                    .parse()
+                    .unwrap_or(DEFAULT_SWIPE_UNDO_DIST)
+            }),
+        };
+
+        view! {
+            <p class="title">"Gestures"</p>
+            <p>"Distances are in centimeters."</p>
+            <p>
+                <label for="pinch-zoom-dist">"Pinch to zoom: "</label>
+                <input
+                    type="number"
+                    id="pinch-zoom-dist"
+                    required
+                    min="0.5"
+                    max="10"
+                    step="0.5"
+                    bind:value=pinch_zoom_dist
+                />
+            </p>
+            <p>
+                <input type="checkbox" id="swipe-undo" bind:checked=swipe_undo />
+                <label for="swipe-undo">"Swipe with three fingers to undo: "</label>
+                <input
+                    type="number"
+                    id="swipe-undo-dist"
+                    required
+                    min="0.5"
+                    max="10"
+                    step="0.5"
+                    disabled=move || !swipe_undo.get()
+                    bind:value=swipe_undo_dist
+                />
+            </p>
+            <div class="btn-group reversed">
+                <button on:click=move |_| ret.set(Self::RetVal::Save(gestures()))>"Save"</button>
+                <button formnovalidate on:click=ret.on(Self::RetVal::Back)>
+                    "Back"
+                </button>
+            </div>
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct ConfirmDialog(pub Confirm);
 
-#[derive(Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub enum ConfirmRetVal {
     #[default]
     Cancel,
     Confirm,
+    /// Confirms resigning, with a farewell message.
+    Resign(String),
 }
 
 impl DialogImpl for ConfirmDialog {
@@ -488,28 +978,42 @@ impl DialogImpl for ConfirmDialog {
 
     fn class(&self) -> Option<&'static str> {
         match self.0 {
-            Confirm::ConnClosed(_) | Confirm::Error(_) => None,
+            Confirm::Claim(_, _, _, true) => Some("warning"),
+            Confirm::HandOver(_)
+            | Confirm::TransferCode(_)
+            | Confirm::JoinCode(_)
+            | Confirm::GameOver(..)
+            | Confirm::ConnClosed(_)
+            | Confirm::Error(_) => None,
             _ => Some("transparent"),
         }
     }
 
-    fn inner_view(self) -> impl IntoView {
+    fn inner_view(self, ret: Ret<Self::RetVal>) -> impl IntoView {
         let mut title = None;
         let mut confirm = "Confirm";
         let mut cancel = Some("Cancel");
+        let hand_over;
+        let game_over;
+        let transfer_code;
+        let join_code;
+        let mut farewell = None;
 
         let message = match &self.0 {
             Confirm::MainMenu => "Back to main menu?",
             Confirm::Submit(_, None) => "Place one stone?",
             Confirm::Submit(_, Some(_)) => "Place two stones?",
-            Confirm::Pass(None) => "Place no stone and pass?",
-            Confirm::Pass(Some(_)) => "Place one stone and pass?",
+            Confirm::Pass => "Place no stone and pass?",
             Confirm::BeginClaim => {
                 (confirm, cancel) = ("Noted", None);
                 "To claim a win, click on one end of a six-in-a-row and then on the other end."
             }
+            Confirm::Claim(_, _, _, true) => {
+                title = Some("Warning");
+                confirm = "Claim Anyway";
+                "This row belongs to the opponent. Claiming it makes you lose!"
+            }
             Confirm::Claim(tentatives, ..) => match tentatives.len() {
-                // TODO: Inform the user if they're claiming a win for the opponent?
                 0 => "Claim a win?",
                 1 => "Place one stone and claim a win?",
                 _ => "Place two stones and claim a win?",
@@ -527,7 +1031,41 @@ impl DialogImpl for ConfirmDialog {
                     Request::Reset => "The opponent requests to reset the game.",
                 }
             }
-            Confirm::Resign => "Resign the game?",
+            Confirm::Resign => {
+                let farewell = RwSignal::new(String::new());
+                return Either::Left(view! {
+                    <p>"Resign the game?"</p>
+                    <input
+                        type="text"
+                        id="farewell"
+                        maxlength=MAX_FAREWELL_LEN
+                        placeholder="Farewell message (optional)"
+                        bind:value=farewell
+                    />
+                    <div class="btn-group">
+                        <button>"Cancel"</button>
+                        <button on:click=move |_| {
+                            ret.set(Self::RetVal::Resign(farewell.get()));
+                        }>"Resign"</button>
+                    </div>
+                });
+            }
+            Confirm::HandOver(stone) => {
+                title = Some("Pass & Play");
+                (confirm, cancel) = ("Ready", None);
+                hand_over = format!("Hand the device to {stone:?}.");
+                &hand_over
+            }
+            Confirm::GameOver(result, stone, message) => {
+                title = Some("Game Over");
+                (confirm, cancel) = ("Analyze", Some("Close"));
+                game_over = match stone {
+                    Some(stone) => format!("{result}. You played {stone:?}."),
+                    None => format!("{result}."),
+                };
+                farewell.clone_from(message);
+                &game_over
+            }
             Confirm::ConnClosed(reason) => {
                 title = Some("Connection Closed");
                 (confirm, cancel) = ("Retry", Some("Menu"));
@@ -538,15 +1076,36 @@ impl DialogImpl for ConfirmDialog {
                 (confirm, cancel) = ("Main Menu", None);
                 message
             }
+            Confirm::TransferCode(code) => {
+                title = Some("Transfer Seat");
+                (confirm, cancel) = ("Done", None);
+                transfer_code = format!(
+                    "Join this game on another device and enter {code} as the passcode \
+                     within {} minutes. The code works only once.",
+                    TRANSFER_CODE_TTL.as_secs() / 60
+                );
+                &transfer_code
+            }
+            Confirm::JoinCode(code) => {
+                title = Some("Join Code");
+                (confirm, cancel) = ("Done", None);
+                join_code = format!(
+                    "To join this game, enter {code} as the game ID within {} minutes. \
+                     The code works only once.",
+                    JOIN_CODE_TTL.as_secs() / 60
+                );
+                &join_code
+            }
         };
 
-        view! {
+        Either::Right(view! {
             {title.map(|s| view! { <p class="title">{s}</p> })}
             <p>{message.to_owned()}</p>
+            {farewell.map(|s| view! { <p>{s}</p> })}
             <div class="btn-group">
                 {cancel.map(|s| view! { <button>{s}</button> })}
-                <button value=ret!(Confirm)>{confirm}</button>
+                <button on:click=ret.on(Self::RetVal::Confirm)>{confirm}</button>
             </div>
-        }
+        })
     }
 }
@@ -1,20 +1,28 @@
-use crate::{console_log, Event, WinClaim};
+use crate::{common_move_count, console_log, Event, WinClaim};
 use c6ol_core::game::{Direction, Move, Point, Record, Stone};
 use leptos::{ev, html, prelude::*};
+use serde::{Deserialize, Serialize};
 use std::{
     collections::{HashMap, HashSet},
     f64, iter,
+    time::Duration,
 };
 use tinyvec::ArrayVec;
 use web_sys::{
-    js_sys::Array, wasm_bindgen::prelude::*, CanvasRenderingContext2d, HtmlCanvasElement,
-    KeyboardEvent, MouseEvent, PointerEvent, ResizeObserver, WheelEvent,
+    js_sys::Array, wasm_bindgen::prelude::*, CanvasRenderingContext2d, Gamepad, GamepadButton,
+    HtmlCanvasElement, HtmlDialogElement, HtmlElement, KeyboardEvent, MouseEvent, PointerEvent,
+    ResizeObserver, WheelEvent,
 };
 
 const BOARD_COLOR: &str = "#ffcc66";
 const CURSOR_COLOR_ACTIVE: &str = "firebrick";
 const CURSOR_COLOR_INACTIVE: &str = "grey";
 const WIN_RING_COLOR: &str = "seagreen";
+const HEATMAP_COLOR: &str = "orangered";
+const DIVERGENCE_RING_COLOR: &str = "royalblue";
+const PROVISIONAL_RING_COLOR: &str = "grey";
+const OFF_VIEW_ARROW_COLOR: &str = "firebrick";
+const ANNOTATION_COLOR: &str = "darkviolet";
 
 const DEFAULT_VIEW_SIZE: i16 = 15;
 
@@ -32,13 +40,104 @@ const CURSOR_SIDE_RATIO: f64 = 4.25;
 const CURSOR_OFFSET_RATIO: f64 = CURSOR_SIDE_RATIO * 2.0;
 
 const PHANTOM_MOVE_OPACITY: f64 = 0.5;
+const CLAIM_HINT_OPACITY: f64 = 0.4;
+const ANNOTATION_OPACITY: f64 = 0.75;
+
+const HEATMAP_RADIUS: i16 = 2;
+const HEATMAP_SATURATION: u32 = 12;
+const HEATMAP_MAX_OPACITY: f64 = 0.5;
 
 const MOVE_TEXT_WIDTH_RATIO: f64 = 2.0;
 const MOVE_TEXT_BORDER_RATIO: f64 = 100.0;
 const MOVE_TEXT_OPACITY: f64 = 0.5;
 
-const DIST_FOR_PINCH_ZOOM: f64 = 2.0 * 96.0 / 2.54; // 2cm
-const DIST_FOR_SWIPE_RETRACT: f64 = 4.0 * 96.0 / 2.54; // 4cm
+/// Number of CSS pixels in a centimeter.
+const PX_PER_CM: f64 = 96.0 / 2.54;
+pub const DEFAULT_PINCH_ZOOM_DIST: f64 = 2.0;
+pub const DEFAULT_SWIPE_UNDO_DIST: f64 = 4.0;
+
+/// Offsets of the up, left, down and right directions in the view.
+const DIRECTION_OFFSETS: [(i16, i16); 4] = [(0, -1), (-1, 0), (0, 1), (1, 0)];
+
+const GAMEPAD_POLL_INTERVAL: Duration = Duration::from_millis(50);
+const GAMEPAD_STICK_THRESHOLD: f64 = 0.5;
+/// Number of polls before a held direction starts repeating.
+const GAMEPAD_REPEAT_DELAY: u32 = 6;
+/// Number of polls between repeats of a held direction.
+const GAMEPAD_REPEAT_INTERVAL: u32 = 2;
+
+// Button indices in the standard gamepad mapping.
+// See: https://w3c.github.io/gamepad/#remapping
+const GAMEPAD_A: usize = 0;
+const GAMEPAD_B: usize = 1;
+const GAMEPAD_X: usize = 2;
+const GAMEPAD_LB: usize = 4;
+const GAMEPAD_RB: usize = 5;
+const GAMEPAD_START: usize = 9;
+const GAMEPAD_UP: usize = 12;
+const GAMEPAD_DOWN: usize = 13;
+const GAMEPAD_LEFT: usize = 14;
+const GAMEPAD_RIGHT: usize = 15;
+const GAMEPAD_BUTTON_COUNT: usize = 16;
+
+/// Thresholds of touch gestures on the view, in centimeters.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Gestures {
+    /// Change in distance between two pointers for each step of pinch zoom.
+    pub pinch_zoom_dist: f64,
+    /// Distance that three pointers must move to undo the previous move,
+    /// or `None` if swiping to undo is disabled.
+    pub swipe_undo_dist: Option<f64>,
+}
+
+impl Default for Gestures {
+    fn default() -> Self {
+        Self {
+            pinch_zoom_dist: DEFAULT_PINCH_ZOOM_DIST,
+            swipe_undo_dist: Some(DEFAULT_SWIPE_UNDO_DIST),
+        }
+    }
+}
+
+/// Shape of a mark placed on the board.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Mark {
+    Circle,
+    Triangle,
+}
+
+/// Arrows and marks drawn on the board for explaining plans.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Annotations {
+    /// Arrows as pairs of board positions, from tail to head.
+    pub arrows: Vec<(Point, Point)>,
+    /// Marks at board positions.
+    pub marks: Vec<(Point, Mark)>,
+}
+
+impl Annotations {
+    /// Adds an arrow, or removes it if already present.
+    fn toggle_arrow(&mut self, start: Point, end: Point) {
+        if let Some(i) = self.arrows.iter().position(|&a| a == (start, end)) {
+            self.arrows.remove(i);
+        } else {
+            self.arrows.push((start, end));
+        }
+    }
+
+    /// Cycles the mark at a position, from none to circle to triangle.
+    fn cycle_mark(&mut self, p: Point) {
+        match self.marks.iter().position(|&(q, _)| q == p) {
+            Some(i) => match self.marks[i].1 {
+                Mark::Circle => self.marks[i].1 = Mark::Triangle,
+                Mark::Triangle => {
+                    self.marks.remove(i);
+                }
+            },
+            None => self.marks.push((p, Mark::Circle)),
+        }
+    }
+}
 
 /// Represents `pointerId`, `offsetX` and `offsetY` fields
 /// of a `PointerEvent` or `MouseEvent`.
@@ -103,6 +202,18 @@ enum PointerState {
     /// The view may be dragged by pointer or zoomed by wheel or keyboard
     /// only when the state is `Calm` or `Moved`.
     Moved,
+    /// Entered when the state is `Calm`, exactly one pointer is active,
+    /// and it is dragged from a stone of ours while claiming a win.
+    ///
+    /// The winning row is claimed from the stone to the current position,
+    /// and submitted when the pointer becomes inactive if it is valid.
+    ClaimDragged,
+    /// Entered when the state is `Calm`, exactly one pointer is active,
+    /// and it is pressed with Shift held while annotating.
+    ///
+    /// An arrow is drawn from the position pressed to the current position,
+    /// and toggled in the annotations when the pointer becomes inactive.
+    ArrowDragged,
     /// Entered when exactly one pointer is active,
     /// and a second pointer becomes active.
     Pinched,
@@ -125,6 +236,80 @@ struct State {
     last_hover_before_enabled: Option<PointerOffsets>,
     // See comments at `PointerState`.
     pointer_state: PointerState,
+    /// Position of the previous touch in double-tap mode, if to be confirmed.
+    last_tap: Option<Point>,
+}
+
+#[derive(Default)]
+struct GamepadState {
+    /// Bit set of buttons pressed at the last poll.
+    pressed: u32,
+    /// Number of polls each direction (see `DIRECTION_OFFSETS`) has been held for.
+    held_polls: [u32; 4],
+}
+
+/// Returns the first connected gamepad (if any).
+fn first_gamepad() -> Option<Gamepad> {
+    window()
+        .navigator()
+        .get_gamepads()
+        .ok()?
+        .iter()
+        .filter_map(|pad| pad.dyn_into::<Gamepad>().ok())
+        .find(Gamepad::connected)
+}
+
+/// Returns the topmost open dialog (if any).
+fn top_dialog() -> Option<HtmlDialogElement> {
+    let dialogs = document().query_selector_all("dialog[open]").ok()?;
+    dialogs
+        .get(dialogs.length().checked_sub(1)?)?
+        .dyn_into()
+        .ok()
+}
+
+/// Clicks the element with focus (if any).
+fn click_focused_element() {
+    if let Some(elem) = document()
+        .active_element()
+        .and_then(|elem| elem.dyn_into::<HtmlElement>().ok())
+    {
+        elem.click();
+    }
+}
+
+/// Closes the topmost open dialog (if any) without a return value.
+fn close_top_dialog() {
+    if let Some(dialog) = top_dialog() {
+        dialog.close();
+    }
+}
+
+/// Moves the focus to the next (or previous) enabled button
+/// in the topmost open dialog, wrapping around.
+fn move_dialog_focus(forward: bool) {
+    let Some(buttons) =
+        top_dialog().and_then(|dialog| dialog.query_selector_all("button:not([disabled])").ok())
+    else {
+        return;
+    };
+    let buttons: Vec<HtmlElement> = (0..buttons.length())
+        .filter_map(|i| buttons.get(i)?.dyn_into().ok())
+        .collect();
+    if buttons.is_empty() {
+        return;
+    }
+
+    let active = document().active_element();
+    let next = match buttons
+        .iter()
+        .position(|b| Some(b.unchecked_ref()) == active.as_ref())
+    {
+        Some(i) if forward => (i + 1) % buttons.len(),
+        Some(i) => (i + buttons.len() - 1) % buttons.len(),
+        None => 0,
+    };
+    _ = buttons[next].focus();
 }
 
 enum ClampTo {
@@ -132,10 +317,71 @@ enum ClampTo {
     InsideAndBorder,
 }
 
+/// Orientation of the view relative to the board.
+#[derive(Clone, Copy, Default, Eq, PartialEq)]
+pub struct Orientation {
+    /// Number of clockwise quarter turns, applied after flipping.
+    rotation: u8,
+    /// Whether the board is mirrored horizontally.
+    flipped: bool,
+}
+
+impl Orientation {
+    /// Returns the orientation rotated clockwise by a quarter turn.
+    pub fn rotated(self) -> Self {
+        Self {
+            rotation: (self.rotation + 1) % 4,
+            ..self
+        }
+    }
+
+    /// Returns the orientation rotated counterclockwise by a quarter turn.
+    pub fn rotated_back(self) -> Self {
+        Self {
+            rotation: (self.rotation + 3) % 4,
+            ..self
+        }
+    }
+
+    /// Returns the orientation mirrored horizontally (as seen in the view).
+    pub fn flipped(self) -> Self {
+        Self {
+            // Flipping is applied first, so mirroring the rotated board
+            // horizontally is the same as flipping and rotating backwards.
+            rotation: (4 - self.rotation) % 4,
+            flipped: !self.flipped,
+        }
+    }
+
+    /// Maps an offset on the board to one in the view.
+    fn apply(self, x: i16, y: i16) -> (i16, i16) {
+        let x = if self.flipped { -x } else { x };
+        match self.rotation {
+            0 => (x, y),
+            1 => (-y, x),
+            2 => (-x, -y),
+            _ => (y, -x),
+        }
+    }
+
+    /// Maps an offset in the view to one on the board (undoes `apply`).
+    fn unapply(self, x: i16, y: i16) -> (i16, i16) {
+        let (x, y) = match self.rotation {
+            0 => (x, y),
+            1 => (y, -x),
+            2 => (-x, -y),
+            _ => (-y, x),
+        };
+        (if self.flipped { -x } else { x }, y)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
 struct Calc {
     view_size: i16,
     grid_size: f64,
     view_center: Point,
+    orientation: Orientation,
 }
 
 impl Calc {
@@ -153,9 +399,10 @@ impl Calc {
 
     /// Converts a view position to board position.
     fn view_to_board_pos(&self, p: Point) -> Point {
-        let x = p.x - self.view_size / 2 + self.view_center.x;
-        let y = p.y - self.view_size / 2 + self.view_center.y;
-        Point { x, y }
+        let (dx, dy) = self
+            .orientation
+            .unapply(p.x - self.view_size / 2, p.y - self.view_size / 2);
+        Point::new(dx + self.view_center.x, dy + self.view_center.y)
     }
 
     /// Converts a canvas position to board position, testing if it is out of view.
@@ -165,9 +412,10 @@ impl Calc {
     }
 
     fn board_to_view_pos_unclamped(&self, p: Point) -> (i16, i16) {
-        let x = p.x + self.view_size / 2 - self.view_center.x;
-        let y = p.y + self.view_size / 2 - self.view_center.y;
-        (x, y)
+        let (dx, dy) = self
+            .orientation
+            .apply(p.x - self.view_center.x, p.y - self.view_center.y);
+        (dx + self.view_size / 2, dy + self.view_size / 2)
     }
 
     /// Converts a board position to view position, returning `None` if out of view.
@@ -196,6 +444,106 @@ impl Calc {
     }
 }
 
+/// Returns a stone of the previous move that is out of view, along with
+/// the position on the border where an arrow points to it.
+///
+/// Moves made by `stone` are skipped, since we know where we played.
+fn off_view_move(record: &Record, stone: Option<Stone>, calc: &Calc) -> Option<(Point, Point)> {
+    let Some(Move::Place(p1, p2)) = record.prev_move() else {
+        return None;
+    };
+    if stone == Some(Record::turn_at(record.move_index() - 1)) {
+        return None;
+    }
+    iter::once(p1).chain(p2).find_map(|p| {
+        let (arrow, out) = calc.board_to_view_pos_clamped(p, ClampTo::InsideAndBorder);
+        out.then_some((p, arrow))
+    })
+}
+
+/// An offscreen canvas caching the stones drawn for a view.
+struct StoneLayer {
+    canvas: HtmlCanvasElement,
+    /// The canvas size, device pixel ratio and calculator the stones are drawn with.
+    view: Option<(f64, f64, Calc)>,
+    /// Moves whose stones are drawn.
+    moves: Vec<Move>,
+}
+
+impl StoneLayer {
+    fn new() -> Self {
+        Self {
+            canvas: document()
+                .create_element("canvas")
+                .unwrap()
+                .unchecked_into(),
+            view: None,
+            moves: vec![],
+        }
+    }
+
+    /// Brings the layer up to date with the moves in the past of `record`.
+    ///
+    /// Only the stones of new moves are drawn if the view is unchanged
+    /// and no drawn move is undone or replaced.
+    fn update(&mut self, size: f64, calc: Calc, record: &Record) {
+        let moves = &record.moves()[..record.move_index()];
+        let dpr = window().device_pixel_ratio();
+        let view = Some((size, dpr, calc));
+
+        let ctx = context_2d(self.canvas.clone());
+        if self.view != view || !moves.starts_with(&self.moves) {
+            // Resizing clears the canvas and resets the transform.
+            let physical_size = (size * dpr) as u32;
+            self.canvas.set_width(physical_size);
+            self.canvas.set_height(physical_size);
+            ctx.scale(dpr, dpr).unwrap();
+
+            self.view = view;
+            self.moves.clear();
+        }
+
+        let stone_radius = calc.grid_size / STONE_RADIUS_RATIO;
+        let draw_circle = |p: Point| {
+            let (x, y) = calc.view_to_canvas_pos(p);
+            ctx.begin_path();
+            ctx.arc(x, y, stone_radius, 0.0, f64::consts::TAU).unwrap();
+            ctx.fill();
+        };
+
+        // We project the out-of-view stones onto the view border,
+        // and stores the resulting positions in this set.
+        let mut out_pos = HashSet::new();
+
+        for (i, &mov) in moves.iter().enumerate().skip(self.moves.len()) {
+            let Move::Place(p1, p2) = mov else {
+                continue;
+            };
+            ctx.set_fill_style_str(match Record::turn_at(i) {
+                Stone::Black => "black",
+                Stone::White => "white",
+            });
+
+            for p in iter::once(p1).chain(p2) {
+                let (p, out) = calc.board_to_view_pos_clamped(p, ClampTo::InsideAndBorder);
+                if out {
+                    out_pos.insert(p);
+                    continue;
+                }
+                draw_circle(p);
+            }
+        }
+
+        // Draw the out-of-view stones on the view border.
+        ctx.set_fill_style_str("gray");
+        for p in out_pos {
+            draw_circle(p);
+        }
+
+        self.moves.extend_from_slice(&moves[self.moves.len()..]);
+    }
+}
+
 fn context_2d(canvas: HtmlCanvasElement) -> CanvasRenderingContext2d {
     canvas
         .get_context("2d")
@@ -228,10 +576,48 @@ pub fn GameView(
     #[prop(default = RwSignal::new(DEFAULT_VIEW_SIZE))]
     view_size: RwSignal<i16>,
     #[prop(optional)] view_center: RwSignal<Point>,
+    #[prop(optional)] orientation: RwSignal<Orientation>,
     #[prop(optional)] cursor_pos: RwSignal<Option<Point>>,
     #[prop(optional)] phantom_pos: RwSignal<Option<Point>>,
     #[prop(optional)] tentatives_pos: RwSignal<ArrayVec<[Point; 2]>>,
     #[prop(optional)] win_claim: RwSignal<Option<WinClaim>>,
+    /// Whether to shade positions by how contested they are.
+    #[prop(optional)]
+    heatmap: RwSignal<bool>,
+    /// Whether hitting a position cycles its stone instead of placing one.
+    #[prop(optional)]
+    setup: RwSignal<bool>,
+    /// Arrows and marks to draw on the board.
+    #[prop(optional)]
+    annotations: RwSignal<Annotations>,
+    /// Whether arrows can be drawn by Shift-dragging
+    /// and marks placed by Ctrl-clicking.
+    #[prop(optional, into)]
+    annotatable: Signal<bool>,
+    /// Another record to compare with.
+    ///
+    /// Stones placed since the records diverge are ringed, and those
+    /// of the other record are shown as phantom stones.
+    #[prop(optional)]
+    compare_with: RwSignal<Option<Record>>,
+    /// Cursor positions shared by the black and white players, respectively.
+    #[prop(optional)]
+    shared_cursors: RwSignal<[Option<Point>; 2]>,
+    /// Whether a touch only moves the cursor until repeated on the same position.
+    #[prop(optional)]
+    double_tap: RwSignal<bool>,
+    /// Number of moves at the end of the record not yet confirmed by the server.
+    ///
+    /// Stones placed by these moves are ringed.
+    #[prop(optional)]
+    provisional: RwSignal<usize>,
+    /// Thresholds of touch gestures.
+    #[prop(optional)]
+    gestures: RwSignal<Gestures>,
+    /// Whether to ignore keyboard and gamepad input,
+    /// so that it goes to another view shown beside this one.
+    #[prop(optional)]
+    passive: bool,
 ) -> impl IntoView {
     let disabled = Memo::new(move |_| disabled());
 
@@ -240,6 +626,11 @@ pub fn GameView(
 
     // Non-reactive state.
     let state = StoredValue::<State>::default();
+    let gamepad_state = StoredValue::<GamepadState>::default();
+    let stone_layer = StoredValue::new_local(StoneLayer::new());
+
+    // Arrow being drawn by Shift-dragging, if any.
+    let dragged_arrow = RwSignal::new(None::<(Point, Point)>);
 
     // Pixel size of the canvas.
     let canvas_size = RwSignal::new(0.0);
@@ -252,6 +643,7 @@ pub fn GameView(
         view_size: view_size.get(),
         grid_size: grid_size.get(),
         view_center: view_center.get(),
+        orientation: orientation.get(),
     };
 
     // Tests if it is our turn to play.
@@ -260,13 +652,28 @@ pub fn GameView(
         stone.is_some() && stone == record.read_untracked().turn()
     };
 
+    // Returns the direction from `p` to `end` if they are the endpoints of a
+    // winning row of `stone`, with the tentative stones placed.
+    let winning_row_dir = move |stone: Stone, p: Point, end: Point| {
+        let dir = Direction::from_unit_vec((end.x - p.x).signum(), (end.y - p.y).signum())?;
+        record
+            .write_untracked()
+            .with_temp_placements(stone, &tentatives_pos.get(), |record| {
+                record.test_winning_row(p, dir) == Some(end)
+            })
+            .then_some(dir)
+    };
+
     // Hits the cursor.
     //
     // Hitting an empty position puts a phantom stone there if there are not
     // enough tentative stones for this turn. Hitting a phantom stone makes
     // it tentative. Hitting a tentative stone makes it phantom. When there
     // are enough tentative stones, the move is automatically submitted.
-    let hit_cursor = move |cursor: Point| {
+    //
+    // If `direct`, skips the phantom stone: hitting an empty position makes
+    // it tentative, and hitting a tentative stone removes it.
+    let hit_cursor = move |cursor: Point, direct: bool| {
         let phantom = phantom_pos.get();
         let mut tentatives = tentatives_pos.get();
 
@@ -274,6 +681,10 @@ pub fn GameView(
             return;
         }
 
+        if setup.get() {
+            return on_event(Event::Setup(cursor));
+        }
+
         if let Some(claim) = win_claim.get() {
             let Some(stone) = stone.get() else {
                 return;
@@ -282,22 +693,12 @@ pub fn GameView(
             let new_claim = match claim {
                 WinClaim::PendingPoint | WinClaim::Ready(..) => WinClaim::PendingDirection(cursor),
                 WinClaim::PendingDirection(p) => {
-                    let Some(dir) = Direction::from_unit_vec(
-                        (cursor.x - p.x).signum(),
-                        (cursor.y - p.y).signum(),
-                    ) else {
+                    if p == cursor {
                         return;
-                    };
-
-                    if record
-                        .write_untracked()
-                        .with_temp_placements(stone, &tentatives, |record| {
-                            record.test_winning_row(p, dir) == Some(cursor)
-                        })
-                    {
-                        WinClaim::Ready(p, dir)
-                    } else {
-                        WinClaim::PendingDirection(cursor)
+                    }
+                    match winning_row_dir(stone, p, cursor) {
+                        Some(dir) => WinClaim::Ready(p, dir),
+                        None => WinClaim::PendingDirection(cursor),
                     }
                 }
             };
@@ -309,14 +710,25 @@ pub fn GameView(
             return;
         }
 
-        if !our_turn() || record.read().stone_at(cursor).is_some() {
+        if !our_turn() {
+            // Let the player know why nothing happened, unless spectating or the game is ended.
+            if stone.get().is_some() && !record.read().is_ended() {
+                on_event(Event::NotYourTurn);
+            }
             return;
         }
 
+        if let Err(err) = record.read().check_move(Move::Place(cursor, None)) {
+            return on_event(Event::IllegalMove(err));
+        }
+
         if let Some(i) = tentatives.iter().position(|&p| p == cursor) {
-            phantom_pos.set(Some(tentatives.remove(i)));
+            let removed = tentatives.remove(i);
+            phantom_pos.set((!direct).then_some(removed));
             tentatives_pos.set(tentatives);
-        } else if phantom == Some(cursor) {
+        } else if phantom == Some(cursor)
+            || (direct && tentatives.len() < record.read().max_stones_to_play())
+        {
             phantom_pos.set(None);
             tentatives.push(cursor);
             tentatives_pos.set(tentatives);
@@ -326,6 +738,11 @@ pub fn GameView(
             }
         } else if tentatives.len() < record.read().max_stones_to_play() {
             phantom_pos.set(Some(cursor));
+        } else if let [first] = tentatives[..] {
+            // There are enough tentative stones, but the move may not have been submitted.
+            if let Err(err) = record.read().check_move(Move::Place(first, Some(cursor))) {
+                on_event(Event::IllegalMove(err));
+            }
         }
     };
 
@@ -414,12 +831,49 @@ pub fn GameView(
         }
     };
 
+    // Hits the cursor, or puts a cursor at the view center if there is no cursor.
+    let hit_or_show_cursor = move || {
+        if let Some(cursor) = cursor_pos.get() {
+            hit_cursor(cursor, false);
+        } else {
+            cursor_pos.set(Some(view_center.get()));
+        }
+    };
+
+    // Moves the cursor in the given direction relative to the view
+    // (see `DIRECTION_OFFSETS`), or puts a cursor at the view center
+    // if there is no cursor.
+    let move_cursor = move |direction: usize| {
+        let Some(mut cursor) = cursor_pos.get() else {
+            cursor_pos.set(Some(view_center.get()));
+            return;
+        };
+
+        // Directions are relative to the view, so map them onto the board.
+        let (dx, dy) = DIRECTION_OFFSETS[direction];
+        let (dx, dy) = orientation.get().unapply(dx, dy);
+
+        cursor.x += dx;
+        cursor.y += dy;
+        cursor_pos.set(Some(cursor));
+
+        // If the cursor is going out of view, adjust the view center to keep up.
+        if calc().board_to_view_pos(cursor).is_none() {
+            view_center.update(|p| {
+                p.x += dx;
+                p.y += dy;
+            });
+        }
+    };
+
     // Handles `keydown` events.
     //
     // - Moves the cursor on W/A/S/D key.
     // - Moves the view center on Arrow Up/Left/Down/Right key.
     // - Zooms out on Minus key.
     // - Zooms in on Plus (Equal) key.
+    // - Rotates the view clockwise on R key, or counterclockwise with Shift.
+    // - Flips the view horizontally on F key.
     // - Hits the cursor on Space/Enter key.
     // - Undoes the previous move (if any) on Backspace key.
     // - Redoes the next move (if any) on Shift+Backspace keys.
@@ -443,6 +897,16 @@ pub fn GameView(
             "KeyD" | "ArrowRight" => 3,
             "Minus" => return zoom(Zoom::Out, None),
             "Equal" => return zoom(Zoom::In, None),
+            "KeyR" => {
+                return orientation.update(|o| {
+                    *o = if ev.shift_key() {
+                        o.rotated_back()
+                    } else {
+                        o.rotated()
+                    };
+                });
+            }
+            "KeyF" => return orientation.update(|o| *o = o.flipped()),
             "Backspace" => {
                 return on_event(if ev.shift_key() {
                     Event::Redo
@@ -456,49 +920,25 @@ pub fn GameView(
                 // Required for the dialog not to close immediately.
                 ev.prevent_default();
 
-                if ev.repeat() {
-                    return;
-                }
-
-                if let Some(cursor) = cursor_pos.get() {
-                    return hit_cursor(cursor);
+                if !ev.repeat() {
+                    hit_or_show_cursor();
                 }
-
-                // Put a cursor at the view center if there is no cursor.
-                cursor_pos.set(Some(view_center.get()));
                 return;
             }
             _ => return,
         };
 
-        let state = state.read_value();
-
         // If the view is being dragged or pinched, bail out to avoid problems.
-        if !state.down_pointers.is_empty() {
+        if !state.read_value().down_pointers.is_empty() {
             return;
         }
 
-        const DIRECTION_OFFSETS: [(i16, i16); 4] = [(0, -1), (-1, 0), (0, 1), (1, 0)];
-
-        let (dx, dy) = DIRECTION_OFFSETS[direction as usize];
         if code.starts_with("Key") {
-            if let Some(mut cursor) = cursor_pos.get() {
-                cursor.x += dx;
-                cursor.y += dy;
-                cursor_pos.set(Some(cursor));
-
-                // If the cursor is going out of view, adjust the view center to keep up.
-                if calc().board_to_view_pos(cursor).is_none() {
-                    view_center.update(|p| {
-                        p.x += dx;
-                        p.y += dy;
-                    });
-                }
-            } else {
-                // Put a cursor at the view center if there is no cursor.
-                cursor_pos.set(Some(view_center.get()));
-            }
+            move_cursor(direction);
         } else {
+            // Directions are relative to the view, so map them onto the board.
+            let (dx, dy) = DIRECTION_OFFSETS[direction];
+            let (dx, dy) = orientation.get().unapply(dx, dy);
             view_center.update(|p| {
                 p.x += dx;
                 p.y += dy;
@@ -509,6 +949,105 @@ pub fn GameView(
         }
     };
 
+    // Polls the first connected gamepad (if any), with the standard mapping.
+    //
+    // - Moves the cursor on D-pad or left stick.
+    // - Zooms out (in) on left (right) bumper.
+    // - Hits the cursor on A button.
+    // - Undoes the previous move (if any) on B button.
+    // - Toggles win claim on X button.
+    // - Opens the game menu on Start button.
+    //
+    // When a dialog is open, moves the focus between its buttons on D-pad or
+    // left stick, clicks the focused button on A button, and closes the dialog
+    // on B button.
+    let poll_gamepad = move || {
+        let Some(pad) = first_gamepad() else {
+            return;
+        };
+
+        let buttons = pad.buttons();
+        let is_pressed = |i: usize| {
+            buttons
+                .get(i as u32)
+                .dyn_into::<GamepadButton>()
+                .is_ok_and(|b| b.pressed())
+        };
+        let axes = pad.axes();
+        let axis = |i: u32| axes.get(i).as_f64().unwrap_or(0.0);
+
+        let mut gamepad = gamepad_state.write_value();
+
+        let pressed = (0..GAMEPAD_BUTTON_COUNT)
+            .filter(|&i| is_pressed(i))
+            .fold(0, |acc, i| acc | 1 << i);
+        let just_pressed = pressed & !gamepad.pressed;
+        gamepad.pressed = pressed;
+        let just_pressed = |i: usize| just_pressed & 1 << i != 0;
+
+        let held = [
+            is_pressed(GAMEPAD_UP) || axis(1) < -GAMEPAD_STICK_THRESHOLD,
+            is_pressed(GAMEPAD_LEFT) || axis(0) < -GAMEPAD_STICK_THRESHOLD,
+            is_pressed(GAMEPAD_DOWN) || axis(1) > GAMEPAD_STICK_THRESHOLD,
+            is_pressed(GAMEPAD_RIGHT) || axis(0) > GAMEPAD_STICK_THRESHOLD,
+        ];
+        let mut fired = [false; 4];
+        for (direction, held) in held.into_iter().enumerate() {
+            let polls = &mut gamepad.held_polls[direction];
+            if held {
+                fired[direction] = *polls == 0
+                    || (*polls >= GAMEPAD_REPEAT_DELAY
+                        && (*polls - GAMEPAD_REPEAT_DELAY).is_multiple_of(GAMEPAD_REPEAT_INTERVAL));
+                *polls += 1;
+            } else {
+                *polls = 0;
+            }
+        }
+        drop(gamepad);
+
+        if disabled.get_untracked() {
+            if just_pressed(GAMEPAD_A) {
+                click_focused_element();
+            } else if just_pressed(GAMEPAD_B) {
+                close_top_dialog();
+            } else if fired[0] || fired[1] {
+                move_dialog_focus(false);
+            } else if fired[2] || fired[3] {
+                move_dialog_focus(true);
+            }
+            return;
+        }
+
+        if just_pressed(GAMEPAD_START) {
+            return on_event(Event::Menu);
+        }
+        if just_pressed(GAMEPAD_A) {
+            return hit_or_show_cursor();
+        }
+        if just_pressed(GAMEPAD_B) {
+            return on_event(Event::Undo);
+        }
+        if just_pressed(GAMEPAD_X) {
+            return on_event(Event::ClaimWin);
+        }
+
+        // If the view is being dragged or pinched, bail out to avoid problems.
+        if !state.read_value().down_pointers.is_empty() {
+            return;
+        }
+
+        if just_pressed(GAMEPAD_LB) {
+            zoom(Zoom::Out, None);
+        } else if just_pressed(GAMEPAD_RB) {
+            zoom(Zoom::In, None);
+        }
+        for (direction, fired) in fired.into_iter().enumerate() {
+            if fired {
+                move_cursor(direction);
+            }
+        }
+    };
+
     // Handles `wheel` events.
     let on_wheel = move |ev: WheelEvent| {
         zoom(
@@ -523,6 +1062,7 @@ pub fn GameView(
 
     // Handles `pointerdown` events.
     let on_pointerdown = move |ev: PointerEvent| {
+        let shift = ev.shift_key();
         let po: PointerOffsets = ev.into();
 
         let mut state = state.write_value();
@@ -536,7 +1076,14 @@ pub fn GameView(
             },
         );
 
-        if state.down_pointers.len() == 2 {
+        if state.down_pointers.len() == 1
+            && state.pointer_state == PointerState::Calm
+            && shift
+            && annotatable.get()
+            && !disabled.get()
+        {
+            state.pointer_state = PointerState::ArrowDragged;
+        } else if state.down_pointers.len() == 2 {
             state.prev_view_size = view_size.get();
             state.pointer_state = PointerState::Pinched;
             if cursor_pos.get().is_some() {
@@ -550,6 +1097,9 @@ pub fn GameView(
     // Attempts to hit the cursor when the pointer is the only active one,
     // the view isn't ever dragged, zoomed, or pinched since the pointer
     // became active, the view isn't disabled, and the main button is pressed.
+    //
+    // In double-tap mode, a touch hits the cursor directly only when
+    // it is on the same position as the previous touch.
     let on_pointerup = move |ev: PointerEvent| {
         let mut state = state.write_value();
         if state.down_pointers.remove(&ev.pointer_id()).is_none() {
@@ -559,6 +1109,21 @@ pub fn GameView(
         if !state.down_pointers.is_empty() {
             return;
         }
+        if state.pointer_state == PointerState::ClaimDragged {
+            state.pointer_state = PointerState::Calm;
+            if let Some(WinClaim::Ready(..)) = win_claim.get() {
+                on_event(Event::Submit);
+            }
+            return;
+        }
+        if state.pointer_state == PointerState::ArrowDragged {
+            state.pointer_state = PointerState::Calm;
+            if let Some((start, end)) = dragged_arrow.get() {
+                dragged_arrow.set(None);
+                annotations.update(|a| a.toggle_arrow(start, end));
+            }
+            return;
+        }
         if state.pointer_state != PointerState::Calm {
             state.pointer_state = PointerState::Calm;
             return;
@@ -567,8 +1132,35 @@ pub fn GameView(
             return;
         }
 
-        if let Some(cursor) = update_cursor(ev.into()) {
-            hit_cursor(cursor);
+        // Cycle the mark at the cursor if clicked with Ctrl held.
+        if (ev.ctrl_key() || ev.meta_key()) && annotatable.get() {
+            if let Some(cursor) = update_cursor(ev.into()) {
+                annotations.update(|a| a.cycle_mark(cursor));
+            }
+            return;
+        }
+
+        // Center the view on the previous move if its arrow is hit.
+        let calc = calc();
+        let (p, out) = calc.canvas_to_view_pos(ev.offset_x(), ev.offset_y());
+        if out {
+            let off_view = off_view_move(&record.read(), stone.get(), &calc);
+            if let Some((target, _)) = off_view.filter(|&(_, arrow)| arrow == p) {
+                view_center.set(target);
+                return;
+            }
+        }
+
+        if ev.pointer_type() == "touch" && double_tap.get() {
+            if let Some(cursor) = update_cursor(ev.into()) {
+                // The first tap only moves the cursor, and a second tap confirms.
+                if state.last_tap.replace(cursor) == Some(cursor) {
+                    state.last_tap = None;
+                    hit_cursor(cursor, true);
+                }
+            }
+        } else if let Some(cursor) = update_cursor(ev.into()) {
+            hit_cursor(cursor, false);
         }
     };
 
@@ -577,11 +1169,13 @@ pub fn GameView(
     // Performs different actions according to the number of active pointers:
     //
     // - 0: Updates the cursor.
-    // - 1: Drags the view if it isn't ever pinched since the pointer became active.
+    // - 1: Drags the view if it isn't ever pinched since the pointer became active,
+    //      claims a win if dragged from a stone of ours while claiming a win,
+    //      or draws an arrow if pressed with Shift while annotating.
     // - 2: Roughly speaking, whenever the distance of pointers increases (decreases)
-    //      by `DIST_FOR_PINCH_ZOOM`, `viewSize` will be decreased (increased) by 2.
+    //      by `pinch_zoom_dist`, `viewSize` will be decreased (increased) by 2.
     // - 3: Retracts the previous move if all pointers have moved for at least
-    //      a distance of `DIST_FOR_SWIPE_RETRACT`.
+    //      a distance of `swipe_undo_dist`, unless swiping to undo is disabled.
     let on_hover = move |po: PointerOffsets| {
         let mut state = state.write_value();
         if disabled.get() {
@@ -614,6 +1208,53 @@ pub fn GameView(
         if state.down_pointers.is_empty() {
             update_cursor(po);
         } else if state.down_pointers.len() == 1 {
+            let start = state
+                .down_pointers
+                .values()
+                .next()
+                .unwrap()
+                .board_pos_on_down;
+            let claiming_from_own_stone = || {
+                let Some(stone) = stone.get() else {
+                    return false;
+                };
+                win_claim.get().is_some()
+                    && (record.read().stone_at(start) == Some(stone)
+                        || tentatives_pos.read().contains(&start))
+            };
+
+            if state.pointer_state == PointerState::ClaimDragged
+                || (state.pointer_state == PointerState::Calm && claiming_from_own_stone())
+            {
+                let Some(end) = update_cursor(po) else {
+                    return;
+                };
+                let Some(our_stone) = stone.get() else {
+                    // We are no longer playing, so cancel the drag.
+                    state.pointer_state = PointerState::Moved;
+                    return;
+                };
+                if end != start {
+                    state.pointer_state = PointerState::ClaimDragged;
+                    let dir = winning_row_dir(our_stone, start, end);
+                    win_claim.set(Some(match dir {
+                        Some(dir) => WinClaim::Ready(start, dir),
+                        None => WinClaim::PendingDirection(start),
+                    }));
+                }
+                return;
+            }
+
+            if state.pointer_state == PointerState::ArrowDragged {
+                let arrow = update_cursor(po)
+                    .filter(|&end| end != start)
+                    .map(|end| (start, end));
+                if arrow != dragged_arrow.get_untracked() {
+                    dragged_arrow.set(arrow);
+                }
+                return;
+            }
+
             if state.pointer_state > PointerState::Moved {
                 return;
             }
@@ -632,8 +1273,8 @@ pub fn GameView(
 
             let dist_diff = p1.last.dist(p2.last) - p1.down.dist(p2.down);
 
-            let mut new_view_size =
-                state.prev_view_size - (dist_diff / DIST_FOR_PINCH_ZOOM) as i16 * 2;
+            let step = gestures.read().pinch_zoom_dist * PX_PER_CM;
+            let mut new_view_size = state.prev_view_size - (dist_diff / step) as i16 * 2;
             if new_view_size < 1 {
                 new_view_size = 1;
             }
@@ -645,9 +1286,12 @@ pub fn GameView(
             if state.pointer_state == PointerState::Retracted {
                 return;
             }
+            let Some(dist) = gestures.read().swipe_undo_dist else {
+                return;
+            };
 
             for p in state.down_pointers.values() {
-                if p.last.dist(p.down) < DIST_FOR_SWIPE_RETRACT {
+                if p.last.dist(p.down) < dist * PX_PER_CM {
                     return;
                 }
             }
@@ -668,11 +1312,15 @@ pub fn GameView(
         }
         if state.down_pointers.is_empty() {
             state.pointer_state = PointerState::Calm;
+            if dragged_arrow.get_untracked().is_some() {
+                dragged_arrow.set(None);
+            }
         }
         if state.last_hover_before_enabled.and_then(|po| po.id) == po.id {
             state.last_hover_before_enabled = None;
         }
-        if cursor_pos.get().is_some() {
+        // Keep the cursor at the first tap in double-tap mode.
+        if cursor_pos.get().is_some() && state.last_tap.is_none() {
             cursor_pos.set(None);
         }
     };
@@ -686,6 +1334,16 @@ pub fn GameView(
         }
     });
 
+    // Move the focus back to the board once the last dialog is closed,
+    // so that keys do not activate a button focused before it was opened.
+    Effect::new(move || {
+        if !disabled.get() && !passive {
+            if let Some(canvas) = canvas_ref.get_untracked() {
+                _ = canvas.focus();
+            }
+        }
+    });
+
     // Draws the view.
     let draw = move || {
         console_log!("draw");
@@ -716,6 +1374,41 @@ pub fn GameView(
         ctx.set_fill_style_str(BOARD_COLOR);
         ctx.fill_rect(0.0, 0.0, size, size);
 
+        // Draw the heatmap.
+        if heatmap.get() {
+            // Numbers of black and white stones near each position in view.
+            let mut counts = HashMap::<Point, [u32; 2]>::new();
+
+            let record = record.read_untracked();
+            for turn in record.turns().take(record.move_index()) {
+                let stone = turn.stone;
+                for p in turn.placed() {
+                    for dx in -HEATMAP_RADIUS..=HEATMAP_RADIUS {
+                        for dy in -HEATMAP_RADIUS..=HEATMAP_RADIUS {
+                            let q = Point::new(p.x.wrapping_add(dx), p.y.wrapping_add(dy));
+                            if let Some(q) = calc.board_to_view_pos(q) {
+                                counts.entry(q).or_default()[stone.index()] += 1;
+                            }
+                        }
+                    }
+                }
+            }
+
+            ctx.set_fill_style_str(HEATMAP_COLOR);
+            for (p, [black, white]) in counts {
+                // Positions near stones of both colors are more contested.
+                let heat = (black + white + 2 * black.min(white)).min(HEATMAP_SATURATION);
+                ctx.set_global_alpha(
+                    HEATMAP_MAX_OPACITY * f64::from(heat) / f64::from(HEATMAP_SATURATION),
+                );
+
+                let (x, y) = calc.view_to_canvas_pos(p);
+                let half = grid_size / 2.0;
+                ctx.fill_rect(x - half, y - half, grid_size, grid_size);
+            }
+            ctx.set_global_alpha(1.0);
+        }
+
         ctx.set_stroke_style_str("black");
         ctx.set_line_width(grid_size / LINE_WIDTH_RATIO);
 
@@ -751,6 +1444,29 @@ pub fn GameView(
         ctx.stroke();
         ctx.set_line_dash(&Array::new()).unwrap();
 
+        // Find the rows that can be claimed as a win, as pairs of endpoints.
+        let claim_hints: Vec<(Point, Point)> =
+            match (win_claim.get_untracked(), stone.get_untracked()) {
+                (Some(_), Some(stone)) => record.write_untracked().with_temp_placements(
+                    stone,
+                    &tentatives_pos.get_untracked(),
+                    |record| {
+                        record
+                            .winning_rows(stone)
+                            .map(|(p, dir)| {
+                                let end = p
+                                    .adjacent_iter(dir)
+                                    .take_while(|&p| record.stone_at(p) == Some(stone))
+                                    .last()
+                                    .unwrap();
+                                (p, end)
+                            })
+                            .collect()
+                    },
+                ),
+                _ => vec![],
+            };
+
         let record = record.read_untracked();
         let dot_radius = grid_size / DOT_RADIUS_RATIO;
 
@@ -766,33 +1482,41 @@ pub fn GameView(
         let moves = record.moves();
         let move_index = record.move_index();
         let stone_radius = grid_size / STONE_RADIUS_RATIO;
-        // We project the out-of-view stones onto the view border,
-        // and stores the resulting positions in this set.
-        let mut out_pos = HashSet::new();
 
-        // Draw the stones.
-        for (i, &mov) in moves.iter().enumerate().take(move_index) {
-            let Move::Place(p1, p2) = mov else {
-                continue;
-            };
-            let stone = Record::turn_at(i);
+        // Draw the stones, redrawing only what changed since the last time.
+        stone_layer.update_value(|layer| {
+            layer.update(size, calc, &record);
+            ctx.draw_image_with_html_canvas_element_and_dw_and_dh(
+                &layer.canvas,
+                0.0,
+                0.0,
+                size,
+                size,
+            )
+            .unwrap();
+        });
 
-            for p in iter::once(p1).chain(p2) {
-                let (p, out) = calc.board_to_view_pos_clamped(p, ClampTo::InsideAndBorder);
-                if out {
-                    out_pos.insert(p);
-                    continue;
-                }
+        // Draw the rows that can be claimed.
+        if !claim_hints.is_empty() {
+            ctx.set_global_alpha(CLAIM_HINT_OPACITY);
+            ctx.set_stroke_style_str(WIN_RING_COLOR);
+            ctx.set_line_width(stone_radius);
+            ctx.set_line_cap("round");
 
-                set_fill_style_by_stone(stone);
-                draw_circle(p, stone_radius);
+            ctx.begin_path();
+            for (start, end) in claim_hints {
+                let (x, y) = calc.board_to_view_pos_unclamped(start);
+                let (x, y) = calc.view_to_canvas_pos(Point::new(x, y));
+                ctx.move_to(x, y);
+
+                let (x, y) = calc.board_to_view_pos_unclamped(end);
+                let (x, y) = calc.view_to_canvas_pos(Point::new(x, y));
+                ctx.line_to(x, y);
             }
-        }
+            ctx.stroke();
 
-        // Draw the out-of-view stones on the view border.
-        ctx.set_fill_style_str("gray");
-        for p in out_pos {
-            draw_circle(p, stone_radius);
+            ctx.set_line_cap("butt");
+            ctx.set_global_alpha(1.0);
         }
 
         let draw_win_ring = |p: Point| {
@@ -814,6 +1538,36 @@ pub fn GameView(
             }
         };
 
+        // Draw the divergence from the other record.
+        if let Some(other) = &*compare_with.read() {
+            let common = common_move_count(&record, other);
+
+            ctx.set_global_alpha(PHANTOM_MOVE_OPACITY);
+            for turn in other.turns().take(other.move_index()).skip(common) {
+                set_fill_style_by_stone(turn.stone);
+                for p in turn.placed() {
+                    if record.stone_at(p).is_none() {
+                        if let Some(p) = calc.board_to_view_pos(p) {
+                            draw_circle(p, stone_radius);
+                        }
+                    }
+                }
+            }
+            ctx.set_global_alpha(1.0);
+
+            ctx.set_stroke_style_str(DIVERGENCE_RING_COLOR);
+            for &mov in moves[..move_index].iter().skip(common) {
+                let Move::Place(p1, p2) = mov else {
+                    continue;
+                };
+                for p in iter::once(p1).chain(p2) {
+                    if let Some(p) = calc.board_to_view_pos(p) {
+                        draw_win_ring(p);
+                    }
+                }
+            }
+        }
+
         // Draw the previous move.
         if let Some(mov) = record.prev_move() {
             let stone = Record::turn_at(move_index - 1);
@@ -870,6 +1624,38 @@ pub fn GameView(
             }
         }
 
+        // Draw an arrow on the border pointing to the previous move if out of view.
+        if let Some((p, arrow)) = off_view_move(&record, stone.get_untracked(), &calc) {
+            let (x, y) = calc.board_to_view_pos_unclamped(p);
+            let center = f64::from(calc.view_size / 2);
+            let angle = (f64::from(y) - center).atan2(f64::from(x) - center);
+            let (ax, ay) = calc.view_to_canvas_pos(arrow);
+
+            ctx.save();
+            ctx.translate(ax, ay).unwrap();
+            ctx.rotate(angle).unwrap();
+            ctx.begin_path();
+            ctx.move_to(stone_radius, 0.0);
+            ctx.line_to(-stone_radius / 2.0, stone_radius * 0.75);
+            ctx.line_to(-stone_radius / 2.0, -stone_radius * 0.75);
+            ctx.close_path();
+            ctx.set_fill_style_str(OFF_VIEW_ARROW_COLOR);
+            ctx.fill();
+            ctx.restore();
+        }
+
+        // Draw the provisional moves.
+        ctx.set_stroke_style_str(PROVISIONAL_RING_COLOR);
+        for &mov in &moves[moves.len() - provisional.get().min(moves.len())..] {
+            if let Move::Place(p1, p2) = mov {
+                for p in iter::once(p1).chain(p2) {
+                    if let Some(p) = calc.board_to_view_pos(p) {
+                        draw_win_ring(p);
+                    }
+                }
+            }
+        }
+
         if let Some(stone) = stone.get_untracked() {
             // Draw the phantom stone.
             if let Some(p) = phantom_pos
@@ -921,8 +1707,67 @@ pub fn GameView(
             }
         }
 
-        // Draw the cursor.
-        if let Some(p) = cursor_pos.get().and_then(|p| calc.board_to_view_pos(p)) {
+        // Draw the annotations.
+        ctx.set_global_alpha(ANNOTATION_OPACITY);
+        ctx.set_stroke_style_str(ANNOTATION_COLOR);
+        ctx.set_fill_style_str(ANNOTATION_COLOR);
+        ctx.set_line_width(grid_size / WIN_RING_WIDTH_RATIO * 1.5);
+
+        let annotations = annotations.read();
+        for &(p, mark) in &annotations.marks {
+            let Some(p) = calc.board_to_view_pos(p) else {
+                continue;
+            };
+            let (x, y) = calc.view_to_canvas_pos(p);
+            let r = stone_radius * 0.6;
+
+            ctx.begin_path();
+            match mark {
+                Mark::Circle => ctx.arc(x, y, r, 0.0, f64::consts::TAU).unwrap(),
+                Mark::Triangle => {
+                    for i in 0..3 {
+                        let angle = f64::consts::TAU * f64::from(i) / 3.0 - f64::consts::FRAC_PI_2;
+                        ctx.line_to(x + r * angle.cos(), y + r * angle.sin());
+                    }
+                    ctx.close_path();
+                }
+            }
+            ctx.stroke();
+        }
+
+        for &(start, end) in annotations.arrows.iter().chain(&dragged_arrow.get()) {
+            let (x, y) = calc.board_to_view_pos_unclamped(start);
+            let (x1, y1) = calc.view_to_canvas_pos(Point::new(x, y));
+            let (x, y) = calc.board_to_view_pos_unclamped(end);
+            let (x2, y2) = calc.view_to_canvas_pos(Point::new(x, y));
+
+            // Stop the shaft short of the head so that its tip stays sharp.
+            let angle = (y2 - y1).atan2(x2 - x1);
+            let head = stone_radius;
+            let (dx, dy) = (angle.cos(), angle.sin());
+
+            ctx.begin_path();
+            ctx.move_to(x1, y1);
+            ctx.line_to(x2 - head * dx, y2 - head * dy);
+            ctx.stroke();
+
+            ctx.begin_path();
+            ctx.move_to(x2, y2);
+            ctx.line_to(
+                x2 - head * dx - head / 2.0 * dy,
+                y2 - head * dy + head / 2.0 * dx,
+            );
+            ctx.line_to(
+                x2 - head * dx + head / 2.0 * dy,
+                y2 - head * dy - head / 2.0 * dx,
+            );
+            ctx.close_path();
+            ctx.fill();
+        }
+        ctx.set_global_alpha(1.0);
+
+        // Draws a cursor at a view position with the given color.
+        let draw_cursor = |p: Point, color: &str| {
             let (x, y) = calc.view_to_canvas_pos(p);
 
             let line_width = grid_size / CURSOR_LINE_WIDTH_RATIO;
@@ -933,11 +1778,7 @@ pub fn GameView(
             let in_offset = offset - line_width / 2.0;
             let out_offset = offset + side;
 
-            ctx.set_stroke_style_str(if our_turn() {
-                CURSOR_COLOR_ACTIVE
-            } else {
-                CURSOR_COLOR_INACTIVE
-            });
+            ctx.set_stroke_style_str(color);
             ctx.begin_path();
             for (dx, dy) in [(1, 1), (1, -1), (-1, -1), (-1, 1)] {
                 let (dx, dy) = (dx as f64, dy as f64);
@@ -947,6 +1788,31 @@ pub fn GameView(
                 ctx.line_to(x + offset * dx, y + out_offset * dy);
             }
             ctx.stroke();
+        };
+
+        // Draw the cursors shared by players.
+        for (stone, p) in Stone::VALUES.into_iter().zip(shared_cursors.get()) {
+            if let Some(p) = p.and_then(|p| calc.board_to_view_pos(p)) {
+                draw_cursor(
+                    p,
+                    match stone {
+                        Stone::Black => "black",
+                        Stone::White => "white",
+                    },
+                );
+            }
+        }
+
+        // Draw the cursor.
+        if let Some(p) = cursor_pos.get().and_then(|p| calc.board_to_view_pos(p)) {
+            draw_cursor(
+                p,
+                if our_turn() {
+                    CURSOR_COLOR_ACTIVE
+                } else {
+                    CURSOR_COLOR_INACTIVE
+                },
+            );
         }
     };
 
@@ -1018,13 +1884,20 @@ pub fn GameView(
         });
     });
 
-    let handle = window_event_listener(ev::keydown, on_keydown);
-    on_cleanup(move || handle.remove());
+    if !passive {
+        let handle = window_event_listener(ev::keydown, on_keydown);
+        let gamepad_handle = set_interval_with_handle(poll_gamepad, GAMEPAD_POLL_INTERVAL).unwrap();
+        on_cleanup(move || {
+            handle.remove();
+            gamepad_handle.clear();
+        });
+    }
 
     view! {
-        <div id="view-container" node_ref=container_ref>
+        <div class="view-container" node_ref=container_ref>
             <canvas
-                id="view"
+                class="view"
+                tabindex="-1"
                 node_ref=canvas_ref
                 on:wheel=on_wheel
                 on:pointerdown=on_pointerdown
@@ -2,20 +2,24 @@
 
 mod dialog;
 mod game_view;
+mod replay;
+mod storage;
 
 use base64::{prelude::BASE64_STANDARD, Engine};
 use c6ol_core::{
-    game::{Direction, Move, Point, Record, Stone},
-    protocol::{ClientMessage, Request, ServerMessage},
+    game::{Direction, GameResult, Move, MoveError, Point, Record, RecordDecoder, Stone, WinBy},
+    protocol::{ClientMessage, GameId, Passcode, Request, ServerMessage, JOIN_CODE_LEN},
 };
 use dialog::*;
-use leptos::{ev, prelude::*};
-use std::sync::atomic::{AtomicU32, Ordering};
+use game_view::{Annotations, Orientation};
+use leptos::{ev, prelude::*, task::spawn_local};
+use std::{borrow::Cow, iter, mem, time::Duration};
 use tinyvec::ArrayVec;
+use wasm_bindgen_futures::JsFuture;
 use web_sys::{
-    js_sys::{ArrayBuffer, Uint8Array},
+    js_sys::{ArrayBuffer, Date, Uint8Array},
     wasm_bindgen::prelude::*,
-    BinaryType, CloseEvent, MessageEvent, Storage, WebSocket,
+    BinaryType, CloseEvent, MessageEvent, RequestInit, Response, WebSocket,
 };
 
 macro_rules! console_log {
@@ -31,25 +35,62 @@ pub(crate) use console_log;
 enum Confirm {
     MainMenu,
     Submit(Point, Option<Point>),
-    Pass(Option<Point>),
+    Pass,
     BeginClaim,
-    Claim(ArrayVec<[Point; 2]>, Point, Direction),
+    /// Claims a win after placing the tentative stones,
+    /// with whether the row belongs to the opponent.
+    Claim(ArrayVec<[Point; 2]>, Point, Direction, bool),
     Request(Request),
     Accept(Request),
     Resign,
+    HandOver(Stone),
+    /// An online game ended, with the result, our stone (if playing)
+    /// and the farewell message left by a resigning player (if any).
+    GameOver(String, Option<Stone>, Option<String>),
     ConnClosed(String),
     Error(String),
+    /// Shows a seat transfer code received from the server.
+    TransferCode(String),
+    /// Shows a join code issued by the server.
+    JoinCode(String),
+}
+
+#[derive(Clone, Copy)]
+struct RequestLogEntry {
+    stone: Stone,
+    req: Request,
+    /// The number of moves in the record when requested.
+    move_index: usize,
+    /// The outcome, or `None` if pending.
+    outcome: Option<RequestOutcome>,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum RequestOutcome {
+    Accepted,
+    /// Ignored by us.
+    Declined,
+    /// Cleared by another change to the record.
+    Expired,
 }
 
 enum Event {
     Menu,
     Submit,
+    ClaimWin,
     Undo,
     Redo,
     Home,
     End,
     Resign,
     Draw,
+    NotYourTurn,
+    IllegalMove(MoveError),
+    /// Jumps to the given move index when navigating the history.
+    Jump(usize),
+    /// Cycles the stone at the given position in setup mode,
+    /// from empty to Black to White.
+    Setup(Point),
 }
 
 #[derive(Clone, Copy)]
@@ -59,14 +100,17 @@ enum WinClaim {
     Ready(Point, Direction),
 }
 
-const STORAGE_KEY_RECORD: &str = "record";
 const ANALYZE_PREFIX: &str = "analyze,";
+const COMPARE_PREFIX: &str = "compare,";
+const WATCH_SUFFIX: &str = "/watch";
 
-#[derive(Clone)]
-struct DialogEntry {
-    id: u32,
-    dialog: Dialog,
-}
+const TITLE: &str = "Connect6";
+
+const TOAST_DURATION: Duration = Duration::from_secs(2);
+const CURSOR_SHARE_INTERVAL: Duration = Duration::from_millis(250);
+const REQUEST_LOG_CAPACITY: usize = 20;
+/// Number of moves to decode before yielding to the event loop.
+const DECODE_CHUNK_MOVES: usize = 10_000;
 
 #[expect(dead_code)]
 struct WebSocketState {
@@ -76,11 +120,116 @@ struct WebSocketState {
     onmessage: Closure<dyn Fn(MessageEvent)>,
 }
 
+/// State of the connection to the server, while online.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum ConnState {
+    /// Opening the connection for the first time.
+    Connecting,
+    /// The connection is open, and we are joined (and signed in, if playing).
+    Open,
+    /// The connection was lost, with the number of reconnection attempts made.
+    ///
+    /// Messages other than moves are queued until we are joined again.
+    Reconnecting(u32),
+    /// The connection is closed for good.
+    Closed,
+}
+
+/// Reasons given by the server for closing the connection
+/// when it fails to sign us in.
+const SIGN_IN_FAILURES: [&str; 2] = ["Wrong passcode.", "Seat taken by another connection."];
+
 const CLOSE_CODE_ABNORMAL: u16 = 1006;
 const CLOSE_CODE_POLICY: u16 = 1008;
 
-fn local_storage() -> Storage {
-    window().local_storage().unwrap().unwrap()
+const RECONNECT_BASE_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_ATTEMPTS: u32 = 5;
+
+/// Decodes a record from a base64 string.
+fn decode_record(buf: &str) -> Option<Record> {
+    let buf = BASE64_STANDARD.decode(buf).ok()?;
+    Record::decode(&mut &buf[..], false)
+}
+
+/// Decodes a record progressively from `buf[pos..]`, yielding to the event
+/// loop after every chunk of moves so that long records do not freeze the page.
+///
+/// The fraction of bytes decoded is written to `progress` while decoding.
+/// Decoding is cancelled once `generation` is bumped from `gen`,
+/// before the next chunk is decoded.
+fn decode_progressively(
+    buf: Vec<u8>,
+    pos: usize,
+    mut decoder: RecordDecoder,
+    progress: RwSignal<Option<f64>>,
+    (generation, gen): (StoredValue<u32>, u32),
+    done: impl FnOnce(Option<Record>) + 'static,
+) {
+    if generation.get_value() != gen {
+        return;
+    }
+
+    let mut rest = &buf[pos..];
+    if !decoder.decode(&mut rest, DECODE_CHUNK_MOVES) {
+        progress.set(None);
+        done(None);
+    } else if rest.is_empty() {
+        progress.set(None);
+        done(decoder.finish());
+    } else {
+        let pos = buf.len() - rest.len();
+        progress.set(Some(pos as f64 / buf.len() as f64));
+        set_timeout(
+            move || decode_progressively(buf, pos, decoder, progress, (generation, gen), done),
+            Duration::ZERO,
+        );
+    }
+}
+
+/// Returns the number of leading moves that two records have in common.
+fn common_move_count(a: &Record, b: &Record) -> usize {
+    iter::zip(a.moves(), b.moves())
+        .take_while(|(a, b)| a == b)
+        .count()
+}
+
+/// Describes the result of the game (if ended).
+fn describe_result(record: &Record) -> Option<String> {
+    Some(match record.result()? {
+        GameResult::Draw => "Game Drawn".into(),
+        GameResult::Win(stone, WinBy::Resignation) => format!("{:?} Resigned", stone.opposite()),
+        GameResult::Win(stone, WinBy::Row) => format!("{stone:?} Won"),
+    })
+}
+
+/// Records a jump between move indices in the history,
+/// so that navigating back and forth redoes and undoes it.
+fn history_push_jump(from: usize, to: usize) {
+    let history = window().history().unwrap();
+    history
+        .replace_state(&JsValue::from(from as f64), "")
+        .unwrap();
+    history.push_state(&JsValue::from(to as f64), "").unwrap();
+}
+
+/// Sends a request with an empty body to the server API,
+/// returning the response text if it succeeds.
+async fn fetch_api(method: &str, path: &str) -> Option<String> {
+    let init = RequestInit::new();
+    init.set_method(method);
+    let res = JsFuture::from(window().fetch_with_str_and_init(path, &init))
+        .await
+        .ok()?;
+    let res: Response = res.unchecked_into();
+    if !res.ok() {
+        return None;
+    }
+    JsFuture::from(res.text().ok()?).await.ok()?.as_string()
+}
+
+/// Tests if the text is a join code.
+fn is_join_code(text: &str) -> bool {
+    text.len() == JOIN_CODE_LEN && text.bytes().all(|b| b.is_ascii_digit())
 }
 
 fn history_push_state(url: &str) {
@@ -93,57 +242,335 @@ fn history_push_state(url: &str) {
 /// Entry-point for the app.
 #[component]
 pub fn App() -> impl IntoView {
+    storage::migrate();
+
     let record = RwSignal::new(Record::new());
     let stone = RwSignal::new(None::<Stone>);
 
-    let tentatives_pos = RwSignal::new(ArrayVec::new());
+    let tentatives_pos = RwSignal::new(ArrayVec::<[Point; 2]>::new());
     let win_claim = RwSignal::new(None);
+    let orientation = RwSignal::new(Orientation::default());
+    let heatmap = RwSignal::new(false);
+    // Whether hitting a position cycles its stone, for setting up a position.
+    let setup = RwSignal::new(false);
+    // Arrows and marks drawn on the board while analyzing.
+    let annotations = RwSignal::new(Annotations::default());
+    let compare_with = RwSignal::new(None::<Record>);
+    // Whether to show the other record compared with in a view of its own.
+    let split = RwSignal::new(false);
+    // Whether to navigate both records together in split views.
+    let sync_split = RwSignal::new(true);
+    // The other record shown in split views.
+    let other_record = RwSignal::new(Record::new());
+
+    let cursor_pos = RwSignal::new(None::<Point>);
+    // Whether to share our cursor with others in an online game.
+    let share_cursor = RwSignal::new(false);
+    // Whether to show the cursors shared by others.
+    let show_shared_cursors = RwSignal::new(true);
+    let shared_cursors = RwSignal::new([None::<Point>; 2]);
+    let double_tap = RwSignal::new(storage::get(storage::Key::DoubleTap).is_some());
+    let gestures = RwSignal::new(
+        storage::get(storage::Key::Gestures)
+            .and_then(|s| ron::from_str(&s).ok())
+            .unwrap_or_default(),
+    );
 
     let game_id = RwSignal::new(String::new());
+    // Fraction of the record decoded from an analyze link, if decoding.
+    let decode_progress = RwSignal::new(None::<f64>);
+    // Bumped whenever the game ID is set, cancelling any ongoing decoding.
+    let decode_generation = StoredValue::new(0u32);
 
     let requests = RwSignal::new([None::<Stone>; Request::VALUES.len()]);
     let who_requested = move |req: Request| requests.read()[req as usize];
 
-    let dialog_entries = RwSignal::new(Vec::<DialogEntry>::new());
+    // Recent requests in the current game, oldest first.
+    let request_log = RwSignal::new(Vec::<RequestLogEntry>::new());
+    // When our last retract request was declined, in milliseconds since the epoch.
+    let retract_declined_at = StoredValue::new(None::<f64>);
+    // The cooldown of retract requests after a decline, as sent by the server.
+    let retract_cooldown = StoredValue::new(Duration::ZERO);
 
-    let show_dialog = move |dialog: Dialog| {
-        static NEXT_ID: AtomicU32 = AtomicU32::new(0);
+    let dialogs = RwSignal::new(DialogStack::default());
 
-        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
-        dialog_entries.write().push(DialogEntry { id, dialog });
-    };
+    let show_dialog = move |dialog: Dialog| dialogs.write().push(dialog);
 
     let confirm = move |confirm: Confirm| show_dialog(Dialog::from(ConfirmDialog(confirm)));
 
+    let show_main_menu_dialog = move || {
+        let recent_games = if cfg!(feature = "online") {
+            storage::get_recent_games()
+        } else {
+            vec![]
+        };
+        show_dialog(Dialog::from(MainMenuDialog { recent_games }));
+    };
+
+    let toast = RwSignal::new(None::<Cow<'static, str>>);
+    let toast_timeout = StoredValue::new(None::<TimeoutHandle>);
+
+    // Shows a transient message, replacing the current one (if any).
+    let show_toast = move |msg: Cow<'static, str>| {
+        if let Some(handle) = toast_timeout.get_value() {
+            handle.clear();
+        }
+        toast.set(Some(msg));
+        let handle = set_timeout_with_handle(move || toast.set(None), TOAST_DURATION).ok();
+        toast_timeout.set_value(handle);
+    };
+
     let ws_state = StoredValue::new_local(None::<WebSocketState>);
 
     let online = move || ws_state.read_value().is_some();
 
+    let conn_state = RwSignal::new(ConnState::Connecting);
+    // Messages made while reconnecting, to be sent once joined again.
+    let outbox = StoredValue::new(Vec::<ClientMessage>::new());
+    // The passcode we signed in with, to sign in again on reconnecting.
+    let passcode = StoredValue::new(None::<Passcode>);
+    let reconnect = Trigger::new();
+    let reconnect_timeout = StoredValue::new(None::<TimeoutHandle>);
+
+    // Spectators can browse the history of an online game on their own.
+    let spectating = move || online() && stone.get().is_none();
+    // Whether a spectator jumps to every new move.
+    let follow_live = RwSignal::new(true);
+
     Effect::new(move || {
-        if *game_id.read() == "local" {
+        if let Some(key) = storage::Key::for_game(&game_id.read()) {
             // Save the record to local storage.
+            match storage::set_record(key, &record.read()) {
+                Ok(()) => {}
+                Err(storage::Error::Unavailable) => {
+                    show_toast("Storage unavailable, not saved".into());
+                }
+                Err(storage::Error::QuotaExceeded) => show_toast("Storage full, not saved".into()),
+            }
+        }
+    });
+
+    // Saves the tentative stones of an online game to local storage,
+    // along with the move index, so that they can be restored on reopening.
+    Effect::new(move || {
+        let tentatives = tentatives_pos.get();
+        if !online() || stone.get_untracked().is_none() {
+            return;
+        }
+
+        let game_id = game_id.read_untracked();
+        let key = storage::Key::Tentatives(&game_id);
+        if tentatives.is_empty() {
+            storage::remove(key);
+        } else {
             let mut buf = vec![];
-            record.read().encode(&mut buf, true);
-            let buf = BASE64_STANDARD.encode(buf);
-            local_storage().set_item(STORAGE_KEY_RECORD, &buf).unwrap();
+            for p in tentatives {
+                p.encode(&mut buf);
+            }
+            let index = record.read_untracked().move_index();
+            let buf = format!("{index},{}", BASE64_STANDARD.encode(buf));
+            // Not worth bothering the player if this fails.
+            _ = storage::set(key, &buf);
         }
     });
 
-    // Sends the message on the WebSocket connection.
-    let send = move |msg: ClientMessage| {
+    // Restores the saved tentative stones (if any) of the current online game,
+    // provided that they are still valid.
+    let restore_tentatives = move || {
+        let game_id = game_id.read_untracked();
+        let key = storage::Key::Tentatives(&game_id);
+        let Some(saved) = storage::get(key) else {
+            return;
+        };
+        // The effect above saves them again once restored.
+        storage::remove(key);
+
+        let record = record.read_untracked();
+        if stone.get_untracked() != record.turn() {
+            return;
+        }
+
+        let Some((index, buf)) = saved.split_once(',') else {
+            return;
+        };
+        let Ok(buf) = BASE64_STANDARD.decode(buf) else {
+            return;
+        };
+        if index.parse() != Ok(record.move_index()) {
+            return;
+        }
+
+        let mut buf = &buf[..];
+        let mut tentatives = ArrayVec::<[Point; 2]>::new();
+        while !buf.is_empty() {
+            match Point::decode(&mut buf) {
+                Some(p)
+                    if tentatives.len() < record.max_stones_to_play()
+                        && record.stone_at(p).is_none()
+                        && !tentatives.contains(&p) =>
+                {
+                    tentatives.push(p);
+                }
+                _ => return,
+            }
+        }
+
+        // Defer until the view has cleared the tentative stones on stone change.
+        set_timeout(move || tentatives_pos.set(tentatives), Duration::ZERO);
+    };
+
+    // Sends the message on the WebSocket connection at once, returning
+    // whether the connection is open.
+    let send_now = move |msg: ClientMessage| {
+        if let ClientMessage::Start(p) | ClientMessage::StartReserved(p, _) = &msg {
+            passcode.set_value(Some(p.clone()));
+        }
         if let Some(ws_state) = &*ws_state.read_value() {
             if ws_state.ws.ready_state() == WebSocket::OPEN {
                 ws_state.ws.send_with_u8_array(&msg.encode()).unwrap();
-                return;
+                return true;
+            }
+        }
+        false
+    };
+
+    // Sends the message on the WebSocket connection, or queues it if
+    // reconnecting, returning whether the message is sent or queued.
+    let send = move |msg: ClientMessage| {
+        match conn_state.get_untracked() {
+            ConnState::Open => {
+                if send_now(msg) {
+                    return true;
+                }
+            }
+            ConnState::Reconnecting(_) => {
+                outbox.write_value().push(msg);
+                return true;
             }
+            ConnState::Connecting | ConnState::Closed => {}
         }
         confirm(Confirm::Error("Connection is not open.".into()));
+        false
+    };
+
+    // Whether to ask if the player means to place one stone and pass.
+    let one_stone_prompt = RwSignal::new(false);
+
+    // Dismisses the prompt once the tentative stones change.
+    Effect::new(move || {
+        tentatives_pos.track();
+        one_stone_prompt.set(false);
+    });
+
+    // Number of our moves at the end of the record that are made locally
+    // but not yet echoed by the server.
+    let provisional = RwSignal::new(0_usize);
+
+    // Sends a move to the server and makes it locally at once,
+    // so that it shows up without waiting for the round trip.
+    let play = move |mov: Move| {
+        // Moves are not queued, as the record may change before we are back.
+        if let ConnState::Reconnecting(_) = conn_state.get_untracked() {
+            show_toast("Reconnecting, move not sent".into());
+            return;
+        }
+        let sent = send(match mov {
+            Move::Place(p1, p2) => ClientMessage::Place(p1, p2),
+            Move::Pass => ClientMessage::Pass,
+            Move::Win(p, dir) => ClientMessage::ClaimWin(p, dir),
+            Move::Draw | Move::Resign(_) => unreachable!(),
+        });
+        // The server makes claims in their canonical form.
+        if sent && record.write().make_move(mov.normalize_win()) {
+            provisional.update(|n| *n += 1);
+        }
+    };
+
+    // Takes back the provisional moves (if any), which the server did not make.
+    let drop_provisional = move |record: &mut Record| {
+        let n = provisional.get_untracked();
+        if n == 0 {
+            return;
+        }
+        for _ in 0..n {
+            record.undo_move();
+        }
+        record.truncate_future(0);
+        provisional.set(0);
+    };
+
+    let last_shared_cursor = StoredValue::new(None::<Point>);
+
+    // Shares our cursor at a low rate while enabled, and hides it when disabled.
+    let share_cursor_handle = set_interval_with_handle(
+        move || {
+            let cursor = share_cursor
+                .get_untracked()
+                .then(|| cursor_pos.get_untracked())
+                .flatten();
+            if cursor == last_shared_cursor.get_value()
+                || stone.get_untracked().is_none()
+                || conn_state.get_untracked() != ConnState::Open
+            {
+                return;
+            }
+
+            if send_now(ClientMessage::Cursor(cursor)) {
+                last_shared_cursor.set_value(cursor);
+            }
+        },
+        CURSOR_SHARE_INTERVAL,
+    )
+    .unwrap();
+
+    // Returns the message to join the current game again, if any.
+    let rejoin_msg = move || {
+        let id = game_id.read_untracked();
+        let id = id.strip_suffix(WATCH_SUFFIX).unwrap_or(&id);
+        GameId::try_from(id.as_bytes())
+            .ok()
+            .map(ClientMessage::Join)
     };
 
     let on_close = move |ev: CloseEvent| {
         let code = ev.code();
+
+        // Reconnect if the connection is lost after being open,
+        // backing off exponentially between attempts.
+        if code == CLOSE_CODE_ABNORMAL && rejoin_msg().is_some() {
+            let attempts = match conn_state.get_untracked() {
+                ConnState::Open => Some(0),
+                ConnState::Reconnecting(n) if n < MAX_RECONNECT_ATTEMPTS => Some(n),
+                _ => None,
+            };
+            if let Some(n) = attempts {
+                conn_state.set(ConnState::Reconnecting(n + 1));
+                let handle = set_timeout_with_handle(
+                    move || reconnect.notify(),
+                    RECONNECT_BASE_DELAY * 2_u32.pow(n),
+                )
+                .ok();
+                reconnect_timeout.set_value(handle);
+                return;
+            }
+        }
         let mut reason = ev.reason();
 
+        // Watch the game instead if we fail to sign in again on reconnecting.
+        if let ConnState::Reconnecting(_) = conn_state.get_untracked() {
+            if passcode.with_value(Option::is_some) && SIGN_IN_FAILURES.contains(&&*reason) {
+                passcode.set_value(None);
+                stone.set(None);
+                outbox.write_value().clear();
+                show_toast(format!("{reason} Watching instead.").into());
+                reconnect.notify();
+                return;
+            }
+        }
+
+        conn_state.set(ConnState::Closed);
+        outbox.write_value().clear();
+
         if reason.is_empty() {
             if code == CLOSE_CODE_ABNORMAL {
                 reason = "Closed abnormally.".into();
@@ -154,6 +581,17 @@ pub fn App() -> impl IntoView {
         confirm(Confirm::ConnClosed(reason));
     };
 
+    // Marks the pending request as declined, which hides its banner.
+    let decline_request = move |req: Request| {
+        if let Some(entry) = request_log
+            .write()
+            .iter_mut()
+            .rfind(|entry| entry.req == req && entry.outcome.is_none())
+        {
+            entry.outcome = Some(RequestOutcome::Declined);
+        }
+    };
+
     let confirm_request = move |req: Request| {
         confirm(if who_requested(req).is_some() {
             Confirm::Accept(req)
@@ -170,10 +608,22 @@ pub fn App() -> impl IntoView {
             record: record.read_only(),
             win_claim: win_claim.read_only(),
             requests: requests.read_only(),
+            heatmap: heatmap.read_only(),
+            setup: setup.read_only(),
+            split: split.read_only(),
+            sync_split: sync_split.read_only(),
+            follow_live: follow_live.read_only(),
+            share_cursor: share_cursor.read_only(),
+            show_shared_cursors: show_shared_cursors.read_only(),
+            compare_with: compare_with.read_only(),
+            double_tap: double_tap.read_only(),
+            has_request_log: !request_log.read().is_empty(),
         }));
     };
 
     let first_msg_seen = StoredValue::new(false);
+    // The farewell message received right before a resignation.
+    let farewell = StoredValue::new(None::<String>);
 
     let on_message = move |ev: MessageEvent| {
         let Some(msg) = ev
@@ -189,6 +639,37 @@ pub fn App() -> impl IntoView {
             return;
         };
 
+        // The request accepted by the message (if any).
+        let accepted = match msg {
+            ServerMessage::Move(Move::Draw) => Some(Request::Draw),
+            ServerMessage::Retract => Some(Request::Retract),
+            ServerMessage::Record(_) if first_msg_seen.get_value() => Some(Request::Reset),
+            _ => None,
+        };
+
+        let ending = matches!(msg, ServerMessage::Move(mov) if mov.is_ending());
+
+        // We are back once joined again, and signed in again if playing.
+        let reconnecting = matches!(conn_state.get_untracked(), ConnState::Reconnecting(_));
+        let back = reconnecting
+            && (matches!(msg, ServerMessage::Started(..)) || passcode.with_value(Option::is_none));
+
+        // Our retract request is declined if the opponent makes a move instead.
+        if let ServerMessage::Move(mov) = msg {
+            let mover = match mov {
+                Move::Place(..) | Move::Pass => record.read_untracked().turn(),
+                // The winner is most likely the one claiming.
+                Move::Win(p, _) => record.read_untracked().stone_at(p),
+                Move::Draw => who_requested(Request::Draw).map(Stone::opposite),
+                Move::Resign(s) => Some(s),
+            };
+            if let Some(s) = stone.get() {
+                if who_requested(Request::Retract) == Some(s) && mover == Some(s.opposite()) {
+                    retract_declined_at.set_value(Some(Date::now()));
+                }
+            }
+        }
+
         let mut record_changed = false;
         match msg {
             ServerMessage::Started(our_stone, new_game_id) => {
@@ -200,30 +681,117 @@ pub fn App() -> impl IntoView {
                     history_push_state(&format!("#{id}"));
 
                     show_game_menu_dialog();
+                } else {
+                    restore_tentatives();
                 }
+                // Remember the game to rejoin it from the main menu.
+                storage::push_recent_game(&game_id.get_untracked(), our_stone);
                 for req in Request::VALUES {
                     if who_requested(req) == Some(our_stone.opposite()) {
                         confirm_request(req);
                     }
                 }
             }
-            ServerMessage::Record(new_record) => {
+            ServerMessage::Record(mut new_record) => {
+                provisional.set(0);
+
+                // Stay put if browsing the history and the moves up to there are unchanged.
+                if first_msg_seen.get_value() && stone.get().is_none() && !follow_live.get() {
+                    let old = record.read_untracked();
+                    let index = old.move_index();
+                    if new_record.moves().get(..index) == Some(&old.moves()[..index]) {
+                        new_record.jump(index);
+                    }
+                }
                 record.set(*new_record);
-                if !first_msg_seen.get_value() {
+                if !first_msg_seen.get_value()
+                    && !reconnecting
+                    && !game_id.read().ends_with(WATCH_SUFFIX)
+                {
                     show_dialog(Dialog::from(JoinDialog));
                 }
                 record_changed = true;
             }
             ServerMessage::Move(mov) => {
-                record.write().make_move(mov);
+                let mut record = record.write();
+                let n = provisional.get_untracked();
+                if n > 0 && record.moves()[record.moves().len() - n] == mov {
+                    // The earliest provisional move is confirmed.
+                    provisional.set(n - 1);
+                } else {
+                    drop_provisional(&mut record);
+                    record.push_move(mov);
+
+                    // Stay put if browsing the history.
+                    if stone.get().is_some() || follow_live.get() {
+                        let len = record.moves().len();
+                        record.jump(len);
+                    }
+                }
                 record_changed = true;
             }
             ServerMessage::Retract => {
-                record.write().undo_move();
+                let mut record = record.write();
+                if stone.get().is_none() {
+                    // Drop the retracted move, which may be in the future when browsing.
+                    let index = record.move_index();
+                    let moves = &record.moves()[..record.moves().len() - 1];
+                    let mut new_record = Record::new();
+                    _ = new_record.make_moves(moves);
+                    if !follow_live.get() {
+                        new_record.jump(index.min(moves.len()));
+                    }
+                    *record = new_record;
+                } else {
+                    drop_provisional(&mut record);
+                    record.undo_move();
+                }
                 record_changed = true;
             }
+            ServerMessage::TransferCode(ref code) => {
+                confirm(Confirm::TransferCode(
+                    String::from_utf8_lossy(code).into_owned(),
+                ));
+            }
+            ServerMessage::Passcode(ref p) => {
+                // Sign in with it on reconnecting, as the transfer code is used up.
+                passcode.set_value(Some(p.clone()));
+            }
+            ServerMessage::RetractCooldown(cooldown) => {
+                retract_cooldown.set_value(cooldown);
+                // Sent before the record on joining, which is still taken as the first message.
+                return;
+            }
+            ServerMessage::Farewell(farewell_stone, ref message) => {
+                farewell.set_value(Some(format!(
+                    "{farewell_stone:?} says: \u{201c}{message}\u{201d}"
+                )));
+            }
+            ServerMessage::Cursor(cursor_stone, p) => {
+                if stone.get() != Some(cursor_stone) && show_shared_cursors.get() {
+                    shared_cursors.write()[cursor_stone.index()] = p;
+                }
+            }
             ServerMessage::Request(req_stone, req) => {
                 requests.write()[req as usize] = Some(req_stone);
+
+                // The request might already be logged before reconnecting.
+                let mut log = request_log.write();
+                if !log.iter().any(|entry| {
+                    entry.stone == req_stone && entry.req == req && entry.outcome.is_none()
+                }) {
+                    if log.len() == REQUEST_LOG_CAPACITY {
+                        log.remove(0);
+                    }
+                    log.push(RequestLogEntry {
+                        stone: req_stone,
+                        req,
+                        move_index: record.read_untracked().moves().len(),
+                        outcome: None,
+                    });
+                }
+                drop(log);
+
                 if stone.get() == Some(req_stone.opposite()) {
                     confirm_request(req);
                 }
@@ -234,21 +802,42 @@ pub fn App() -> impl IntoView {
             // Clear the requests if the record changed.
             requests.write().fill(None);
 
+            // Resolve pending requests, except when the record is sent on joining.
+            if first_msg_seen.get_value() {
+                for entry in request_log.write().iter_mut() {
+                    if entry.outcome.is_none() {
+                        entry.outcome = Some(if Some(entry.req) == accepted {
+                            RequestOutcome::Accepted
+                        } else {
+                            RequestOutcome::Expired
+                        });
+                    }
+                }
+            }
+
             // Also clear all confirm dialogs.
-            let mut entries = dialog_entries.write();
-            let mut removed = false;
+            let mut dialogs = dialogs.write();
+            if !dialogs.retain(|dialog| !matches!(dialog, Dialog::Confirm(_))) {
+                dialogs.untrack();
+            }
+            drop(dialogs);
 
-            entries.retain(|entry| match &entry.dialog {
-                Dialog::Confirm(ConfirmDialog(_)) => {
-                    removed = true;
-                    false
-                }
-                _ => true,
-            });
+            // Offer to analyze the game once it ends live.
+            if ending && first_msg_seen.get_value() {
+                let result = describe_result(&record.read_untracked()).unwrap_or_default();
+                let farewell = farewell.write_value().take();
+                confirm(Confirm::GameOver(result, stone.get(), farewell));
+            }
+        }
 
-            if !removed {
-                entries.untrack();
+        if back {
+            conn_state.set(ConnState::Open);
+            // Share our cursor again, which the server has forgotten.
+            last_shared_cursor.set_value(None);
+            for msg in mem::take(&mut *outbox.write_value()) {
+                send_now(msg);
             }
+            show_toast("Reconnected".into());
         }
 
         first_msg_seen.set_value(true);
@@ -265,7 +854,18 @@ pub fn App() -> impl IntoView {
         let ws = WebSocket::new(&format!("{proto}//{host}/ws")).unwrap();
         ws.set_binary_type(BinaryType::Arraybuffer);
 
-        let onopen = Closure::once(move || send(init_msg));
+        let onopen = Closure::once(move || {
+            if conn_state.get_untracked() == ConnState::Connecting {
+                conn_state.set(ConnState::Open);
+            }
+            send_now(init_msg);
+            // Sign in again on reconnecting.
+            if let ConnState::Reconnecting(_) = conn_state.get_untracked() {
+                if let Some(passcode) = passcode.get_value() {
+                    send_now(ClientMessage::Start(passcode));
+                }
+            }
+        });
         ws.set_onopen(Some(onopen.as_ref().unchecked_ref()));
 
         let onclose = Closure::<dyn Fn(CloseEvent)>::new(on_close);
@@ -284,6 +884,16 @@ pub fn App() -> impl IntoView {
         }));
     };
 
+    // Reconnects to the game after a delay, see `on_close`.
+    Effect::new(move || {
+        reconnect.track();
+        if let ConnState::Reconnecting(_) = conn_state.get_untracked() {
+            if let Some(msg) = rejoin_msg() {
+                connect(msg);
+            }
+        }
+    });
+
     let set_game_id = move |id: &str| {
         if let Some(ws_state) = ws_state.write_value().take() {
             let ws = ws_state.ws;
@@ -292,58 +902,118 @@ pub fn App() -> impl IntoView {
             ws.set_onmessage(None);
             ws.close().unwrap();
         }
+        if let Some(handle) = reconnect_timeout.write_value().take() {
+            handle.clear();
+        }
+        conn_state.set(ConnState::Connecting);
+        outbox.write_value().clear();
+        passcode.set_value(None);
 
         requests.write().fill(None);
-        dialog_entries.write().clear();
+        dialogs.write().clear();
+        heatmap.set(false);
+        setup.set(false);
+        annotations.set(Annotations::default());
+        compare_with.set(None);
+        split.set(false);
+        share_cursor.set(false);
+        shared_cursors.set([None; 2]);
+        last_shared_cursor.set_value(None);
+        provisional.set(0);
 
         if location_hash().as_deref() != Some(id) {
             history_push_state(&format!("#{id}"));
         }
 
+        if game_id.get_untracked() == "pass" {
+            // Undo the turning around in pass & play mode.
+            orientation.set(Orientation::default());
+        }
+        // Keep the request log when reconnecting to the same game.
+        if game_id.get_untracked() != id {
+            request_log.write().clear();
+            retract_declined_at.set_value(None);
+        }
         game_id.set(id.into());
 
         stone.set(None);
+        decode_progress.set(None);
+        decode_generation.update_value(|gen| *gen = gen.wrapping_add(1));
 
         if id.is_empty() {
             record.write().clear();
-            show_dialog(Dialog::from(MainMenuDialog));
+            show_main_menu_dialog();
             return;
         }
 
-        if id == "local" {
-            if let Some(decoded_record) = local_storage()
-                .get_item(STORAGE_KEY_RECORD)
-                .unwrap()
-                .and_then(|buf| BASE64_STANDARD.decode(buf).ok())
-                .and_then(|buf| Record::decode(&mut &buf[..], true))
-            {
+        if let Some(key) = storage::Key::for_game(id) {
+            if let Some(decoded_record) = storage::get_record(key) {
                 record.set(decoded_record);
             } else {
                 record.write().clear();
             }
             stone.set(record.read().turn());
+
+            if id == "pass" {
+                // Face the player to move, assuming Black sits at the bottom.
+                let mut o = Orientation::default();
+                if stone.get() == Some(Stone::White) {
+                    o = o.rotated().rotated();
+                }
+                orientation.set(o);
+            }
             return;
         }
 
         if let Some(buf) = id.strip_prefix(ANALYZE_PREFIX) {
-            if let Some(decoded_record) = BASE64_STANDARD
-                .decode(buf)
-                .ok()
-                .and_then(|buf| Record::decode(&mut &buf[..], false))
+            let on_decoded = move |decoded_record: Option<Record>| {
+                if let Some(decoded_record) = decoded_record {
+                    record.set(decoded_record);
+                    stone.set(record.read().turn());
+                } else {
+                    confirm(Confirm::Error("Corrupted record.".into()));
+                }
+            };
+
+            let Ok(buf) = BASE64_STANDARD.decode(buf) else {
+                on_decoded(None);
+                return;
+            };
+            let mut rest = &buf[..];
+            let decoder = RecordDecoder::new(&mut rest, false).unwrap();
+            let pos = buf.len() - rest.len();
+
+            record.write().clear();
+            decode_progress.set(Some(0.0));
+            let gen = (decode_generation, decode_generation.get_value());
+            decode_progressively(buf, pos, decoder, decode_progress, gen, on_decoded);
+            return;
+        }
+
+        if let Some(bufs) = id.strip_prefix(COMPARE_PREFIX) {
+            // Base64 strings never contain commas.
+            if let Some((a, b)) = bufs
+                .split_once(',')
+                .and_then(|(a, b)| Some((decode_record(a)?, decode_record(b)?)))
             {
-                record.set(decoded_record);
+                record.set(a);
+                compare_with.set(Some(b));
                 stone.set(record.read().turn());
             } else {
-                confirm(Confirm::Error("Failed to decode record.".into()));
+                confirm(Confirm::Error("Corrupted records.".into()));
             }
             return;
         }
 
         #[cfg(feature = "online")]
-        if let Ok(id) = c6ol_core::protocol::GameId::try_from(id.as_bytes()) {
-            if id.iter().all(u8::is_ascii_alphanumeric) {
-                connect(ClientMessage::Join(id));
-                return;
+        {
+            // A watch link connects without asking for a passcode.
+            let id = id.strip_suffix(WATCH_SUFFIX).unwrap_or(id);
+            if let Ok(id) = GameId::try_from(id.as_bytes()) {
+                if id.iter().all(u8::is_ascii_alphanumeric) {
+                    connect(ClientMessage::Join(id));
+                    return;
+                }
             }
         }
 
@@ -358,12 +1028,36 @@ pub fn App() -> impl IntoView {
             Event::Submit => {
                 let tentatives = tentatives_pos.get();
                 let claim = win_claim.get();
+
+                // Ask inline first if only one of two stones is placed.
+                let claiming = matches!(claim, Some(WinClaim::Ready(..)));
+                if !claiming
+                    && tentatives.len() == 1
+                    && record.read().max_stones_to_play() == 2
+                    && !one_stone_prompt.get_untracked()
+                {
+                    one_stone_prompt.set(true);
+                    return;
+                }
+                one_stone_prompt.set(false);
+
                 if online() {
                     confirm(match claim {
-                        Some(WinClaim::Ready(p, dir)) => Confirm::Claim(tentatives, p, dir),
+                        Some(WinClaim::Ready(p, dir)) => {
+                            let owner = if tentatives.contains(&p) {
+                                stone.get()
+                            } else {
+                                record.read().stone_at(p)
+                            };
+                            Confirm::Claim(tentatives, p, dir, owner != stone.get())
+                        }
                         _ => match tentatives[..] {
-                            [] => Confirm::Pass(None),
-                            [p] if record.read().has_past() => Confirm::Pass(Some(p)),
+                            [] => Confirm::Pass,
+                            // Already confirmed inline.
+                            [p] if record.read().has_past() => {
+                                play(Move::Place(p, None));
+                                return;
+                            }
                             [p] => Confirm::Submit(p, None),
                             [p1, p2] => Confirm::Submit(p1, Some(p2)),
                             _ => unreachable!(),
@@ -390,11 +1084,35 @@ pub fn App() -> impl IntoView {
                     record_changed = true;
                 }
             }
+            Event::ClaimWin => {
+                if win_claim.get().is_none() {
+                    win_claim.set(Some(WinClaim::PendingPoint));
+                    if online() {
+                        confirm(Confirm::BeginClaim);
+                    }
+                } else {
+                    win_claim.set(None);
+                }
+            }
             Event::Undo => {
                 if !record.read().has_past() {
                     return;
                 }
-                if online() {
+                if spectating() {
+                    record.write().undo_move();
+                } else if online() {
+                    if who_requested(Request::Retract).is_none() {
+                        // The server rejects requests made too soon after one is declined.
+                        let cooldown_ms = retract_cooldown.get_value().as_millis() as f64;
+                        let remaining_ms = retract_declined_at
+                            .get_value()
+                            .map_or(0.0, |t| t + cooldown_ms - Date::now());
+                        if remaining_ms > 0.0 {
+                            let secs = (remaining_ms / 1000.0).ceil();
+                            show_toast(format!("Retract declined, try again in {secs}s").into());
+                            return;
+                        }
+                    }
                     if who_requested(Request::Retract) != stone.get() {
                         confirm_request(Request::Retract);
                     }
@@ -407,7 +1125,9 @@ pub fn App() -> impl IntoView {
                 if !record.read().has_future() {
                     return;
                 }
-                if !online() {
+                if spectating() {
+                    record.write().redo_move();
+                } else if !online() {
                     record.write().redo_move();
                     record_changed = true;
                 }
@@ -416,11 +1136,14 @@ pub fn App() -> impl IntoView {
                 if !record.read().has_past() {
                     return;
                 }
-                if online() {
+                if spectating() {
+                    record.write().jump(0);
+                } else if online() {
                     if who_requested(Request::Reset) != stone.get() {
                         confirm_request(Request::Reset);
                     }
                 } else {
+                    history_push_jump(record.read().move_index(), 0);
                     record.write().jump(0);
                     record_changed = true;
                 }
@@ -429,10 +1152,20 @@ pub fn App() -> impl IntoView {
                 if !record.read().has_future() {
                     return;
                 }
-                if !online() {
+                if spectating() || !online() {
                     let mut record = record.write();
                     let len = record.moves().len();
+                    if !online() {
+                        history_push_jump(record.move_index(), len);
+                    }
                     record.jump(len);
+                    record_changed = !online();
+                }
+            }
+            Event::Jump(index) => {
+                if spectating() {
+                    record.write().jump(index);
+                } else if !online() && record.write().jump(index) {
                     record_changed = true;
                 }
             }
@@ -455,10 +1188,51 @@ pub fn App() -> impl IntoView {
                     record_changed = true;
                 }
             }
+            Event::Setup(p) => {
+                let mut black = vec![];
+                let mut white = vec![];
+                let old = record.read();
+                for turn in old.turns().take(old.move_index()) {
+                    let stones = match turn.stone {
+                        Stone::Black => &mut black,
+                        Stone::White => &mut white,
+                    };
+                    stones.extend(turn.placed().filter(|&q| q != p));
+                }
+                match old.stone_at(p) {
+                    None => black.push(p),
+                    Some(Stone::Black) => white.push(p),
+                    Some(Stone::White) => {}
+                }
+                drop(old);
+
+                let new_record =
+                    Record::from_position(&black, &white).expect("points should be distinct");
+                record.set(new_record);
+                record_changed = true;
+            }
+            Event::NotYourTurn => show_toast("Not your turn".into()),
+            Event::IllegalMove(err) => show_toast(
+                match err {
+                    MoveError::Ended => "Game is ended",
+                    MoveError::FirstMoveTwoStones => "First move is a single stone",
+                    MoveError::Occupied => "Square occupied",
+                    MoveError::InvalidWinningRow => "Invalid winning row",
+                }
+                .into(),
+            ),
         }
 
         if record_changed {
-            stone.set(record.read().turn());
+            let turn = record.read().turn();
+            if let Some(next) = turn {
+                if *game_id.read() == "pass" && turn != stone.get() {
+                    // Turn the board around for the other player.
+                    orientation.update(|o| *o = o.rotated().rotated());
+                    confirm(Confirm::HandOver(next));
+                }
+            }
+            stone.set(turn);
         }
     };
 
@@ -478,45 +1252,124 @@ pub fn App() -> impl IntoView {
         GameMenuRetVal::Redo => on_event(Event::Redo),
         GameMenuRetVal::Home => on_event(Event::Home),
         GameMenuRetVal::End => on_event(Event::End),
-        GameMenuRetVal::ClaimWin => {
-            if win_claim.get().is_none() {
-                win_claim.set(Some(WinClaim::PendingPoint));
-                if online() {
-                    confirm(Confirm::BeginClaim);
-                }
-            } else {
-                win_claim.set(None);
-            }
-        }
+        GameMenuRetVal::ClaimWin => on_event(Event::ClaimWin),
         GameMenuRetVal::Resign => on_event(Event::Resign),
         GameMenuRetVal::Submit => on_event(Event::Submit),
         GameMenuRetVal::Draw => on_event(Event::Draw),
+        GameMenuRetVal::Rotate => orientation.update(|o| *o = o.rotated()),
+        GameMenuRetVal::Flip => orientation.update(|o| *o = o.flipped()),
+        GameMenuRetVal::Heatmap => heatmap.update(|on| *on = !*on),
+        GameMenuRetVal::Setup => {
+            let on = !setup.get();
+            setup.set(on);
+            if on {
+                tentatives_pos.write().clear();
+                win_claim.set(None);
+            }
+        }
+        GameMenuRetVal::Split => {
+            let on = !split.get();
+            if on {
+                other_record.set(compare_with.get().unwrap_or_default());
+            }
+            split.set(on);
+        }
+        GameMenuRetVal::SyncSplit => sync_split.update(|on| *on = !*on),
+        GameMenuRetVal::ShareCursor => share_cursor.update(|on| *on = !*on),
+        GameMenuRetVal::ShowCursors => {
+            let on = !show_shared_cursors.get();
+            show_shared_cursors.set(on);
+            if !on {
+                shared_cursors.set([None; 2]);
+            }
+        }
+        GameMenuRetVal::FollowLive => {
+            let on = !follow_live.get();
+            follow_live.set(on);
+            if on {
+                on_event(Event::End);
+            }
+        }
+        GameMenuRetVal::DoubleTap => {
+            let on = !double_tap.get();
+            double_tap.set(on);
+            // Remember the setting across games and sessions.
+            if on {
+                _ = storage::set(storage::Key::DoubleTap, "");
+            } else {
+                storage::remove(storage::Key::DoubleTap);
+            }
+        }
+        GameMenuRetVal::Gestures => show_dialog(Dialog::from(GesturesDialog(gestures.get()))),
+        GameMenuRetVal::JoinCode => {
+            let path = format!("/api/games/{}/join-code", game_id.read_untracked());
+            spawn_local(async move {
+                match fetch_api("POST", &path).await {
+                    Some(code) => confirm(Confirm::JoinCode(code)),
+                    None => show_toast("Failed to get a join code".into()),
+                }
+            });
+        }
+        GameMenuRetVal::Transfer => {
+            send(ClientMessage::Transfer);
+        }
+        GameMenuRetVal::Requests => show_dialog(Dialog::from(RequestLogDialog {
+            log: request_log.read_only(),
+        })),
+        GameMenuRetVal::ExportGif => {
+            let data = replay::encode_gif(&record.read_untracked(), &annotations.read_untracked());
+            let name = if online() {
+                let id = game_id.read_untracked();
+                format!("c6ol-{}.gif", id.strip_suffix(WATCH_SUFFIX).unwrap_or(&id))
+            } else {
+                "c6ol.gif".into()
+            };
+            replay::download(&data, &name, "image/gif");
+        }
     };
 
     let on_dialog_return = move |id: u32, ret_val: RetVal| {
-        // We can't simply `pop`, because if we right click as soon as we close
-        // the game menu, `show_dialog` can get called before `on_dialog_return`.
-        // This is similar to how a `pointerover` event gets fired before a
-        // `close` event when a dialog is closed with a pointer (see comments at
-        // `on_hover` in `game_menu.rs`).
-        let mut entries = dialog_entries.write();
-        let i = (0..entries.len()).rfind(|&i| entries[i].id == id).unwrap();
-        let dialog = entries.remove(i).dialog;
-        drop(entries);
+        // Ignore a dialog already dismissed along with others.
+        let Some(dialog) = dialogs.write().remove(id) else {
+            return;
+        };
 
         match ret_val {
             RetVal::MainMenu(ret_val) => match ret_val {
                 MainMenuRetVal::Offline => set_game_id("local"),
+                MainMenuRetVal::PassAndPlay => set_game_id("pass"),
+                #[cfg(feature = "online")]
                 MainMenuRetVal::Online => {
                     show_dialog(Dialog::from(OnlineMenuDialog));
                 }
+                MainMenuRetVal::Rejoin(game_id) => set_game_id(&game_id),
             },
             RetVal::OnlineMenu(ret_val) => match ret_val {
                 OnlineMenuRetVal::Cancel => {
-                    show_dialog(Dialog::from(MainMenuDialog));
+                    show_main_menu_dialog();
                 }
-                OnlineMenuRetVal::Start(passcode) => {
-                    connect(ClientMessage::Start(passcode.into_bytes().into()));
+                OnlineMenuRetVal::Start(passcode, passcode_white) => {
+                    // The server rejects equal passcodes, telling why.
+                    connect(if passcode_white.is_empty() {
+                        ClientMessage::Start(passcode.into_bytes().into())
+                    } else {
+                        ClientMessage::StartReserved(
+                            passcode.into_bytes().into(),
+                            passcode_white.into_bytes().into(),
+                        )
+                    });
+                }
+                OnlineMenuRetVal::Join(code) if is_join_code(&code) => {
+                    spawn_local(async move {
+                        if let Some(game_id) =
+                            fetch_api("POST", &format!("/api/join-codes/{code}")).await
+                        {
+                            set_game_id(&game_id);
+                        } else {
+                            show_toast("Join code not found, expired, or used".into());
+                            show_dialog(Dialog::from(OnlineMenuDialog));
+                        }
+                    });
                 }
                 OnlineMenuRetVal::Join(game_id) => set_game_id(&game_id),
             },
@@ -527,11 +1380,30 @@ pub fn App() -> impl IntoView {
                 }
             },
             RetVal::GameMenu(ret_val) => on_game_menu_return(ret_val),
+            RetVal::RequestLog(ret_val) => match ret_val {
+                RequestLogRetVal::Close => {}
+                RequestLogRetVal::Back => show_game_menu_dialog(),
+            },
+            RetVal::Gestures(ret_val) => match ret_val {
+                GesturesRetVal::Back => show_game_menu_dialog(),
+                GesturesRetVal::Save(new_gestures) => {
+                    gestures.set(new_gestures);
+                    // Remember the setting across games and sessions.
+                    _ = storage::set(
+                        storage::Key::Gestures,
+                        &ron::to_string(&new_gestures).unwrap(),
+                    );
+                }
+            },
             RetVal::Confirm(ret_val) => {
                 let Dialog::Confirm(ConfirmDialog(confirm)) = dialog else {
                     unreachable!();
                 };
 
+                if let (Confirm::Accept(req), ConfirmRetVal::Cancel) = (&confirm, &ret_val) {
+                    decline_request(*req);
+                }
+
                 if !matches!(confirm, Confirm::ConnClosed(_) | Confirm::Error(_))
                     && ret_val == ConfirmRetVal::Cancel
                 {
@@ -540,26 +1412,36 @@ pub fn App() -> impl IntoView {
 
                 match confirm {
                     Confirm::MainMenu => set_game_id(""),
-                    Confirm::Submit(p1, p2) => send(ClientMessage::Place(p1, p2)),
-                    Confirm::Pass(None) => send(ClientMessage::Pass),
-                    Confirm::Pass(Some(p)) => send(ClientMessage::Place(p, None)),
+                    Confirm::Submit(p1, p2) => play(Move::Place(p1, p2)),
+                    Confirm::Pass => play(Move::Pass),
                     Confirm::BeginClaim => {}
-                    Confirm::Claim(tentatives, p, dir) => {
+                    Confirm::Claim(tentatives, p, dir, _) => {
                         if !tentatives.is_empty() {
-                            send(ClientMessage::Place(
-                                tentatives[0],
-                                tentatives.get(1).copied(),
-                            ));
+                            play(Move::Place(tentatives[0], tentatives.get(1).copied()));
                         }
-                        send(ClientMessage::ClaimWin(p, dir));
+                        play(Move::Win(p, dir));
                     }
                     Confirm::Request(req) | Confirm::Accept(req) => {
                         send(ClientMessage::Request(req));
                     }
-                    Confirm::Resign => send(ClientMessage::Resign),
+                    Confirm::Resign => {
+                        let farewell = match ret_val {
+                            ConfirmRetVal::Resign(farewell) if !farewell.trim().is_empty() => {
+                                Some(farewell.trim().into())
+                            }
+                            _ => None,
+                        };
+                        send(ClientMessage::Resign(farewell));
+                    }
+                    Confirm::HandOver(_) | Confirm::TransferCode(_) | Confirm::JoinCode(_) => {}
+                    Confirm::GameOver(..) => {
+                        let mut buf = vec![];
+                        record.read().encode_with_checksum(&mut buf, false);
+                        set_game_id(&format!("{ANALYZE_PREFIX}{}", BASE64_STANDARD.encode(buf)));
+                    }
                     Confirm::ConnClosed(_) => match ret_val {
                         ConfirmRetVal::Cancel => set_game_id(""),
-                        ConfirmRetVal::Confirm => set_game_id(&game_id.get()),
+                        _ => set_game_id(&game_id.get()),
                     },
                     Confirm::Error(_) => set_game_id(""),
                 }
@@ -568,40 +1450,413 @@ pub fn App() -> impl IntoView {
     };
 
     let on_hash_change = move || {
-        set_game_id(location_hash().as_deref().unwrap_or_default());
+        let id = location_hash().unwrap_or_default();
+        // The game may have been switched on `popstate`.
+        if *game_id.read_untracked() != id {
+            set_game_id(&id);
+        }
     };
     on_hash_change();
 
+    // Handles `popstate` events, which are fired before `hashchange` events.
+    let handle_popstate = window_event_listener(ev::popstate, move |ev| {
+        on_hash_change();
+        if let Some(index) = ev.state().as_f64() {
+            on_event(Event::Jump(index as usize));
+        }
+    });
+
     let handle_hashchange = window_event_listener(ev::hashchange, move |_| on_hash_change());
 
     let handle_storage = window_event_listener(ev::storage, move |ev| {
-        if *game_id.read() == "local" && ev.key().as_deref() == Some(STORAGE_KEY_RECORD) {
-            if let Some(buf) = ev
-                .new_value()
-                .and_then(|buf| BASE64_STANDARD.decode(buf).ok())
-                .and_then(|buf| Record::decode(&mut &buf[..], true))
-            {
+        let key = storage::Key::for_game(&game_id.read());
+        if key.is_some_and(|key| ev.key().as_deref() == Some(&key.name())) {
+            if let Some(buf) = ev.new_value().and_then(|buf| storage::decode_record(&buf)) {
                 record.set(buf);
                 stone.set(record.read().turn());
             }
         }
     });
 
+    // Asks for confirmation before leaving an online game
+    // where we have tentative stones or it is our turn.
+    let handle_beforeunload = window_event_listener(ev::beforeunload, move |ev| {
+        let record = record.read();
+        if online()
+            && stone.read().is_some()
+            && (!tentatives_pos.read().is_empty() || record.turn() == stone.get())
+        {
+            ev.prevent_default();
+        }
+    });
+
     on_cleanup(move || {
         handle_hashchange.remove();
         handle_storage.remove();
+        handle_beforeunload.remove();
+        handle_popstate.remove();
+        share_cursor_handle.clear();
+    });
+
+    // Reflect in the title what awaits us in an online game,
+    // so that it can be seen from another tab.
+    Effect::new(move || {
+        let status = match stone.get() {
+            Some(our_stone) if online() => {
+                let record = record.read();
+                if record.is_ended() {
+                    Some("Game over")
+                } else if Request::VALUES
+                    .into_iter()
+                    .any(|req| who_requested(req) == Some(our_stone.opposite()))
+                {
+                    Some("\u{25cf} Opponent's request")
+                } else if record.turn() == Some(our_stone) {
+                    Some("\u{25cf} Your move")
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+        document().set_title(&match status {
+            Some(status) => format!("{status} \u{2014} {TITLE}"),
+            None => TITLE.into(),
+        });
+    });
+
+    // Shows the toolbar unless in the main menu or viewing an online game.
+    let toolbar_shown = move || {
+        let id = game_id.read();
+        !id.is_empty()
+            && (stone.read().is_some()
+                || storage::Key::for_game(&id).is_some()
+                || id.starts_with(ANALYZE_PREFIX)
+                || id.starts_with(COMPARE_PREFIX))
+    };
+
+    let toolbar_view = move || {
+        let ended = move || record.read().is_ended();
+        view! {
+            // Prevent the buttons from taking focus from the board.
+            <div id="toolbar" class="btn-group" on:mousedown=|ev| ev.prevent_default()>
+                <button
+                    on:click=move |_| on_event(Event::Undo)
+                    disabled=move || !record.read().has_past()
+                >
+                    "Undo"
+                </button>
+                <button
+                    on:click=move |_| on_event(Event::ClaimWin)
+                    class:pushed=move || win_claim.read().is_some()
+                    disabled=ended
+                >
+                    "Claim Win"
+                </button>
+                <button
+                    on:click=move |_| on_event(Event::Submit)
+                    disabled=move || {
+                        ended()
+                            || (record.read().turn() != stone.get()
+                                && !matches!(win_claim.get(), Some(WinClaim::Ready(..))))
+                    }
+                >
+                    "Submit"
+                </button>
+                <button on:click=move |_| on_event(Event::Menu)>"Menu"</button>
+            </div>
+        }
+    };
+
+    // Shows a timeline of passes, claims and requests along the moves,
+    // where clicking a marker jumps to the position at it.
+    let timeline_view = move || {
+        if game_id.read().is_empty() {
+            return None;
+        }
+        let navigable = spectating() || !online();
+        let jump = move |index: usize| {
+            let from = record.read_untracked().move_index();
+            if from == index {
+                return;
+            }
+            if !online() {
+                history_push_jump(from, index);
+            }
+            on_event(Event::Jump(index));
+        };
+
+        let record = record.read();
+        let len = record.moves().len();
+
+        // Markers as (move index, label, title).
+        let mut markers: Vec<(usize, &str, String)> = record
+            .turns()
+            .filter_map(|turn| {
+                let stone = turn.stone;
+                let (label, title) = match turn.mov {
+                    Move::Place(..) => return None,
+                    Move::Pass => ("P", format!("{stone:?} passed")),
+                    Move::Win(..) => ("W", format!("{stone:?} claimed a win")),
+                    Move::Draw => ("D", "Draw agreed".into()),
+                    Move::Resign(stone) => ("R", format!("{stone:?} resigned")),
+                };
+                Some((turn.index + 1, label, title))
+            })
+            .collect();
+        for entry in &*request_log.read() {
+            let outcome = match entry.outcome {
+                Some(outcome) => format!("{outcome:?}"),
+                None => "Pending".into(),
+            };
+            markers.push((
+                entry.move_index.min(len),
+                "?",
+                format!("{:?} requested {:?} ({outcome})", entry.stone, entry.req),
+            ));
+        }
+        if markers.is_empty() {
+            return None;
+        }
+
+        let current = record.move_index();
+        let left = move |index: usize| format!("{}%", index as f64 / len.max(1) as f64 * 100.0);
+
+        let markers = markers
+            .into_iter()
+            .map(|(index, label, title)| {
+                view! {
+                    <button
+                        class="marker"
+                        class:current=index == current
+                        style:left=left(index)
+                        title=format!("Move {index}: {title}")
+                        disabled=!navigable
+                        on:click=move |_| jump(index)
+                    >
+                        {label}
+                    </button>
+                }
+            })
+            .collect_view();
+        Some(view! {
+            // Prevent the markers from taking focus from the board.
+            <div id="timeline" on:mousedown=|ev| ev.prevent_default()>
+                <div class="cursor" style:left=left(current)></div>
+                {markers}
+            </div>
+        })
+    };
+
+    // Shows whose turn it is, and how many stones are left to place
+    // if it is ours (or anyone's when playing offline).
+    let turn_indicator = move || {
+        if game_id.read().is_empty() {
+            return None;
+        }
+        let record = record.read();
+        let turn = record.turn()?;
+        let text = if online() && stone.get() != Some(turn) {
+            format!("{turn:?} to play")
+        } else {
+            let left = record.max_stones_to_play() - tentatives_pos.read().len();
+            let s = if left == 1 { "" } else { "s" };
+            format!("{turn:?} to play: {left} stone{s} left this turn")
+        };
+        Some(view! { <div class="banner">{text}</div> })
+    };
+
+    let one_stone_prompt_view = move || {
+        one_stone_prompt.get().then(|| {
+            view! {
+                <div class="banner">
+                    <span>"Only one stone placed."</span>
+                    <button on:click=move |_| on_event(Event::Submit)>"Place One and Pass"</button>
+                    <button on:click=move |_| one_stone_prompt.set(false)>"Keep Placing"</button>
+                </div>
+            }
+        })
+    };
+
+    let reconnecting_banner = move || {
+        matches!(conn_state.get(), ConnState::Reconnecting(_))
+            .then(|| view! { <div class="banner">"Connection lost, reconnecting\u{2026}"</div> })
+    };
+
+    let setup_banner = move || {
+        setup.get().then(|| {
+            view! {
+                <div class="banner">
+                    <span>"Setting up: tap to cycle stones"</span>
+                    <button on:click=move |_| setup.set(false)>"Done"</button>
+                </div>
+            }
+        })
+    };
+
+    // Shows a banner for each pending request not declined,
+    // so that it stays visible after its dialog is closed.
+    let request_banners = move || {
+        let our_stone = stone.get();
+        let log = request_log.read();
+        Request::VALUES
+            .into_iter()
+            .filter_map(|req| {
+                let req_stone = who_requested(req)?;
+                let declined = log
+                    .iter()
+                    .rev()
+                    .find(|entry| entry.req == req)
+                    .is_some_and(|entry| matches!(entry.outcome, Some(RequestOutcome::Declined)));
+                if declined {
+                    return None;
+                }
+
+                let ours = Some(req_stone) == our_stone;
+                let text = match (ours, req) {
+                    (true, Request::Draw) => "You offered a draw \u{2014} waiting".into(),
+                    (true, Request::Retract) => {
+                        "You requested to retract the previous move \u{2014} waiting".into()
+                    }
+                    (true, Request::Reset) => {
+                        "You requested to reset the game \u{2014} waiting".into()
+                    }
+                    (false, _) => {
+                        let who = if our_stone.is_some() {
+                            "Opponent".into()
+                        } else {
+                            format!("{req_stone:?}")
+                        };
+                        match req {
+                            Request::Draw => format!("{who} offers a draw"),
+                            Request::Retract => {
+                                format!("{who} requests to retract the previous move")
+                            }
+                            Request::Reset => format!("{who} requests to reset the game"),
+                        }
+                    }
+                };
+
+                // Only the opponent of the requester can answer.
+                let answerable = our_stone == Some(req_stone.opposite());
+                Some(view! {
+                    <div class="banner">
+                        <span>{text}</span>
+                        {answerable
+                            .then(|| {
+                                view! {
+                                    <button on:click=move |_| {
+                                        send(ClientMessage::Request(req));
+                                    }>"Accept"</button>
+                                    <button on:click=move |_| decline_request(req)>
+                                        "Decline"
+                                    </button>
+                                }
+                            })}
+                    </div>
+                })
+            })
+            .collect_view()
+    };
+
+    // Compare with the other record as navigated in its own view.
+    Effect::new(move || {
+        if split.get() {
+            compare_with.set(Some(other_record.get()));
+        }
+    });
+
+    // Keep the other record at the same move as ours when syncing.
+    Effect::new(move || {
+        let index = record.read().move_index();
+        if split.get() && sync_split.get() {
+            let mut other = other_record.write_untracked();
+            let index = index.min(other.moves().len());
+            if other.move_index() != index {
+                other.jump(index);
+                drop(other);
+                other_record.notify();
+            }
+        }
     });
 
+    // Handles events from the view of the other record, which can only be navigated.
+    let on_other_event = move |ev: Event| match ev {
+        Event::Menu => on_event(ev),
+        Event::Undo | Event::Redo | Event::Home | Event::End if sync_split.get() => on_event(ev),
+        Event::Undo => _ = other_record.write().undo_move(),
+        Event::Redo => _ = other_record.write().redo_move(),
+        Event::Home => _ = other_record.write().jump(0),
+        Event::End => other_record.update(|r| _ = r.jump(r.moves().len())),
+        _ => {}
+    };
+
+    let other_view = move || {
+        view! {
+            <game_view::GameView
+                record=other_record
+                stone=RwSignal::new(None).read_only()
+                disabled=move || !dialogs.read().is_empty()
+                on_event=on_other_event
+                orientation=orientation
+                passive=true
+            />
+        }
+    };
+
     view! {
-        <game_view::GameView
-            record=record
-            stone=stone.read_only()
-            disabled=move || !dialog_entries.read().is_empty()
-            on_event=on_event
-            tentatives_pos=tentatives_pos
-            win_claim=win_claim
-        />
-        <For each=move || dialog_entries.get() key=|entry| entry.id let(DialogEntry { id, dialog })>
+        <div id="views">
+            <game_view::GameView
+                record=record
+                stone=stone.read_only()
+                disabled=move || !dialogs.read().is_empty()
+                on_event=on_event
+                tentatives_pos=tentatives_pos
+                win_claim=win_claim
+                orientation=orientation
+                heatmap=heatmap
+                setup=setup
+                annotations=annotations
+                annotatable=Signal::derive(move || {
+                    let id = game_id.read();
+                    id.starts_with(ANALYZE_PREFIX) || id.starts_with(COMPARE_PREFIX)
+                })
+                cursor_pos=cursor_pos
+                shared_cursors=shared_cursors
+                compare_with=compare_with
+                double_tap=double_tap
+                provisional=provisional
+                gestures=gestures
+            />
+            <Show when=move || split.get()>{other_view}</Show>
+        </div>
+        {timeline_view}
+        <Show when=toolbar_shown>{toolbar_view}</Show>
+        <div id="banners" on:mousedown=|ev| ev.prevent_default()>
+            {move || {
+                decode_progress
+                    .get()
+                    .map(|fraction| {
+                        view! {
+                            <div class="banner">
+                                "Loading record"
+                                <progress value=fraction />
+                            </div>
+                        }
+                    })
+            }}
+            {turn_indicator}
+            {one_stone_prompt_view}
+            {reconnecting_banner}
+            {setup_banner}
+            {request_banners}
+        </div>
+        {move || toast.get().map(|msg| view! { <div id="toast">{msg}</div> })}
+        <For
+            each=move || dialogs.read().entries().to_vec()
+            key=|entry| entry.id
+            let(DialogEntry { id, dialog })
+        >
             {dialog.show(id, on_dialog_return)}
         </For>
     }
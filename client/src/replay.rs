@@ -0,0 +1,417 @@
+//! Animated replays of games, for sharing without a link.
+
+use crate::game_view::{Annotations, Mark};
+use c6ol_core::game::{Move, Point, Record, Stone};
+use gif::{Encoder, Frame, Repeat};
+use leptos::prelude::*;
+use std::{borrow::Cow, collections::HashMap, f64, iter, time::Duration};
+use web_sys::{
+    js_sys::{Array, Uint8Array},
+    wasm_bindgen::JsCast,
+    Blob, BlobPropertyBag, HtmlAnchorElement, Url,
+};
+
+/// Pixel size of a grid.
+const GRID_SIZE: i32 = 20;
+/// Number of empty grids kept around the stones on each side.
+const MARGIN: i32 = 2;
+/// Maximum number of grids on each axis, beyond which the replay is cropped.
+const MAX_GRIDS: i32 = 39;
+
+// Same ratios as in `game_view.rs`.
+const STONE_RADIUS: i32 = GRID_SIZE * 4 / 9;
+const DOT_RADIUS: i32 = GRID_SIZE * 2 / 27;
+const MARK_RADIUS: i32 = STONE_RADIUS * 3 / 5;
+/// Pixel width of the lines of win rings and annotations.
+const LINE_WIDTH: i32 = 2;
+
+/// Time to keep a download URL alive, long enough for the download to start.
+const REVOKE_DELAY: Duration = Duration::from_secs(10);
+
+/// Delay of each frame, in hundredths of a second.
+const FRAME_DELAY: u16 = 50;
+/// Delay of the last frame, in hundredths of a second.
+const LAST_FRAME_DELAY: u16 = 300;
+
+const BOARD: u8 = 0;
+const BLACK: u8 = 1;
+const WHITE: u8 = 2;
+const WIN_RING: u8 = 3;
+const ANNOTATION: u8 = 4;
+
+const PALETTE: [u8; 15] = [
+    0xff, 0xcc, 0x66, // #ffcc66
+    0x00, 0x00, 0x00, // black
+    0xff, 0xff, 0xff, // white
+    0x2e, 0x8b, 0x57, // seagreen
+    0x94, 0x00, 0xd3, // darkviolet
+];
+
+/// Palette index of a stone.
+fn color(stone: Stone) -> u8 {
+    match stone {
+        Stone::Black => BLACK,
+        Stone::White => WHITE,
+    }
+}
+
+/// Returns the distance from `p` to the line segment from `a` to `b`.
+fn distance_to_segment(p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let len_sq = dx * dx + dy * dy;
+    let t = if len_sq == 0.0 {
+        0.0
+    } else {
+        (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / len_sq).clamp(0.0, 1.0)
+    };
+    (p.0 - a.0 - t * dx).hypot(p.1 - a.1 - t * dy)
+}
+
+/// An indexed image of the board.
+///
+/// The region drawn since the last frame is tracked, so that
+/// only it needs to be written in the next frame.
+struct Canvas {
+    /// Board position at the center of the top-left grid.
+    origin: (i32, i32),
+    grids: (i32, i32),
+    pixels: Vec<u8>,
+    stones: HashMap<Point, Stone>,
+    /// Pixel bounds of the region drawn, as `(left, top, right, bottom)`
+    /// with the right and bottom excluded.
+    dirty: Option<(i32, i32, i32, i32)>,
+}
+
+impl Canvas {
+    fn new(origin: (i32, i32), grids: (i32, i32)) -> Self {
+        let (w, h) = (grids.0 * GRID_SIZE, grids.1 * GRID_SIZE);
+        let mut canvas = Self {
+            origin,
+            grids,
+            pixels: vec![BOARD; (w * h) as usize],
+            stones: HashMap::new(),
+            dirty: Some((0, 0, w, h)),
+        };
+
+        // Draw a line through the center of each grid.
+        for i in 0..grids.0 {
+            let x = i * GRID_SIZE + GRID_SIZE / 2;
+            canvas.fill_rect(x, GRID_SIZE / 2, 1, h - GRID_SIZE, BLACK);
+        }
+        for i in 0..grids.1 {
+            let y = i * GRID_SIZE + GRID_SIZE / 2;
+            canvas.fill_rect(GRID_SIZE / 2, y, w - GRID_SIZE, 1, BLACK);
+        }
+        canvas
+    }
+
+    fn width(&self) -> i32 {
+        self.grids.0 * GRID_SIZE
+    }
+
+    fn height(&self) -> i32 {
+        self.grids.1 * GRID_SIZE
+    }
+
+    fn set(&mut self, x: i32, y: i32, index: u8) {
+        let (w, h) = (self.width(), self.height());
+        if (0..w).contains(&x) && (0..h).contains(&y) {
+            self.pixels[(y * w + x) as usize] = index;
+            self.dirty = Some(match self.dirty {
+                Some((left, top, right, bottom)) => {
+                    (left.min(x), top.min(y), right.max(x + 1), bottom.max(y + 1))
+                }
+                None => (x, y, x + 1, y + 1),
+            });
+        }
+    }
+
+    fn fill_rect(&mut self, x: i32, y: i32, w: i32, h: i32, index: u8) {
+        for y in y..y + h {
+            for x in x..x + w {
+                self.set(x, y, index);
+            }
+        }
+    }
+
+    /// Sets the pixels within the given bounds that satisfy `f`.
+    fn fill_where(
+        &mut self,
+        (left, top, right, bottom): (f64, f64, f64, f64),
+        index: u8,
+        f: impl Fn((f64, f64)) -> bool,
+    ) {
+        for y in top.floor() as i32..=bottom.ceil() as i32 {
+            for x in left.floor() as i32..=right.ceil() as i32 {
+                if f((f64::from(x), f64::from(y))) {
+                    self.set(x, y, index);
+                }
+            }
+        }
+    }
+
+    /// Returns the pixel position of the center of a board position.
+    fn center(&self, p: Point) -> (i32, i32) {
+        let x = i32::from(p.x) - self.origin.0;
+        let y = i32::from(p.y) - self.origin.1;
+        (x * GRID_SIZE + GRID_SIZE / 2, y * GRID_SIZE + GRID_SIZE / 2)
+    }
+
+    fn center_f64(&self, p: Point) -> (f64, f64) {
+        let (x, y) = self.center(p);
+        (f64::from(x), f64::from(y))
+    }
+
+    /// Draws a ring at a board position, filled if `width` is `None`.
+    fn draw_circle(&mut self, p: Point, r: i32, width: Option<i32>, index: u8) {
+        let (cx, cy) = self.center(p);
+        let inner = width.map_or(-1, |w| (r - w) * (r - w));
+        for dy in -r..=r {
+            for dx in -r..=r {
+                let d = dx * dx + dy * dy;
+                if d <= r * r && d > inner {
+                    self.set(cx + dx, cy + dy, index);
+                }
+            }
+        }
+    }
+
+    /// Draws a line between pixel positions.
+    fn draw_line(&mut self, a: (f64, f64), b: (f64, f64), index: u8) {
+        let r = f64::from(LINE_WIDTH) / 2.0;
+        let bounds = (
+            a.0.min(b.0) - r,
+            a.1.min(b.1) - r,
+            a.0.max(b.0) + r,
+            a.1.max(b.1) + r,
+        );
+        self.fill_where(bounds, index, |p| distance_to_segment(p, a, b) <= r);
+    }
+
+    /// Draws a filled triangle with vertices at pixel positions.
+    fn fill_triangle(&mut self, [a, b, c]: [(f64, f64); 3], index: u8) {
+        let bounds = (
+            a.0.min(b.0).min(c.0),
+            a.1.min(b.1).min(c.1),
+            a.0.max(b.0).max(c.0),
+            a.1.max(b.1).max(c.1),
+        );
+        // The point is inside if it is on the same side of every edge.
+        let side = |p: (f64, f64), a: (f64, f64), b: (f64, f64)| {
+            (b.0 - a.0) * (p.1 - a.1) - (b.1 - a.1) * (p.0 - a.0)
+        };
+        self.fill_where(bounds, index, |p| {
+            let sides = [side(p, a, b), side(p, b, c), side(p, c, a)];
+            sides.iter().all(|&s| s >= 0.0) || sides.iter().all(|&s| s <= 0.0)
+        });
+    }
+
+    /// Places a stone at a board position.
+    fn place(&mut self, p: Point, stone: Stone) {
+        self.stones.insert(p, stone);
+        self.draw_circle(p, STONE_RADIUS, None, color(stone));
+    }
+
+    /// Redraws the grid at a board position with its stone (if any),
+    /// erasing anything else drawn on it.
+    fn redraw_grid(&mut self, p: Point) {
+        let (cx, cy) = self.center(p);
+        let (x, y) = (cx - GRID_SIZE / 2, cy - GRID_SIZE / 2);
+        self.fill_rect(x, y, GRID_SIZE, GRID_SIZE, BOARD);
+
+        // Lines end at the centers of the outermost grids.
+        let left = x.max(GRID_SIZE / 2);
+        let right = (x + GRID_SIZE).min(self.width() - GRID_SIZE / 2);
+        self.fill_rect(left, cy, right - left, 1, BLACK);
+        let top = y.max(GRID_SIZE / 2);
+        let bottom = (y + GRID_SIZE).min(self.height() - GRID_SIZE / 2);
+        self.fill_rect(cx, top, 1, bottom - top, BLACK);
+
+        if let Some(&stone) = self.stones.get(&p) {
+            self.draw_circle(p, STONE_RADIUS, None, color(stone));
+        }
+    }
+
+    /// Draws arrows and marks as in `game_view.rs`, but opaque.
+    fn draw_annotations(&mut self, annotations: &Annotations) {
+        for &(p, mark) in &annotations.marks {
+            match mark {
+                Mark::Circle => self.draw_circle(p, MARK_RADIUS, Some(LINE_WIDTH), ANNOTATION),
+                Mark::Triangle => {
+                    let (x, y) = self.center_f64(p);
+                    let r = f64::from(MARK_RADIUS);
+                    let vertex = |i: i32| {
+                        let angle = f64::consts::TAU * f64::from(i) / 3.0 - f64::consts::FRAC_PI_2;
+                        (x + r * angle.cos(), y + r * angle.sin())
+                    };
+                    for i in 0..3 {
+                        self.draw_line(vertex(i), vertex(i + 1), ANNOTATION);
+                    }
+                }
+            }
+        }
+
+        for &(start, end) in &annotations.arrows {
+            let (x1, y1) = self.center_f64(start);
+            let (x2, y2) = self.center_f64(end);
+
+            // Stop the shaft short of the head so that its tip stays sharp.
+            let angle = (y2 - y1).atan2(x2 - x1);
+            let head = f64::from(STONE_RADIUS);
+            let (dx, dy) = (angle.cos(), angle.sin());
+
+            self.draw_line((x1, y1), (x2 - head * dx, y2 - head * dy), ANNOTATION);
+            self.fill_triangle(
+                [
+                    (x2, y2),
+                    (
+                        x2 - head * dx - head / 2.0 * dy,
+                        y2 - head * dy + head / 2.0 * dx,
+                    ),
+                    (
+                        x2 - head * dx + head / 2.0 * dy,
+                        y2 - head * dy - head / 2.0 * dx,
+                    ),
+                ],
+                ANNOTATION,
+            );
+        }
+    }
+
+    /// Returns a frame of the region drawn since the last frame,
+    /// which is kept outside the region when the frame is shown.
+    fn take_frame(&mut self, delay: u16) -> Frame<'static> {
+        // Nothing may be drawn, for example on a pass.
+        let (left, top, right, bottom) = self.dirty.take().unwrap_or((0, 0, 1, 1));
+        let w = self.width();
+        let buffer = (top..bottom)
+            .flat_map(|y| &self.pixels[(y * w + left) as usize..(y * w + right) as usize])
+            .copied()
+            .collect();
+        Frame {
+            left: left as u16,
+            top: top as u16,
+            width: (right - left) as u16,
+            height: (bottom - top) as u16,
+            buffer: Cow::Owned(buffer),
+            delay,
+            ..Frame::default()
+        }
+    }
+}
+
+/// Encodes the moves of a record up to the current one as an animated GIF,
+/// with a frame for the empty board and one for each move.
+/// The annotations are drawn in the last frame.
+///
+/// The board is cropped to the stones placed and the annotations,
+/// plus a margin.
+pub fn encode_gif(record: &Record, annotations: &Annotations) -> Vec<u8> {
+    let turns: Vec<_> = record.turns().take(record.move_index()).collect();
+
+    let mut points = turns
+        .iter()
+        .flat_map(|turn| turn.placed())
+        .chain(annotations.marks.iter().map(|&(p, _)| p))
+        .chain(
+            annotations
+                .arrows
+                .iter()
+                .flat_map(|&(start, end)| [start, end]),
+        );
+    let (mut min, mut max) = points.next().map_or(((0, 0), (0, 0)), |p| {
+        let p = (i32::from(p.x), i32::from(p.y));
+        (p, p)
+    });
+    for p in points {
+        let (x, y) = (i32::from(p.x), i32::from(p.y));
+        min = (min.0.min(x), min.1.min(y));
+        max = (max.0.max(x), max.1.max(y));
+    }
+
+    // Crop around the center of the stones if there are too many grids.
+    let axis = |min: i32, max: i32| {
+        let grids = (max - min + 1 + MARGIN * 2).min(MAX_GRIDS);
+        ((min + max) / 2 - grids / 2, grids)
+    };
+    let (x, w) = axis(min.0, max.0);
+    let (y, h) = axis(min.1, max.1);
+
+    let mut board = Canvas::new((x, y), (w, h));
+    let mut buf = vec![];
+    let mut encoder = Encoder::new(
+        &mut buf,
+        board.width() as u16,
+        board.height() as u16,
+        &PALETTE,
+    )
+    .unwrap();
+    encoder.set_repeat(Repeat::Infinite).unwrap();
+
+    let last = turns.len();
+    if last == 0 {
+        board.draw_annotations(annotations);
+    }
+    let delay = if last == 0 {
+        LAST_FRAME_DELAY
+    } else {
+        FRAME_DELAY
+    };
+    encoder.write_frame(&board.take_frame(delay)).unwrap();
+
+    // Positions marked in the previous frame, to be erased in the next one.
+    let mut marked = vec![];
+
+    for (i, turn) in turns.into_iter().enumerate() {
+        for p in marked.drain(..) {
+            board.redraw_grid(p);
+        }
+        for p in turn.placed() {
+            board.place(p, turn.stone);
+        }
+
+        match turn.mov {
+            Move::Place(p1, p2) => {
+                for p in iter::once(p1).chain(p2) {
+                    board.draw_circle(p, DOT_RADIUS, None, color(turn.stone.opposite()));
+                    marked.push(p);
+                }
+            }
+            Move::Win(p, dir) => {
+                for p in iter::once(p).chain(p.adjacent_iter(dir).take(5)) {
+                    board.draw_circle(p, STONE_RADIUS, Some(LINE_WIDTH), WIN_RING);
+                    marked.push(p);
+                }
+            }
+            Move::Pass | Move::Draw | Move::Resign(_) => {}
+        }
+
+        let delay = if i + 1 == last {
+            board.draw_annotations(annotations);
+            LAST_FRAME_DELAY
+        } else {
+            FRAME_DELAY
+        };
+        encoder.write_frame(&board.take_frame(delay)).unwrap();
+    }
+
+    drop(encoder);
+    buf
+}
+
+/// Downloads the data as a file with the given name and MIME type.
+pub fn download(data: &[u8], name: &str, mime: &str) {
+    let parts = Array::of1(&Uint8Array::from(data));
+    let options = BlobPropertyBag::new();
+    options.set_type(mime);
+    let blob = Blob::new_with_u8_array_sequence_and_options(&parts, &options).unwrap();
+    let url = Url::create_object_url_with_blob(&blob).unwrap();
+
+    let anchor: HtmlAnchorElement = document().create_element("a").unwrap().unchecked_into();
+    anchor.set_href(&url);
+    anchor.set_download(name);
+    anchor.click();
+
+    // Revoke the URL later, as the download may not have started yet.
+    set_timeout(move || Url::revoke_object_url(&url).unwrap(), REVOKE_DELAY);
+}
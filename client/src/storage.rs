@@ -0,0 +1,230 @@
+//! Typed access to local storage, with schema versioning.
+
+use crate::WATCH_SUFFIX;
+use base64::{prelude::BASE64_STANDARD, Engine};
+use c6ol_core::game::{Record, Stone};
+use leptos::prelude::*;
+use std::borrow::Cow;
+use web_sys::{wasm_bindgen::prelude::*, DomException, Storage};
+
+/// Current version of the storage schema.
+///
+/// - 0: Unversioned.
+/// - 1: Adds the version key and drops legacy `analyze,`-prefixed entries.
+const VERSION: u32 = 1;
+
+const KEY_VERSION: &str = "version";
+const KEY_RECORD: &str = "record";
+const KEY_PASS_RECORD: &str = "pass-record";
+const KEY_DOUBLE_TAP: &str = "double-tap";
+const KEY_GESTURES: &str = "gestures";
+const KEY_RECENT_GAMES: &str = "recent-games";
+
+const PREFIX_TENTATIVES: &str = "tentatives,";
+const PREFIX_LEGACY_ANALYZE: &str = "analyze,";
+
+/// Maximum number of recent online games to remember.
+const RECENT_GAMES_CAPACITY: usize = 5;
+
+/// Appended to the key of a record that fails to decode,
+/// under which the record is kept aside instead of being overwritten.
+const SUFFIX_BACKUP: &str = ".bak";
+
+/// A key in local storage.
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum Key<'a> {
+    /// The record of the offline game.
+    Record,
+    /// The record of the pass & play game.
+    PassRecord,
+    /// The tentative stones in the online game with the given ID.
+    Tentatives(&'a str),
+    /// Whether double-tap mode is on.
+    DoubleTap,
+    /// The thresholds of touch gestures.
+    Gestures,
+    /// The recent online games played, one per line as the ID
+    /// followed by a space and the stone number.
+    RecentGames,
+}
+
+impl<'a> Key<'a> {
+    /// Returns the key for the record of the game with the given ID (if any).
+    pub fn for_game(game_id: &str) -> Option<Self> {
+        match game_id {
+            "local" => Some(Self::Record),
+            "pass" => Some(Self::PassRecord),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of the key.
+    pub fn name(self) -> Cow<'a, str> {
+        match self {
+            Self::Record => KEY_RECORD.into(),
+            Self::PassRecord => KEY_PASS_RECORD.into(),
+            Self::Tentatives(id) => format!("{PREFIX_TENTATIVES}{id}").into(),
+            Self::DoubleTap => KEY_DOUBLE_TAP.into(),
+            Self::Gestures => KEY_GESTURES.into(),
+            Self::RecentGames => KEY_RECENT_GAMES.into(),
+        }
+    }
+}
+
+/// An error that occurs when writing to local storage.
+#[derive(Clone, Copy, Debug)]
+pub enum Error {
+    /// Local storage is unavailable, e.g., disabled by the user.
+    Unavailable,
+    /// Local storage is full.
+    QuotaExceeded,
+}
+
+fn storage() -> Option<Storage> {
+    window().local_storage().ok().flatten()
+}
+
+fn is_quota_exceeded(err: &JsValue) -> bool {
+    err.dyn_ref::<DomException>()
+        .is_some_and(|err| err.name() == "QuotaExceededError")
+}
+
+/// Removes all entries whose keys start with `prefix`.
+fn remove_with_prefix(storage: &Storage, prefix: &str) {
+    let len = storage.length().unwrap_or(0);
+    let keys: Vec<String> = (0..len)
+        .filter_map(|i| storage.key(i).ok().flatten())
+        .filter(|key| key.starts_with(prefix))
+        .collect();
+    for key in keys {
+        _ = storage.remove_item(&key);
+    }
+}
+
+/// Gets the value of a key (if any).
+pub fn get(key: Key<'_>) -> Option<String> {
+    storage()?.get_item(&key.name()).ok().flatten()
+}
+
+/// Sets the value of a key.
+///
+/// When local storage is full, drops all saved tentative stones
+/// to free up space before giving up.
+pub fn set(key: Key<'_>, value: &str) -> Result<(), Error> {
+    let storage = storage().ok_or(Error::Unavailable)?;
+    let name = key.name();
+
+    match storage.set_item(&name, value) {
+        Ok(()) => Ok(()),
+        Err(err) if is_quota_exceeded(&err) => {
+            remove_with_prefix(&storage, PREFIX_TENTATIVES);
+            storage.set_item(&name, value).map_err(|err| {
+                if is_quota_exceeded(&err) {
+                    Error::QuotaExceeded
+                } else {
+                    Error::Unavailable
+                }
+            })
+        }
+        Err(_) => Err(Error::Unavailable),
+    }
+}
+
+/// Removes a key.
+pub fn remove(key: Key<'_>) {
+    if let Some(storage) = storage() {
+        _ = storage.remove_item(&key.name());
+    }
+}
+
+/// Decodes a record saved in local storage.
+pub fn decode_record(value: &str) -> Option<Record> {
+    let buf = BASE64_STANDARD.decode(value).ok()?;
+    Record::decode(&mut &buf[..], true)
+}
+
+/// Gets the record saved under a key (if any).
+///
+/// A record that fails to decode is kept aside, so that saving
+/// a new record under the same key does not lose it silently.
+pub fn get_record(key: Key<'_>) -> Option<Record> {
+    let value = get(key)?;
+    let record = decode_record(&value);
+    if record.is_none() {
+        if let Some(storage) = storage() {
+            let backup = format!("{}{SUFFIX_BACKUP}", key.name());
+            if storage.get_item(&backup).ok().flatten().is_none() {
+                _ = storage.set_item(&backup, &value);
+            }
+        }
+    }
+    record
+}
+
+/// Saves a record under a key.
+pub fn set_record(key: Key<'_>, record: &Record) -> Result<(), Error> {
+    let mut buf = vec![];
+    record.encode(&mut buf, true);
+    set(key, &BASE64_STANDARD.encode(buf))
+}
+
+/// Gets the recent online games played with their stones, most recent first.
+pub fn get_recent_games() -> Vec<(String, Stone)> {
+    let Some(value) = get(Key::RecentGames) else {
+        return vec![];
+    };
+    value
+        .lines()
+        .filter_map(|line| {
+            let (id, stone) = line.split_once(' ')?;
+            Some((id.into(), Stone::from_u8(stone.parse().ok()?)?))
+        })
+        .collect()
+}
+
+/// Moves a game to the front of the recent online games,
+/// forgetting the oldest one when there are too many.
+///
+/// The watch suffix is stripped from the ID, if any.
+pub fn push_recent_game(id: &str, stone: Stone) {
+    let id = id.strip_suffix(WATCH_SUFFIX).unwrap_or(id);
+    let mut games = get_recent_games();
+    games.retain(|(other_id, _)| other_id != id);
+    games.insert(0, (id.into(), stone));
+    games.truncate(RECENT_GAMES_CAPACITY);
+
+    let value: Vec<String> = games
+        .iter()
+        .map(|(id, stone)| format!("{id} {}", *stone as u8))
+        .collect();
+    _ = set(Key::RecentGames, &value.join("\n"));
+}
+
+/// Migrates local storage to the current schema version.
+pub fn migrate() {
+    let Some(storage) = storage() else {
+        return;
+    };
+
+    let version = storage
+        .get_item(KEY_VERSION)
+        .ok()
+        .flatten()
+        .and_then(|version| version.parse().ok())
+        .unwrap_or(0);
+    if version >= VERSION {
+        return;
+    }
+
+    if version < 1 {
+        // Analyzed records are shared as links and never read from here.
+        remove_with_prefix(&storage, PREFIX_LEGACY_ANALYZE);
+
+        // Keep aside the saved records that fail to decode.
+        for key in [Key::Record, Key::PassRecord] {
+            get_record(key);
+        }
+    }
+
+    _ = storage.set_item(KEY_VERSION, &VERSION.to_string());
+}
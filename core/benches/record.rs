@@ -0,0 +1,148 @@
+#![allow(missing_docs)]
+
+use c6ol_core::{
+    game::{Point, Record},
+    random::NearStones,
+};
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use rand::{rngs::StdRng, SeedableRng};
+use std::hint::black_box;
+
+/// The number of games in the corpus.
+const GAMES: usize = 100;
+
+/// Plays random games that look like real ones, with a fixed seed
+/// so that runs are comparable.
+fn corpus() -> Vec<Record> {
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut policy = NearStones {
+        radius: 15,
+        distance: 2,
+    };
+    (0..GAMES)
+        .map(|_| {
+            let mut record = Record::new();
+            record.random_playout(&mut rng, &mut policy);
+            record
+        })
+        .collect()
+}
+
+/// The schemes a record can be encoded with.
+const SCHEMES: [(&str, bool, bool); 3] = [
+    ("past", false, false),
+    ("all", true, false),
+    ("checksummed", false, true),
+];
+
+fn encode(record: &Record, buf: &mut Vec<u8>, all: bool, checksummed: bool) {
+    if checksummed {
+        record.encode_with_checksum(buf, all);
+    } else {
+        record.encode(buf, all);
+    }
+}
+
+fn bench_encode(c: &mut Criterion) {
+    let corpus = corpus();
+    let moves: usize = corpus.iter().map(|record| record.moves().len()).sum();
+
+    let mut group = c.benchmark_group("encode");
+    group.throughput(Throughput::Elements(moves as u64));
+    for (name, all, checksummed) in SCHEMES {
+        let mut buf = vec![];
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                for record in &corpus {
+                    buf.clear();
+                    encode(record, &mut buf, all, checksummed);
+                    black_box(&buf);
+                }
+            });
+        });
+    }
+    group.finish();
+}
+
+fn bench_decode(c: &mut Criterion) {
+    let corpus = corpus();
+    let moves: usize = corpus.iter().map(|record| record.moves().len()).sum();
+
+    let mut group = c.benchmark_group("decode");
+    group.throughput(Throughput::Elements(moves as u64));
+    for (name, all, checksummed) in SCHEMES {
+        let encoded: Vec<Vec<u8>> = corpus
+            .iter()
+            .map(|record| {
+                let mut buf = vec![];
+                encode(record, &mut buf, all, checksummed);
+                buf
+            })
+            .collect();
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                for buf in &encoded {
+                    black_box(Record::decode(&mut &buf[..], all).unwrap());
+                }
+            });
+        });
+    }
+    group.finish();
+}
+
+fn bench_point_index(c: &mut Criterion) {
+    let points: Vec<Point> = corpus()
+        .iter()
+        .flat_map(|record| record.turns().flat_map(|turn| turn.placed()))
+        .collect();
+
+    let mut group = c.benchmark_group("point");
+    group.throughput(Throughput::Elements(points.len() as u64));
+    group.bench_function("index", |b| {
+        b.iter(|| {
+            for &p in &points {
+                black_box(black_box(p).index());
+            }
+        });
+    });
+    group.bench_function("from_index", |b| {
+        let indices: Vec<u32> = points.iter().map(|p| p.index()).collect();
+        b.iter(|| {
+            for &i in &indices {
+                black_box(Point::from_index(black_box(i)));
+            }
+        });
+    });
+    group.finish();
+}
+
+fn bench_find_winning_row(c: &mut Criterion) {
+    // Search from every stone on the final board of each game.
+    let corpus = corpus();
+    let searches: usize = corpus
+        .iter()
+        .map(|record| record.turns().flat_map(|turn| turn.placed()).count())
+        .sum();
+
+    let mut group = c.benchmark_group("find_winning_row");
+    group.throughput(Throughput::Elements(searches as u64));
+    group.bench_function("all_stones", |b| {
+        b.iter(|| {
+            for record in &corpus {
+                for p in record.turns().flat_map(|turn| turn.placed()) {
+                    black_box(record.find_winning_row(p));
+                }
+            }
+        });
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_encode,
+    bench_decode,
+    bench_point_index,
+    bench_find_winning_row
+);
+criterion_main!(benches);
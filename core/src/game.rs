@@ -2,7 +2,7 @@
 
 use bytes::{Buf, BufMut};
 use bytes_varint::{try_get_fixed::TryGetFixedSupport, VarIntSupport, VarIntSupportMut};
-use std::{collections::HashMap, iter};
+use std::{collections::HashMap, fmt, fmt::Write, iter, mem};
 
 /// A direction on the board.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -66,6 +66,21 @@ impl Direction {
         })
     }
 
+    /// Returns the opposite direction.
+    #[must_use]
+    pub fn opposite(self) -> Self {
+        match self {
+            Self::North => Self::South,
+            Self::Northeast => Self::Southwest,
+            Self::East => Self::West,
+            Self::Southeast => Self::Northwest,
+            Self::South => Self::North,
+            Self::Southwest => Self::Northeast,
+            Self::West => Self::East,
+            Self::Northwest => Self::Southeast,
+        }
+    }
+
     /// Returns the unit vector in this direction.
     #[must_use]
     pub fn unit_vec(self) -> (i16, i16) {
@@ -184,6 +199,9 @@ pub enum Stone {
 }
 
 impl Stone {
+    /// List of both stones, Black first.
+    pub const VALUES: [Self; 2] = [Self::Black, Self::White];
+
     /// Creates a stone from a `u8`.
     #[must_use]
     pub fn from_u8(n: u8) -> Option<Self> {
@@ -202,6 +220,13 @@ impl Stone {
             Self::White => Self::Black,
         }
     }
+
+    /// Returns the index of the stone in [`Self::VALUES`],
+    /// for use with per-stone arrays.
+    #[must_use]
+    pub fn index(self) -> usize {
+        self as usize - 1
+    }
 }
 
 /// Allows room for extension. Equals (2^7-11^2).
@@ -211,6 +236,36 @@ const MOVE_PASS: u64 = 0;
 const MOVE_WIN: u64 = 1;
 const MOVE_DRAW: u64 = 2;
 const MOVE_RESIGN: u64 = 3;
+/// Marks a record as checksummed before its first move,
+/// and the checksum trailer after its last move.
+const MOVE_CHECKSUM: u64 = 4;
+
+/// Letters for coordinates in SGF, from 0 to 51.
+const SGF_COORDS: &[u8; 52] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+/// Half the size (rounded down) of the smallest board in SGF we write, 19x19.
+const SGF_MIN_HALF_SIZE: i32 = 9;
+/// Half the size (rounded down) of the largest odd-sized board in SGF, 51x51.
+const SGF_MAX_HALF_SIZE: i32 = 25;
+
+/// A running Fletcher-16 checksum.
+#[derive(Clone, Copy, Debug, Default)]
+struct Checksum {
+    a: u16,
+    b: u16,
+}
+
+impl Checksum {
+    fn update(&mut self, bytes: &[u8]) {
+        for &x in bytes {
+            self.a = (self.a + u16::from(x)) % 255;
+            self.b = (self.b + self.a) % 255;
+        }
+    }
+
+    fn value(self) -> u16 {
+        self.b << 8 | self.a
+    }
+}
 
 /// A move made by one player or both players.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -234,6 +289,38 @@ impl Move {
         matches!(self, Self::Win(..) | Self::Draw | Self::Resign(_))
     }
 
+    /// Returns the canonical form of a win claim, or the move unchanged
+    /// if it is not one.
+    ///
+    /// A claim of six stones can be made from either end. The canonical form
+    /// starts from the end such that the direction is one of the first in
+    /// [`Direction::OPPOSITE_PAIRS`], so claims of the same six stones
+    /// compare equal after normalization.
+    #[must_use]
+    pub fn normalize_win(self) -> Self {
+        if let Self::Win(p, dir) = self {
+            if Direction::OPPOSITE_PAIRS.iter().any(|&(_, bwd)| bwd == dir) {
+                if let Some(q) = p.adjacent_iter(dir).nth(4) {
+                    return Self::Win(q, dir.opposite());
+                }
+            }
+        }
+        self
+    }
+
+    /// Returns the canonical form of the move, so that moves with the same
+    /// effect compare equal.
+    ///
+    /// Win claims are normalized with [`Self::normalize_win`], and the two
+    /// stones placed in a move are ordered by [`Point::index`].
+    #[must_use]
+    pub fn normalized(self) -> Self {
+        match self.normalize_win() {
+            Self::Place(p1, Some(p2)) if p2.index() < p1.index() => Self::Place(p2, Some(p1)),
+            mov => mov,
+        }
+    }
+
     /// Encodes the move to a buffer.
     ///
     /// If `compact`, omits the pass after a 1-stone move.
@@ -297,12 +384,120 @@ impl Move {
     }
 }
 
+/// An error that occurs when making an illegal move.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MoveError {
+    /// The game is already ended.
+    Ended,
+    /// Two stones are placed in the first move.
+    FirstMoveTwoStones,
+    /// A stone is placed at an occupied position.
+    Occupied,
+    /// The claimed winning row is invalid.
+    InvalidWinningRow,
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Ended => "game is already ended",
+            Self::FirstMoveTwoStones => "first move is a single stone",
+            Self::Occupied => "position is occupied",
+            Self::InvalidWinningRow => "invalid winning row",
+        })
+    }
+}
+
+impl std::error::Error for MoveError {}
+
+/// An error that occurs when parsing an SGF record.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SgfError {
+    /// The text is not well-formed SGF.
+    Syntax,
+    /// The game is not Connect6, or is set up with stones.
+    Unsupported,
+    /// A board size or a coordinate is invalid.
+    InvalidPoint,
+    /// The stones of the turn at the given move index are played
+    /// by the wrong player, or are too many.
+    InvalidTurn(usize),
+    /// The move at the given move index is illegal.
+    IllegalMove(usize, MoveError),
+}
+
+impl fmt::Display for SgfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Syntax => f.write_str("malformed SGF"),
+            Self::Unsupported => f.write_str("unsupported game or setup"),
+            Self::InvalidPoint => f.write_str("invalid board size or coordinate"),
+            Self::InvalidTurn(i) => write!(f, "invalid turn at move {}", i + 1),
+            Self::IllegalMove(i, err) => write!(f, "illegal move {}: {err}", i + 1),
+        }
+    }
+}
+
+impl std::error::Error for SgfError {}
+
+/// A turn in a record, in which a move is made.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Turn {
+    /// The move index before the move.
+    pub index: usize,
+    /// The stone to play in the turn.
+    pub stone: Stone,
+    /// The move made.
+    pub mov: Move,
+}
+
+impl Turn {
+    /// Returns an iterator of the positions where stones are placed in the turn.
+    pub fn placed(self) -> impl Iterator<Item = Point> {
+        let (p1, p2) = match self.mov {
+            Move::Place(p1, p2) => (Some(p1), p2),
+            _ => (None, None),
+        };
+        p1.into_iter().chain(p2)
+    }
+}
+
+/// The way a game is won.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum WinBy {
+    /// A winning row is claimed.
+    Row,
+    /// The opponent resigns.
+    Resignation,
+}
+
+/// The result of an ended game.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum GameResult {
+    /// The game is won by a player.
+    Win(Stone, WinBy),
+    /// The game is drawn by agreement.
+    Draw,
+}
+
+impl GameResult {
+    /// Returns the winner (if any).
+    #[must_use]
+    pub fn winner(self) -> Option<Stone> {
+        match self {
+            Self::Win(stone, _) => Some(stone),
+            Self::Draw => None,
+        }
+    }
+}
+
 /// A Connect6 game record.
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub struct Record {
     map: HashMap<Point, Stone>,
     moves: Vec<Move>,
     index: usize,
+    future_limit: Option<usize>,
 }
 
 impl Record {
@@ -313,9 +508,37 @@ impl Record {
             map: HashMap::new(),
             moves: vec![],
             index: 0,
+            future_limit: None,
         }
     }
 
+    /// Creates a record reaching the position with the given stones,
+    /// e.g., one set up by hand.
+    ///
+    /// Each player places as many of their stones as allowed in turn,
+    /// passing if they have none left.
+    /// Returns `None` if a point is given more than once.
+    #[must_use]
+    pub fn from_position(black: &[Point], white: &[Point]) -> Option<Self> {
+        let mut record = Self::new();
+        let (mut black, mut white) = (black.iter().copied(), white.iter().copied());
+        while black.len() + white.len() != 0 {
+            let stones = match record.turn_unchecked() {
+                Stone::Black => &mut black,
+                Stone::White => &mut white,
+            };
+            let mov = match stones.next() {
+                None => Move::Pass,
+                Some(p) if record.max_stones_to_play() == 1 => Move::Place(p, None),
+                Some(p) => Move::Place(p, stones.next()),
+            };
+            if !record.make_move(mov) {
+                return None;
+            }
+        }
+        Some(record)
+    }
+
     /// Clears the record.
     pub fn clear(&mut self) {
         self.map.clear();
@@ -359,6 +582,17 @@ impl Record {
         self.index < self.moves.len()
     }
 
+    /// Returns the result of the game (if ended).
+    #[must_use]
+    pub fn result(&self) -> Option<GameResult> {
+        Some(match self.prev_move()? {
+            Move::Win(p, _) => GameResult::Win(self.stone_at(p)?, WinBy::Row),
+            Move::Resign(stone) => GameResult::Win(stone.opposite(), WinBy::Resignation),
+            Move::Draw => GameResult::Draw,
+            Move::Place(..) | Move::Pass => return None,
+        })
+    }
+
     /// Tests if the game is ended.
     #[must_use]
     pub fn is_ended(&self) -> bool {
@@ -377,10 +611,19 @@ impl Record {
         }
     }
 
+    /// Returns an iterator of the turns in the record, including the future ones.
+    pub fn turns(&self) -> impl Iterator<Item = Turn> + '_ {
+        self.moves.iter().enumerate().map(|(index, &mov)| Turn {
+            index,
+            stone: Self::turn_at(index),
+            mov,
+        })
+    }
+
     /// Returns the stone to play at the given move index.
     #[must_use]
     pub fn turn_at(index: usize) -> Stone {
-        if index % 2 == 0 {
+        if index.is_multiple_of(2) {
             Stone::Black
         } else {
             Stone::White
@@ -404,36 +647,102 @@ impl Record {
         self.map.get(&p).copied()
     }
 
-    /// Makes a move, clearing moves in the future.
+    /// Checks if a move can be made, without making it.
     ///
-    /// Returns whether the move succeeded.
-    pub fn make_move(&mut self, mov: Move) -> bool {
+    /// # Errors
+    ///
+    /// Returns an error if the move is illegal.
+    pub fn check_move(&self, mov: Move) -> Result<(), MoveError> {
         if self.is_ended() {
-            return false;
+            return Err(MoveError::Ended);
         }
 
-        if let Move::Place(p1, p2) = mov {
-            if self.index == 0 && p2.is_some() {
-                return false;
+        match mov {
+            Move::Place(p1, p2) => {
+                if self.index == 0 && p2.is_some() {
+                    return Err(MoveError::FirstMoveTwoStones);
+                }
+                if self.map.contains_key(&p1) || p2.is_some_and(|p| self.map.contains_key(&p)) {
+                    return Err(MoveError::Occupied);
+                }
             }
-            if self.map.contains_key(&p1) || p2.is_some_and(|p| self.map.contains_key(&p)) {
-                return false;
+            Move::Win(p, dir) if self.test_winning_row(p, dir).is_none() => {
+                return Err(MoveError::InvalidWinningRow);
             }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Makes a move, clearing moves in the future.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the move is illegal, in which case
+    /// the record is left unchanged.
+    pub fn try_move(&mut self, mov: Move) -> Result<(), MoveError> {
+        self.check_move(mov)?;
 
+        if let Move::Place(p1, p2) = mov {
             let stone = self.turn_unchecked();
             for p in iter::once(p1).chain(p2) {
                 self.map.insert(p, stone);
             }
-        } else if let Move::Win(p, dir) = mov {
-            if self.test_winning_row(p, dir).is_none() {
-                return false;
-            }
         }
 
         self.moves.truncate(self.index);
         self.moves.push(mov);
         self.index += 1;
-        true
+        Ok(())
+    }
+
+    /// Makes a move, clearing moves in the future.
+    ///
+    /// Returns whether the move succeeded.
+    pub fn make_move(&mut self, mov: Move) -> bool {
+        self.try_move(mov).is_ok()
+    }
+
+    /// Makes a sequence of moves, clearing moves in the future.
+    ///
+    /// Returns the number of moves made.
+    ///
+    /// # Errors
+    ///
+    /// Returns the position of the first illegal move and the error,
+    /// in which case the record is left unchanged.
+    pub fn make_moves(&mut self, moves: &[Move]) -> Result<usize, (usize, MoveError)> {
+        if moves.is_empty() {
+            return Ok(0);
+        }
+
+        let index = self.index;
+        let future = self.moves.split_off(index);
+        for (i, &mov) in moves.iter().enumerate() {
+            if let Err(err) = self.try_move(mov) {
+                for _ in 0..i {
+                    self.undo_move();
+                }
+                self.moves.truncate(index);
+                self.moves.extend(future);
+                return Err((i, err));
+            }
+        }
+        Ok(moves.len())
+    }
+
+    /// Makes a move after the last one, past or in the future,
+    /// staying at the current move index.
+    ///
+    /// The move is dropped if it falls beyond the future limit (if any).
+    ///
+    /// Returns whether the move succeeded.
+    pub fn push_move(&mut self, mov: Move) -> bool {
+        let index = self.index;
+        self.jump(self.moves.len());
+        let success = self.make_move(mov);
+        self.jump(index);
+        success
     }
 
     /// Undoes the previous move (if any).
@@ -445,6 +754,7 @@ impl Record {
             }
         }
         self.index -= 1;
+        self.limit_future();
         Some(prev)
     }
 
@@ -478,6 +788,67 @@ impl Record {
         true
     }
 
+    /// Keeps at most `len` moves in the future, dropping the rest.
+    ///
+    /// Long-lived records in memory-constrained clients may call this
+    /// after undoing moves to release the space of a discarded line.
+    pub fn truncate_future(&mut self, len: usize) {
+        self.moves.truncate(self.index.saturating_add(len));
+        self.moves.shrink_to_fit();
+    }
+
+    /// Sets the maximum number of moves kept in the future, or removes
+    /// the limit if `None`.
+    ///
+    /// The future is truncated at once and again whenever moves are undone,
+    /// so that repeated undoing and redoing cannot grow the record beyond it.
+    pub fn set_future_limit(&mut self, limit: Option<usize>) {
+        self.future_limit = limit;
+        self.limit_future();
+    }
+
+    /// Returns the maximum number of moves kept in the future (if any).
+    #[must_use]
+    pub fn future_limit(&self) -> Option<usize> {
+        self.future_limit
+    }
+
+    /// Drops the moves in the future beyond the limit (if any).
+    fn limit_future(&mut self) {
+        if let Some(limit) = self.future_limit {
+            if self.moves.len() - self.index > limit {
+                self.truncate_future(limit);
+            }
+        }
+    }
+
+    /// Returns the canonical form of the record, so that records of the same
+    /// game compare equal regardless of how the moves were entered.
+    ///
+    /// Moves in the future are dropped, and the rest are normalized
+    /// with [`Move::normalized`].
+    #[must_use]
+    pub fn normalized(&self) -> Self {
+        let moves = self.moves[..self.index]
+            .iter()
+            .map(|mov| mov.normalized())
+            .collect();
+        Self {
+            map: self.map.clone(),
+            moves,
+            index: self.index,
+            future_limit: None,
+        }
+    }
+
+    /// Estimates the number of bytes the record allocates on the heap.
+    #[must_use]
+    pub fn heap_size(&self) -> usize {
+        // A hash map entry takes a control byte besides the key-value pair.
+        let entry_size = mem::size_of::<(Point, Stone)>() + 1;
+        self.moves.capacity() * mem::size_of::<Move>() + self.map.capacity() * entry_size
+    }
+
     /// Returns an iterator of adjacent positions occupied by `stone`
     /// in the direction `dir`, starting from `p` (exclusive).
     fn scan(&self, p: Point, dir: Direction, stone: Stone) -> impl Iterator<Item = Point> + '_ {
@@ -509,6 +880,27 @@ impl Record {
         self.scan(p, dir, self.stone_at(p)?).nth(4)
     }
 
+    /// Returns an iterator of all winning rows of `stone` on the board.
+    ///
+    /// Each row is maximal in length, and is represented by one of its
+    /// endpoints and a direction pointing to the other endpoint.
+    pub fn winning_rows(&self, stone: Stone) -> impl Iterator<Item = (Point, Direction)> + '_ {
+        self.map
+            .iter()
+            .filter(move |&(_, &s)| s == stone)
+            .flat_map(move |(&p, _)| {
+                Direction::OPPOSITE_PAIRS
+                    .into_iter()
+                    .filter(move |&(_, dir_bwd)| {
+                        // Start from the endpoint in the backward direction.
+                        p.adjacent(dir_bwd)
+                            .is_none_or(|p| self.stone_at(p) != Some(stone))
+                    })
+                    .filter(move |&(dir_fwd, _)| self.scan(p, dir_fwd, stone).nth(4).is_some())
+                    .map(move |(dir_fwd, _)| (p, dir_fwd))
+            })
+    }
+
     /// Places `stone` at each of `positions` temporarily, calls `f`
     /// and returns the result after undoing the placements.
     ///
@@ -533,38 +925,491 @@ impl Record {
     ///
     /// If `all`, includes all moves prefixed with the current move index.
     pub fn encode(&self, buf: &mut Vec<u8>, all: bool) {
+        self.encode_inner(buf, all, false);
+    }
+
+    /// Encodes the record to a buffer like [`Self::encode`], but marked
+    /// as checksummed and followed by a checksum of the bytes encoded.
+    ///
+    /// Such a record fails to decode if it is truncated or corrupted,
+    /// instead of decoding into a different record.
+    pub fn encode_with_checksum(&self, buf: &mut Vec<u8>, all: bool) {
+        let start = buf.len();
+        self.encode_inner(buf, all, true);
+
+        let mut checksum = Checksum::default();
+        checksum.update(&buf[start..]);
+        buf.put_u8(MOVE_CHECKSUM as u8);
+        buf.put_u16(checksum.value());
+    }
+
+    fn encode_inner(&self, buf: &mut Vec<u8>, all: bool, checksummed: bool) {
         if all {
             buf.put_u64_varint(self.index as u64);
         }
+        // The first move is never a checksum marker, nor is an empty record.
+        if checksummed {
+            buf.put_u8(MOVE_CHECKSUM as u8);
+        }
         let end = if all { self.moves.len() } else { self.index };
         for i in 0..end {
             self.moves[i].encode(buf, i == 0);
         }
     }
 
+    /// Serializes the past moves of the record to SGF (Smart Game Format).
+    ///
+    /// Each turn is a node with the stones placed, or an empty value for a pass.
+    /// Ending moves are not written as nodes, but as the result in the root node.
+    ///
+    /// The board is the smallest odd-sized square of at least 19x19 holding
+    /// all stones, centered at the origin if possible, or else at the center
+    /// of the stones. Returns `None` if it would be larger than 51x51,
+    /// the largest board SGF allows.
+    #[must_use]
+    pub fn to_sgf(&self) -> Option<String> {
+        let placed = || self.turns().take(self.index).flat_map(Turn::placed);
+        let half_size = |(cx, cy): (i32, i32)| {
+            placed()
+                .map(|p| (i32::from(p.x) - cx).abs().max((i32::from(p.y) - cy).abs()))
+                .fold(SGF_MIN_HALF_SIZE, i32::max)
+        };
+
+        let mut center = (0, 0);
+        let mut half = half_size(center);
+        if half > SGF_MAX_HALF_SIZE {
+            let mid = |coord: fn(Point) -> i16| {
+                let (min, max) = placed()
+                    .map(|p| i32::from(coord(p)))
+                    .fold((i32::MAX, i32::MIN), |(min, max), c| {
+                        (min.min(c), max.max(c))
+                    });
+                (min + max) / 2
+            };
+            center = (mid(|p| p.x), mid(|p| p.y));
+            half = half_size(center);
+            if half > SGF_MAX_HALF_SIZE {
+                return None;
+            }
+        }
+
+        let stone_letter = |stone| match stone {
+            Stone::Black => 'B',
+            Stone::White => 'W',
+        };
+        let coord =
+            |c: i16, center: i32| char::from(SGF_COORDS[(i32::from(c) - center + half) as usize]);
+
+        let mut sgf = format!("(;FF[4]GM[20]SZ[{}]", half * 2 + 1);
+        match self.result() {
+            Some(GameResult::Win(stone, WinBy::Row)) => {
+                _ = write!(sgf, "RE[{}+]", stone_letter(stone));
+            }
+            Some(GameResult::Win(stone, WinBy::Resignation)) => {
+                _ = write!(sgf, "RE[{}+R]", stone_letter(stone));
+            }
+            Some(GameResult::Draw) => sgf.push_str("RE[0]"),
+            None => {}
+        }
+
+        for turn in self.turns().take(self.index) {
+            if turn.mov.is_ending() {
+                continue;
+            }
+            _ = write!(sgf, ";{}", stone_letter(turn.stone));
+            if turn.mov == Move::Pass {
+                sgf.push_str("[]");
+            }
+            for p in turn.placed() {
+                _ = write!(sgf, "[{}{}]", coord(p.x, center.0), coord(p.y, center.1));
+            }
+        }
+        sgf.push(')');
+        Some(sgf)
+    }
+
+    /// Parses a record from the main line of the first game in SGF (Smart Game Format),
+    /// like one written by [`Self::to_sgf`] or by other Connect6 clients.
+    ///
+    /// The center of the board is taken as the origin. Consecutive nodes of
+    /// the same player make up a turn, and an empty value (or `tt` on boards
+    /// up to 19x19) is a pass. A resignation or a draw in the result is
+    /// made as a move, and so is a win if the winner has a winning row.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the SGF is malformed or unsupported,
+    /// or the moves in it are illegal.
+    pub fn from_sgf(sgf: &str) -> Result<Self, SgfError> {
+        let nodes = SgfParser(sgf).game_tree()?;
+
+        let root = nodes.first().ok_or(SgfError::Syntax)?;
+        let prop = |node: &SgfNode, id: &str| {
+            node.iter()
+                .find(|(ident, _)| ident == id)
+                .map(|(_, values)| values[0].trim().to_owned())
+        };
+        if prop(root, "GM").is_some_and(|gm| gm != "20")
+            || root.iter().any(|(ident, _)| ident == "AB" || ident == "AW")
+        {
+            return Err(SgfError::Unsupported);
+        }
+
+        let (width, height) = match prop(root, "SZ") {
+            None => (19, 19),
+            Some(size) => {
+                let parse = |s: &str| s.trim().parse().ok().filter(|n| (1..=52).contains(n));
+                let (w, h) = size.split_once(':').unwrap_or((&size, &size));
+                parse(w).zip(parse(h)).ok_or(SgfError::InvalidPoint)?
+            }
+        };
+
+        // Each stone or pass on its own, with the player.
+        let mut plays = vec![];
+        for node in &nodes {
+            for (ident, values) in node {
+                let stone = match &ident[..] {
+                    "B" => Stone::Black,
+                    "W" => Stone::White,
+                    _ => continue,
+                };
+                for value in values {
+                    let p = match value.trim().as_bytes() {
+                        [] => None,
+                        b"tt" if width <= 19 && height <= 19 => None,
+                        &[x, y] => {
+                            let coord = |c: u8, size: usize| {
+                                SGF_COORDS[..size]
+                                    .iter()
+                                    .position(|&b| b == c)
+                                    .map(|i| (i as i16) - (size / 2) as i16)
+                            };
+                            let x = coord(x, width).ok_or(SgfError::InvalidPoint)?;
+                            let y = coord(y, height).ok_or(SgfError::InvalidPoint)?;
+                            Some(Point::new(x, y))
+                        }
+                        _ => return Err(SgfError::InvalidPoint),
+                    };
+                    plays.push((stone, p));
+                }
+            }
+        }
+
+        let mut record = Self::new();
+        for turn in plays.chunk_by(|a, b| a.0 == b.0) {
+            let index = record.index;
+            if turn[0].0 != Self::turn_at(index) {
+                return Err(SgfError::InvalidTurn(index));
+            }
+            let mov = match *turn {
+                [(_, None)] => Move::Pass,
+                [(_, Some(p))] => Move::Place(p, None),
+                [(_, Some(p)), (_, Some(q))] => Move::Place(p, Some(q)),
+                _ => return Err(SgfError::InvalidTurn(index)),
+            };
+            record
+                .try_move(mov)
+                .map_err(|err| SgfError::IllegalMove(index, err))?;
+        }
+
+        let ending = prop(root, "RE").and_then(|re| {
+            if re == "0" || re.eq_ignore_ascii_case("draw") {
+                return Some(Move::Draw);
+            }
+            let winner = match re.as_bytes().first()? {
+                b'B' => Stone::Black,
+                b'W' => Stone::White,
+                _ => return None,
+            };
+            if re[1..].trim_start_matches('+').starts_with('R') {
+                Some(Move::Resign(winner.opposite()))
+            } else {
+                let (p, dir) = record.winning_rows(winner).next()?;
+                Some(Move::Win(p, dir).normalize_win())
+            }
+        });
+        if let Some(mov) = ending {
+            let index = record.index;
+            record
+                .try_move(mov)
+                .map_err(|err| SgfError::IllegalMove(index, err))?;
+        }
+        Ok(record)
+    }
+
     /// Decodes a record from a buffer.
+    ///
+    /// The checksum of a record encoded by [`Self::encode_with_checksum`]
+    /// is verified.
     #[must_use]
     pub fn decode(buf: &mut &[u8], all: bool) -> Option<Self> {
+        let mut decoder = RecordDecoder::new(buf, all)?;
+        if !decoder.decode(buf, usize::MAX) {
+            return None;
+        }
+        decoder.finish()
+    }
+}
+
+/// A decoder that decodes a record incrementally, some moves at a time.
+///
+/// Decoding a buffer to the end with successive calls to [`Self::decode`]
+/// and then calling [`Self::finish`] is equivalent to [`Record::decode`].
+#[derive(Clone, Debug)]
+pub struct RecordDecoder {
+    index: Option<usize>,
+    record: Record,
+    checksum: Checksum,
+    checksummed: bool,
+    verified: bool,
+}
+
+impl RecordDecoder {
+    /// Creates a new decoder, decoding the move index from a buffer first
+    /// if `all` is set.
+    ///
+    /// Returns `None` if the move index fails to decode.
+    #[must_use]
+    pub fn new(buf: &mut &[u8], all: bool) -> Option<Self> {
+        let start = *buf;
         let index = if all {
             Some(buf.try_get_usize_varint().ok()?)
         } else {
             None
         };
 
-        let mut record = Self::new();
+        let mut checksum = Checksum::default();
+        checksum.update(&start[..start.len() - buf.len()]);
+        Some(Self {
+            index,
+            record: Record::new(),
+            checksum,
+            checksummed: false,
+            verified: false,
+        })
+    }
+
+    /// Returns the number of moves decoded.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.record.moves.len()
+    }
 
-        while buf.has_remaining() {
-            let mov = Move::decode(buf, !record.has_past())?;
+    /// Tests if no moves are decoded.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.record.moves.is_empty()
+    }
+
+    /// Tests if the record is marked as checksummed.
+    #[must_use]
+    pub fn is_checksummed(&self) -> bool {
+        self.checksummed
+    }
+
+    /// Decodes at most `max_moves` moves from a buffer,
+    /// verifying the checksum if it is reached.
+    ///
+    /// Returns `false` if a move fails to decode or is illegal,
+    /// or if the checksum mismatches or is followed by anything.
+    pub fn decode(&mut self, buf: &mut &[u8], max_moves: usize) -> bool {
+        let record = &mut self.record;
+        for _ in 0..max_moves {
+            if !buf.has_remaining() {
+                break;
+            }
+
+            // A varint starting with the marker byte is the marker itself.
+            if buf[0] == MOVE_CHECKSUM as u8 {
+                if self.checksummed {
+                    buf.advance(1);
+                    self.verified = buf.try_get_u16().ok() == Some(self.checksum.value())
+                        && !buf.has_remaining();
+                    return self.verified;
+                }
+                if record.moves.is_empty() {
+                    self.checksummed = true;
+                    self.checksum.update(&buf[..1]);
+                    buf.advance(1);
+                    continue;
+                }
+            }
+
+            let start = *buf;
+            let Some(mov) = Move::decode(buf, !record.has_past()) else {
+                return false;
+            };
             if !record.make_move(mov) {
-                return None;
+                return false;
             }
+            self.checksum.update(&start[..start.len() - buf.len()]);
         }
+        true
+    }
 
-        if let Some(index) = index {
-            if !record.jump(index) {
+    /// Returns the record decoded, jumping to the move index if decoded.
+    ///
+    /// Returns `None` if the move index is out of range,
+    /// or if the record is checksummed but the checksum is not reached.
+    #[must_use]
+    pub fn finish(mut self) -> Option<Record> {
+        if self.checksummed && !self.verified {
+            return None;
+        }
+        if let Some(index) = self.index {
+            if !self.record.jump(index) {
                 return None;
             }
         }
-        Some(record)
+        Some(self.record)
+    }
+}
+
+/// An encoder that appends the past moves of a record incrementally.
+///
+/// The bytes appended by successive calls to [`Self::encode`] concatenate to
+/// the encoding of the record by [`Record::encode`] without `all`, as long as
+/// no encoded moves are undone in between.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RecordEncoder {
+    len: usize,
+}
+
+impl RecordEncoder {
+    /// Creates a new encoder with no moves encoded.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of moves encoded.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Tests if no moves are encoded.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Encodes the past moves of `record` that are not yet encoded to a buffer.
+    ///
+    /// Returns `false` without encoding anything if any encoded move has
+    /// been undone, in which case the caller should discard the bytes and
+    /// start over with a new encoder.
+    pub fn encode(&mut self, record: &Record, buf: &mut Vec<u8>) -> bool {
+        let index = record.move_index();
+        if index < self.len {
+            return false;
+        }
+        for i in self.len..index {
+            record.moves[i].encode(buf, i == 0);
+        }
+        self.len = index;
+        true
+    }
+}
+
+/// A node in SGF, as a list of properties with their values.
+type SgfNode = Vec<(String, Vec<String>)>;
+
+/// A parser for SGF, which keeps the main line only.
+struct SgfParser<'a>(&'a str);
+
+impl SgfParser<'_> {
+    /// Skips whitespace and consumes `c` if it is next.
+    fn eat(&mut self, c: char) -> bool {
+        self.0 = self.0.trim_start();
+        if let Some(rest) = self.0.strip_prefix(c) {
+            self.0 = rest;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Parses a game tree, returning the nodes of its main line.
+    ///
+    /// Variations are parsed in a loop rather than recursively,
+    /// so that deep nesting cannot overflow the stack.
+    fn game_tree(&mut self) -> Result<Vec<SgfNode>, SgfError> {
+        let mut nodes = vec![];
+        // The number of game trees open.
+        let mut depth = 0usize;
+        // Whether the next game tree to open is on the main line.
+        let mut on_main_line = true;
+        loop {
+            if !self.eat('(') {
+                return Err(SgfError::Syntax);
+            }
+            depth += 1;
+            while self.eat(';') {
+                let node = self.node()?;
+                if on_main_line {
+                    nodes.push(node);
+                }
+            }
+
+            // Close game trees until another variation opens.
+            while !self.0.trim_start().starts_with('(') {
+                if !self.eat(')') {
+                    return Err(SgfError::Syntax);
+                }
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(nodes);
+                }
+                // Only the first variation is on the main line.
+                on_main_line = false;
+            }
+        }
+    }
+
+    fn node(&mut self) -> Result<SgfNode, SgfError> {
+        let mut node = vec![];
+        loop {
+            self.0 = self.0.trim_start();
+            let len = self
+                .0
+                .find(|c: char| !c.is_ascii_alphabetic())
+                .unwrap_or(self.0.len());
+            if len == 0 {
+                return Ok(node);
+            }
+            // Old versions of SGF allow lowercase letters in identifiers.
+            let ident: String = self.0[..len]
+                .chars()
+                .filter(char::is_ascii_uppercase)
+                .collect();
+            self.0 = &self.0[len..];
+
+            let mut values = vec![];
+            while self.eat('[') {
+                values.push(self.value()?);
+            }
+            if values.is_empty() {
+                return Err(SgfError::Syntax);
+            }
+            node.push((ident, values));
+        }
+    }
+
+    /// Parses the rest of a value after the opening bracket.
+    fn value(&mut self) -> Result<String, SgfError> {
+        let mut value = String::new();
+        let mut chars = self.0.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                ']' => {
+                    self.0 = &self.0[i + 1..];
+                    return Ok(value);
+                }
+                '\\' => value.extend(chars.next().map(|(_, c)| c)),
+                _ => value.push(c),
+            }
+        }
+        Err(SgfError::Syntax)
     }
 }
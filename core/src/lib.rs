@@ -3,4 +3,7 @@
 #![warn(clippy::must_use_candidate)]
 
 pub mod game;
+pub mod notation;
 pub mod protocol;
+#[cfg(feature = "rand")]
+pub mod random;
@@ -0,0 +1,183 @@
+//! Human-readable notation of points, moves, and records.
+//!
+//! A point is written as a column and a row, like `K10` for the origin.
+//! Columns `A` to `Z` (skipping `I`) run east from `x = -9`, continuing
+//! with `AA`, `AB`, and so on, while lowercase columns `a`, `b`, ... run
+//! west from `x = -10`. Rows count north from `y = 9` at row 1, going
+//! through zero to negative numbers further north.
+//!
+//! A move is written as one of the following:
+//!
+//! - `K10` or `K10,L11` for one or two stones placed.
+//! - `pass` for a pass.
+//! - `win:K10/E` for a winning row claimed from `K10` to the east.
+//! - `draw` for a draw.
+//! - `resign:B` or `resign:W` for a resignation.
+//!
+//! A record is written as its past moves separated by spaces.
+
+use crate::game::{Direction, Move, Point, Record, Stone};
+
+/// Letters for columns, skipping `I` as in Go.
+const COLUMN_LETTERS: &[u8; 25] = b"ABCDEFGHJKLMNOPQRSTUVWXYZ";
+/// The column number of `K`, where `x = 0`.
+const ORIGIN_COLUMN: i32 = 10;
+/// The row number where `y = 0`.
+const ORIGIN_ROW: i32 = 10;
+
+impl Direction {
+    /// Returns the name of the direction in notation, like `NE` for northeast.
+    fn notation_name(self) -> &'static str {
+        match self {
+            Self::North => "N",
+            Self::Northeast => "NE",
+            Self::East => "E",
+            Self::Southeast => "SE",
+            Self::South => "S",
+            Self::Southwest => "SW",
+            Self::West => "W",
+            Self::Northwest => "NW",
+        }
+    }
+
+    /// Parses a direction from its name in notation.
+    fn from_notation_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "N" => Self::North,
+            "NE" => Self::Northeast,
+            "E" => Self::East,
+            "SE" => Self::Southeast,
+            "S" => Self::South,
+            "SW" => Self::Southwest,
+            "W" => Self::West,
+            "NW" => Self::Northwest,
+            _ => return None,
+        })
+    }
+}
+
+impl Point {
+    /// Writes the point in notation, like `K10` for the origin.
+    #[must_use]
+    pub fn to_notation(self) -> String {
+        let x = i32::from(self.x);
+        let (mut n, lowercase) = if x > -ORIGIN_COLUMN {
+            (x + ORIGIN_COLUMN, false)
+        } else {
+            (1 - ORIGIN_COLUMN - x, true)
+        };
+
+        // Bijective base-25, with the least significant letter first.
+        let mut letters = vec![];
+        while n > 0 {
+            n -= 1;
+            let letter = COLUMN_LETTERS[(n % 25) as usize];
+            letters.push(if lowercase {
+                letter.to_ascii_lowercase()
+            } else {
+                letter
+            });
+            n /= 25;
+        }
+        letters.reverse();
+
+        let row = ORIGIN_ROW - i32::from(self.y);
+        format!("{}{row}", String::from_utf8(letters).unwrap())
+    }
+
+    /// Parses a point from notation.
+    #[must_use]
+    pub fn from_notation(s: &str) -> Option<Self> {
+        let len = s.find(|c: char| !c.is_ascii_alphabetic())?;
+        let (letters, row) = s.split_at(len);
+        if letters.is_empty() || row.starts_with('+') {
+            return None;
+        }
+        let row: i32 = row.parse().ok()?;
+
+        let lowercase = letters.bytes().all(|b| b.is_ascii_lowercase());
+        if !lowercase && !letters.bytes().all(|b| b.is_ascii_uppercase()) {
+            return None;
+        }
+        let mut n = 0i32;
+        for b in letters.bytes() {
+            let digit = COLUMN_LETTERS
+                .iter()
+                .position(|&l| l == b.to_ascii_uppercase())?;
+            n = n.checked_mul(25)?.checked_add(digit as i32 + 1)?;
+        }
+        let x = if lowercase {
+            1 - ORIGIN_COLUMN - n
+        } else {
+            n - ORIGIN_COLUMN
+        };
+
+        let x = i16::try_from(x).ok()?;
+        let y = i16::try_from(ORIGIN_ROW.checked_sub(row)?).ok()?;
+        Some(Self::new(x, y))
+    }
+}
+
+impl Move {
+    /// Writes the move in notation.
+    #[must_use]
+    pub fn to_notation(self) -> String {
+        match self {
+            Self::Place(p, None) => p.to_notation(),
+            Self::Place(p1, Some(p2)) => format!("{},{}", p1.to_notation(), p2.to_notation()),
+            Self::Pass => "pass".into(),
+            Self::Win(p, dir) => format!("win:{}/{}", p.to_notation(), dir.notation_name()),
+            Self::Draw => "draw".into(),
+            Self::Resign(Stone::Black) => "resign:B".into(),
+            Self::Resign(Stone::White) => "resign:W".into(),
+        }
+    }
+
+    /// Parses a move from notation.
+    #[must_use]
+    pub fn from_notation(s: &str) -> Option<Self> {
+        Some(match s {
+            "pass" => Self::Pass,
+            "draw" => Self::Draw,
+            "resign:B" => Self::Resign(Stone::Black),
+            "resign:W" => Self::Resign(Stone::White),
+            _ => {
+                if let Some(win) = s.strip_prefix("win:") {
+                    let (p, name) = win.split_once('/')?;
+                    let dir = Direction::from_notation_name(name)?;
+                    Self::Win(Point::from_notation(p)?, dir)
+                } else if let Some((p1, p2)) = s.split_once(',') {
+                    Self::Place(Point::from_notation(p1)?, Some(Point::from_notation(p2)?))
+                } else {
+                    Self::Place(Point::from_notation(s)?, None)
+                }
+            }
+        })
+    }
+}
+
+impl Record {
+    /// Writes the past moves of the record in notation, separated by spaces.
+    #[must_use]
+    pub fn to_notation_string(&self) -> String {
+        self.moves()[..self.move_index()]
+            .iter()
+            .map(|mov| mov.to_notation())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Parses a record from moves in notation separated by whitespace.
+    ///
+    /// Returns `None` if any move is malformed or illegal.
+    #[must_use]
+    pub fn from_notation_string(s: &str) -> Option<Self> {
+        let mut record = Self::new();
+        for mov in s.split_whitespace() {
+            if !record.make_move(Move::from_notation(mov)?) {
+                return None;
+            }
+        }
+        Some(record)
+    }
+}
@@ -2,8 +2,8 @@
 
 use crate::game::{Direction, Move, Point, Record, Stone};
 use bytes::{Buf, BufMut};
-use bytes_varint::try_get_fixed::TryGetFixedSupport;
-use std::{iter, mem};
+use bytes_varint::{try_get_fixed::TryGetFixedSupport, VarIntSupport, VarIntSupportMut};
+use std::{fmt, iter, mem, time::Duration};
 use strum::{EnumDiscriminants, FromRepr};
 
 /// A passcode.
@@ -11,6 +11,22 @@ pub type Passcode = Box<[u8]>;
 /// A game ID.
 pub type GameId = [u8; 10];
 
+/// The maximum length in bytes of a farewell message sent on resigning.
+pub const MAX_FAREWELL_LEN: usize = 140;
+
+/// The default time for which a player cannot request to retract again
+/// after their request is declined, i.e., a move is made instead.
+pub const DEFAULT_RETRACT_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// The time for which a seat transfer code is valid.
+pub const TRANSFER_CODE_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// The number of digits in a join code.
+pub const JOIN_CODE_LEN: usize = 6;
+
+/// The time for which a join code resolves to its game, unless used.
+pub const JOIN_CODE_TTL: Duration = Duration::from_secs(10 * 60);
+
 /// A player's request.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum Request {
@@ -38,6 +54,124 @@ impl Request {
     }
 }
 
+/// An error that occurs when decoding a message.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ProtocolError {
+    /// The message ended before a field.
+    Truncated,
+    /// The message is of an unknown kind.
+    UnknownKind(u8),
+    /// The message has bytes left after its last field.
+    TrailingBytes,
+    /// A field of the given name has an invalid value.
+    InvalidValue(&'static str),
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Truncated => f.write_str("message is truncated"),
+            Self::UnknownKind(kind) => write!(f, "unknown message kind {kind}"),
+            Self::TrailingBytes => f.write_str("message has trailing bytes"),
+            Self::InvalidValue(name) => write!(f, "invalid {name}"),
+        }
+    }
+}
+
+impl std::error::Error for ProtocolError {}
+
+/// Decodes a field with `f`, telling an ended buffer from an invalid value.
+fn field<T>(
+    buf: &mut &[u8],
+    name: &'static str,
+    f: impl FnOnce(&mut &[u8]) -> Option<T>,
+) -> Result<T, ProtocolError> {
+    if !buf.has_remaining() {
+        return Err(ProtocolError::Truncated);
+    }
+    f(buf).ok_or(ProtocolError::InvalidValue(name))
+}
+
+/// Decodes a field of varints with `f`, which is also truncated
+/// if the buffer ends in the middle of a varint.
+fn varint_field<T>(
+    buf: &mut &[u8],
+    name: &'static str,
+    f: impl FnOnce(&mut &[u8]) -> Option<T>,
+) -> Result<T, ProtocolError> {
+    let last = buf.last().copied();
+    field(buf, name, f).map_err(|err| {
+        // A varint that ends with a continuation bit consumes the entire buffer.
+        if !buf.has_remaining() && last.is_some_and(|b| b & 0x80 != 0) {
+            ProtocolError::Truncated
+        } else {
+            err
+        }
+    })
+}
+
+/// Decodes an optional field of varints with `f`,
+/// which is absent if the buffer is ended.
+fn opt_varint_field<T>(
+    buf: &mut &[u8],
+    name: &'static str,
+    f: impl FnOnce(&mut &[u8]) -> Option<T>,
+) -> Result<Option<T>, ProtocolError> {
+    if buf.has_remaining() {
+        varint_field(buf, name, f).map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+/// Decodes an optional field with `f`, which is absent if the buffer is ended.
+fn opt_field<T>(
+    buf: &mut &[u8],
+    name: &'static str,
+    f: impl FnOnce(&mut &[u8]) -> Option<T>,
+) -> Result<Option<T>, ProtocolError> {
+    if buf.has_remaining() {
+        field(buf, name, f).map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+fn game_id(buf: &mut &[u8]) -> Result<GameId, ProtocolError> {
+    let id = buf
+        .get(..mem::size_of::<GameId>())
+        .ok_or(ProtocolError::Truncated)?;
+    let id = id.try_into().expect("length should match");
+    buf.advance(mem::size_of::<GameId>());
+    Ok(id)
+}
+
+/// Decodes a farewell message, which takes up the rest of the buffer.
+fn farewell(buf: &mut &[u8]) -> Option<Box<str>> {
+    if buf.len() > MAX_FAREWELL_LEN {
+        return None;
+    }
+    let s = std::str::from_utf8(mem::take(buf)).ok()?;
+    Some(s.into())
+}
+
+/// Decodes a passcode prefixed with its length.
+fn prefixed_passcode(buf: &mut &[u8]) -> Result<Passcode, ProtocolError> {
+    let len = varint_field(buf, "passcode", |buf| buf.try_get_usize_varint().ok())?;
+    let passcode = buf.get(..len).ok_or(ProtocolError::Truncated)?.into();
+    buf.advance(len);
+    Ok(passcode)
+}
+
+/// Checks that the buffer is ended after the last field.
+fn end<T>(buf: &[u8], msg: T) -> Result<T, ProtocolError> {
+    if buf.has_remaining() {
+        Err(ProtocolError::TrailingBytes)
+    } else {
+        Ok(msg)
+    }
+}
+
 /// A client message.
 #[derive(Clone, Debug, EnumDiscriminants)]
 #[strum_discriminants(derive(FromRepr), name(ClientMessageKind), repr(u8), vis(pub(self)))]
@@ -53,10 +187,25 @@ pub enum ClientMessage {
     Pass,
     /// Claims a win.
     ClaimWin(Point, Direction),
-    /// Resigns the game.
-    Resign,
+    /// Resigns the game, with an optional farewell message to the opponent.
+    ///
+    /// The message must be at most [`MAX_FAREWELL_LEN`] bytes long,
+    /// and an empty one is the same as none.
+    Resign(Option<Box<str>>),
     /// Makes a request.
     Request(Request),
+    /// Shares the cursor position, or hides it if `None`.
+    Cursor(Option<Point>),
+    /// When sent upon connection, requests to start a new game
+    /// as Black, reserving White's seat with the second passcode.
+    ///
+    /// The passcodes must differ.
+    StartReserved(Passcode, Passcode),
+    /// Requests a one-time code for moving the seat to another connection,
+    /// which authenticates in place of the passcode until it expires.
+    ///
+    /// A new code replaces the previous one.
+    Transfer,
 }
 
 impl ClientMessage {
@@ -77,38 +226,67 @@ impl ClientMessage {
                 p.encode(&mut buf);
                 buf.put_u8(dir as u8);
             }
-            Self::Resign => {}
+            Self::Resign(farewell) => {
+                if let Some(farewell) = farewell {
+                    buf.put_slice(farewell.as_bytes());
+                }
+            }
             Self::Request(req) => buf.put_u8(req as u8),
+            Self::Cursor(p) => {
+                if let Some(p) = p {
+                    p.encode(&mut buf);
+                }
+            }
+            Self::StartReserved(black, white) => {
+                buf.put_usize_varint(black.len());
+                buf.put_slice(&black);
+                buf.put_slice(&white);
+            }
+            Self::Transfer => {}
         }
         buf
     }
 
     /// Decodes a client message from a buffer.
     #[must_use]
-    pub fn decode(mut buf: &[u8]) -> Option<Self> {
+    pub fn decode(buf: &[u8]) -> Option<Self> {
+        Self::decode_err(buf).ok()
+    }
+
+    /// Decodes a client message from a buffer, telling why it is malformed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the message is malformed.
+    pub fn decode_err(mut buf: &[u8]) -> Result<Self, ProtocolError> {
         use ClientMessageKind as Kind;
 
-        let msg = match Kind::from_repr(buf.try_get_u8().ok()?)? {
+        let kind = buf.try_get_u8().map_err(|_| ProtocolError::Truncated)?;
+        let msg = match Kind::from_repr(kind).ok_or(ProtocolError::UnknownKind(kind))? {
             Kind::Start => Self::Start(Box::from(mem::take(&mut buf))),
-            Kind::Join => Self::Join(mem::take(&mut buf).try_into().ok()?),
-            Kind::Place => {
-                let p1 = Point::decode(&mut buf)?;
-                let p2 = if buf.has_remaining() {
-                    Some(Point::decode(&mut buf)?)
-                } else {
-                    None
-                };
-                Self::Place(p1, p2)
-            }
+            Kind::Join => Self::Join(game_id(&mut buf)?),
+            Kind::Place => Self::Place(
+                varint_field(&mut buf, "point", Point::decode)?,
+                opt_varint_field(&mut buf, "point", Point::decode)?,
+            ),
             Kind::Pass => Self::Pass,
             Kind::ClaimWin => Self::ClaimWin(
-                Point::decode(&mut buf)?,
-                Direction::from_u8(buf.try_get_u8().ok()?)?,
+                varint_field(&mut buf, "point", Point::decode)?,
+                field(&mut buf, "direction", |buf| {
+                    Direction::from_u8(buf.get_u8())
+                })?,
             ),
-            Kind::Resign => Self::Resign,
-            Kind::Request => Self::Request(Request::from_u8(buf.try_get_u8().ok()?)?),
+            Kind::Resign => Self::Resign(opt_field(&mut buf, "farewell", farewell)?),
+            Kind::Request => Self::Request(field(&mut buf, "request", |buf| {
+                Request::from_u8(buf.get_u8())
+            })?),
+            Kind::Cursor => Self::Cursor(opt_varint_field(&mut buf, "point", Point::decode)?),
+            Kind::StartReserved => {
+                Self::StartReserved(prefixed_passcode(&mut buf)?, Box::from(mem::take(&mut buf)))
+            }
+            Kind::Transfer => Self::Transfer,
         };
-        (!buf.has_remaining()).then_some(msg)
+        end(buf, msg)
     }
 }
 
@@ -127,6 +305,18 @@ pub enum ServerMessage {
     Retract,
     /// A player made a request.
     Request(Stone, Request),
+    /// A player shared their cursor position, or hid it if `None`.
+    Cursor(Stone, Option<Point>),
+    /// A player left a farewell message, sent right before their resignation.
+    Farewell(Stone, Box<str>),
+    /// A seat transfer code was generated, sent only to the requesting user.
+    TransferCode(Passcode),
+    /// The passcode of the seat, sent right after `Started` if the user
+    /// signed in with a transfer code, so as to sign in with it again.
+    Passcode(Passcode),
+    /// The time for which a player cannot request to retract again
+    /// after their request is declined, sent first on joining.
+    RetractCooldown(Duration),
 }
 
 impl ServerMessage {
@@ -148,33 +338,82 @@ impl ServerMessage {
                 buf.put_u8(stone as u8);
                 buf.put_u8(request as u8);
             }
+            Self::Cursor(stone, p) => {
+                buf.put_u8(stone as u8);
+                if let Some(p) = p {
+                    p.encode(&mut buf);
+                }
+            }
+            Self::Farewell(stone, farewell) => {
+                buf.put_u8(stone as u8);
+                buf.put_slice(farewell.as_bytes());
+            }
+            Self::TransferCode(code) => buf.put_slice(&code),
+            Self::Passcode(passcode) => buf.put_slice(&passcode),
+            Self::RetractCooldown(cooldown) => {
+                buf.put_u64_varint(u64::try_from(cooldown.as_millis()).unwrap_or(u64::MAX));
+            }
         }
         buf
     }
 
     /// Decodes a server message from a buffer.
     #[must_use]
-    pub fn decode(mut buf: &[u8]) -> Option<Self> {
+    pub fn decode(buf: &[u8]) -> Option<Self> {
+        Self::decode_err(buf).ok()
+    }
+
+    /// Decodes a server message from a buffer, telling why it is malformed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the message is malformed.
+    pub fn decode_err(mut buf: &[u8]) -> Result<Self, ProtocolError> {
         use ServerMessageKind as Kind;
 
-        let msg = match Kind::from_repr(buf.try_get_u8().ok()?)? {
+        let stone = |buf: &mut &[u8]| Stone::from_u8(buf.get_u8());
+
+        let kind = buf.try_get_u8().map_err(|_| ProtocolError::Truncated)?;
+        let msg = match Kind::from_repr(kind).ok_or(ProtocolError::UnknownKind(kind))? {
             Kind::Started => {
-                let stone = Stone::from_u8(buf.try_get_u8().ok()?)?;
+                let stone = field(&mut buf, "stone", stone)?;
                 let game_id = if buf.has_remaining() {
-                    Some(mem::take(&mut buf).try_into().ok()?)
+                    Some(game_id(&mut buf)?)
                 } else {
                     None
                 };
                 Self::Started(stone, game_id)
             }
-            Kind::Record => Self::Record(Box::new(Record::decode(&mut buf, false)?)),
-            Kind::Move => Self::Move(Move::decode(&mut buf, false)?),
+            Kind::Record => {
+                let record = Record::decode(&mut buf, false);
+                Self::Record(Box::new(
+                    record.ok_or(ProtocolError::InvalidValue("record"))?,
+                ))
+            }
+            Kind::Move => Self::Move(varint_field(&mut buf, "move", |buf| {
+                Move::decode(buf, false)
+            })?),
             Kind::Retract => Self::Retract,
             Kind::Request => Self::Request(
-                Stone::from_u8(buf.try_get_u8().ok()?)?,
-                Request::from_u8(buf.try_get_u8().ok()?)?,
+                field(&mut buf, "stone", stone)?,
+                field(&mut buf, "request", |buf| Request::from_u8(buf.get_u8()))?,
+            ),
+            Kind::Cursor => Self::Cursor(
+                field(&mut buf, "stone", stone)?,
+                opt_varint_field(&mut buf, "point", Point::decode)?,
             ),
+            Kind::Farewell => Self::Farewell(
+                field(&mut buf, "stone", stone)?,
+                field(&mut buf, "farewell", farewell)?,
+            ),
+            Kind::TransferCode => Self::TransferCode(Box::from(mem::take(&mut buf))),
+            Kind::Passcode => Self::Passcode(Box::from(mem::take(&mut buf))),
+            Kind::RetractCooldown => {
+                Self::RetractCooldown(varint_field(&mut buf, "cooldown", |buf| {
+                    buf.try_get_u64_varint().ok().map(Duration::from_millis)
+                })?)
+            }
         };
-        (!buf.has_remaining()).then_some(msg)
+        end(buf, msg)
     }
 }
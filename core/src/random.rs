@@ -0,0 +1,104 @@
+//! Random game generation, for testing and load testing.
+
+use crate::game::{Move, Point, Record};
+use rand::{seq::SliceRandom, RngCore};
+use std::iter;
+
+/// A policy for choosing random moves.
+///
+/// The trait is object safe, so that a policy can be chosen at runtime
+/// as a `Box<dyn Policy>`.
+pub trait Policy {
+    /// Chooses a placement to make on the record, which must not be ended.
+    ///
+    /// Returns `None` if no placement is available.
+    fn choose(&mut self, record: &Record, rng: &mut dyn RngCore) -> Option<Move>;
+}
+
+/// Returns the empty points within `radius` from the origin.
+fn empty_points(record: &Record, radius: i16) -> Vec<Point> {
+    (-radius..=radius)
+        .flat_map(|x| (-radius..=radius).map(move |y| Point::new(x, y)))
+        .filter(|&p| record.stone_at(p).is_none())
+        .collect()
+}
+
+/// Chooses as many stones as allowed from the candidates.
+fn choose_place(record: &Record, candidates: &[Point], rng: &mut dyn RngCore) -> Option<Move> {
+    let n = record.max_stones_to_play();
+    let mut chosen = candidates.choose_multiple(rng, n).copied();
+    Some(Move::Place(chosen.next()?, chosen.next()))
+}
+
+/// Places stones uniformly at random within a square around the origin.
+#[derive(Clone, Copy, Debug)]
+pub struct Uniform {
+    /// The maximum distance of a stone from the origin on either axis.
+    pub radius: i16,
+}
+
+impl Policy for Uniform {
+    fn choose(&mut self, record: &Record, rng: &mut dyn RngCore) -> Option<Move> {
+        choose_place(record, &empty_points(record, self.radius), rng)
+    }
+}
+
+/// Places stones at random near existing ones, within a square around the origin.
+///
+/// Games played by this policy look more like real ones than uniform ones,
+/// with stones crowded together and rows formed more often.
+#[derive(Clone, Copy, Debug)]
+pub struct NearStones {
+    /// The maximum distance of a stone from the origin on either axis.
+    pub radius: i16,
+    /// The maximum distance of a stone from an existing one on either axis.
+    pub distance: i16,
+}
+
+impl Policy for NearStones {
+    fn choose(&mut self, record: &Record, rng: &mut dyn RngCore) -> Option<Move> {
+        let empty = empty_points(record, self.radius);
+        let d = self.distance;
+        let near: Vec<Point> = empty
+            .iter()
+            .copied()
+            .filter(|p| {
+                (-d..=d).any(|dx| {
+                    (-d..=d).any(|dy| {
+                        let q = Point::new(p.x.saturating_add(dx), p.y.saturating_add(dy));
+                        q != *p && record.stone_at(q).is_some()
+                    })
+                })
+            })
+            .collect();
+
+        // Fall back to all empty points on an empty board.
+        let candidates = if near.is_empty() { &empty } else { &near };
+        choose_place(record, candidates, rng)
+    }
+}
+
+impl Record {
+    /// Plays random moves chosen by `policy` until the game ends or no
+    /// placement is available, claiming a win as soon as a row is formed.
+    pub fn random_playout<P>(&mut self, rng: &mut dyn RngCore, policy: &mut P)
+    where
+        P: Policy + ?Sized,
+    {
+        while !self.is_ended() {
+            let Some(mov) = policy.choose(self, rng) else {
+                return;
+            };
+            assert!(self.make_move(mov), "policy chose an illegal move");
+
+            if let Move::Place(p1, p2) = mov {
+                if let Some((p, dir)) = iter::once(p1)
+                    .chain(p2)
+                    .find_map(|p| self.find_winning_row(p))
+                {
+                    self.make_move(Move::Win(p, dir));
+                }
+            }
+        }
+    }
+}
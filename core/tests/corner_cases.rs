@@ -1,6 +1,9 @@
 #![allow(missing_docs)]
 
-use c6ol_core::game::{Move, Point, Record};
+use c6ol_core::game::{
+    Direction, GameResult, Move, MoveError, Point, Record, RecordDecoder, RecordEncoder, Stone,
+    Turn, WinBy,
+};
 
 #[test]
 fn place_in_corner() {
@@ -21,3 +24,399 @@ fn place_in_corner() {
         }
     }
 }
+
+#[test]
+fn illegal_moves() {
+    let mut record = Record::new();
+    let (p, q) = (Point::new(0, 0), Point::new(1, 0));
+
+    assert_eq!(
+        record.try_move(Move::Place(p, Some(q))),
+        Err(MoveError::FirstMoveTwoStones)
+    );
+    assert_eq!(record.try_move(Move::Place(p, None)), Ok(()));
+    assert_eq!(
+        record.try_move(Move::Place(q, Some(p))),
+        Err(MoveError::Occupied)
+    );
+    assert_eq!(
+        record.try_move(Move::Win(p, Direction::East)),
+        Err(MoveError::InvalidWinningRow)
+    );
+    assert_eq!(record.try_move(Move::Resign(Stone::White)), Ok(()));
+    assert_eq!(record.try_move(Move::Pass), Err(MoveError::Ended));
+    assert_eq!(record.move_index(), 2);
+}
+
+#[test]
+fn truncate_future() {
+    let mut record = Record::new();
+    assert!(record.make_move(Move::Place(Point::new(0, 0), None)));
+    for _ in 0..3 {
+        assert!(record.make_move(Move::Pass));
+    }
+    let size = record.heap_size();
+
+    assert!(record.jump(1));
+    record.truncate_future(1);
+    assert_eq!(record.moves().len(), 2);
+    assert_eq!(record.move_index(), 1);
+    assert!(record.heap_size() < size);
+
+    record.truncate_future(usize::MAX);
+    assert_eq!(record.moves().len(), 2);
+    record.truncate_future(0);
+    assert!(!record.has_future());
+}
+
+#[test]
+fn future_limit() {
+    let mut record = Record::new();
+    assert!(record.make_move(Move::Place(Point::new(0, 0), None)));
+    for _ in 0..3 {
+        assert!(record.make_move(Move::Pass));
+    }
+
+    assert!(record.jump(1));
+    record.set_future_limit(Some(1));
+    assert_eq!(record.future_limit(), Some(1));
+    assert_eq!(record.moves().len(), 2);
+
+    // The limit holds as more moves are made and undone.
+    assert!(record.redo_move().is_some());
+    for _ in 0..3 {
+        assert!(record.make_move(Move::Pass));
+    }
+    assert_eq!(record.moves().len(), 5);
+    assert!(record.jump(2));
+    assert_eq!(record.moves().len(), 3);
+    assert!(record.undo_move().is_some());
+    assert_eq!(record.moves().len(), 2);
+    assert_eq!(record.move_index(), 1);
+
+    record.set_future_limit(Some(0));
+    assert!(!record.has_future());
+
+    // Without a limit the future is kept again.
+    record.set_future_limit(None);
+    assert!(record.make_move(Move::Pass));
+    assert!(record.jump(0));
+    assert_eq!(record.moves().len(), 2);
+}
+
+#[test]
+fn normalize_win() {
+    let p = Point::new(0, 0);
+    for (fwd, bwd) in Direction::OPPOSITE_PAIRS {
+        let (dx, dy) = fwd.unit_vec();
+        let q = Point::new(dx * 5, dy * 5);
+        assert_eq!(Move::Win(p, fwd).normalize_win(), Move::Win(p, fwd));
+        assert_eq!(Move::Win(q, bwd).normalize_win(), Move::Win(p, fwd));
+    }
+
+    // Claims that cannot be valid are left unchanged.
+    let p = Point::new(i16::MIN, 0);
+    assert_eq!(
+        Move::Win(p, Direction::West).normalize_win(),
+        Move::Win(p, Direction::West)
+    );
+    assert_eq!(Move::Pass.normalize_win(), Move::Pass);
+}
+
+#[test]
+fn encode_incrementally() {
+    let mut record = Record::new();
+    let mut encoder = RecordEncoder::new();
+    let mut buf = vec![];
+
+    let moves = [
+        Move::Place(Point::new(0, 0), None),
+        Move::Place(Point::new(1, 0), None),
+        Move::Pass,
+        Move::Place(Point::new(2, 0), Some(Point::new(-1, 0))),
+        Move::Resign(Stone::Black),
+    ];
+    for mov in moves {
+        assert!(record.make_move(mov));
+        assert!(encoder.encode(&record, &mut buf));
+        assert_eq!(encoder.len(), record.move_index());
+
+        let mut expected = vec![];
+        record.encode(&mut expected, false);
+        assert_eq!(buf, expected);
+    }
+
+    assert!(encoder.encode(&record, &mut buf));
+    record.undo_move();
+    assert!(!encoder.encode(&record, &mut buf));
+}
+
+#[test]
+fn decode_incrementally() {
+    let mut record = Record::new();
+    assert!(record.make_move(Move::Place(Point::new(0, 0), None)));
+    for i in 1..=4 {
+        assert!(record.make_move(Move::Place(Point::new(i, 0), Some(Point::new(0, i)))));
+    }
+    assert!(record.jump(3));
+
+    let mut buf = vec![];
+    record.encode(&mut buf, true);
+
+    let mut rest = &buf[..];
+    let mut decoder = RecordDecoder::new(&mut rest, true).unwrap();
+    while !rest.is_empty() {
+        let len = decoder.len();
+        assert!(decoder.decode(&mut rest, 2));
+        assert!(decoder.len() <= len + 2);
+    }
+    assert_eq!(decoder.len(), 5);
+    assert_eq!(decoder.finish(), Some(record));
+
+    // An illegal move fails to decode.
+    let mut buf = vec![];
+    Move::Place(Point::new(0, 0), None).encode(&mut buf, true);
+    Move::Place(Point::new(1, 0), Some(Point::new(0, 0))).encode(&mut buf, false);
+    let mut rest = &buf[..];
+    let mut decoder = RecordDecoder::new(&mut rest, false).unwrap();
+    assert!(decoder.decode(&mut rest, 1));
+    assert!(!decoder.decode(&mut rest, 1));
+}
+
+#[test]
+fn checksum() {
+    let mut record = Record::new();
+    assert!(record.make_move(Move::Place(Point::new(0, 0), None)));
+    assert!(record.make_move(Move::Place(Point::new(1, 0), Some(Point::new(2, 0)))));
+    assert!(record.make_move(Move::Place(Point::new(300, 0), None)));
+
+    for all in [false, true] {
+        let mut buf = vec![];
+        record.encode_with_checksum(&mut buf, all);
+        assert_eq!(Record::decode(&mut &buf[..], all).as_ref(), Some(&record));
+
+        // Truncated or corrupted records fail to decode,
+        // unless truncated before the checksum marker.
+        let marker = usize::from(all);
+        assert_eq!(buf[marker], 4);
+        for len in marker + 1..buf.len() {
+            assert_eq!(Record::decode(&mut &buf[..len], all), None);
+        }
+        for i in 0..buf.len() {
+            let mut corrupted = buf.clone();
+            corrupted[i] ^= 0x10;
+            assert_ne!(
+                Record::decode(&mut &corrupted[..], all).as_ref(),
+                Some(&record)
+            );
+        }
+    }
+
+    // Records without the checksum still decode.
+    let mut buf = vec![];
+    record.encode(&mut buf, false);
+    assert_eq!(Record::decode(&mut &buf[..], false), Some(record));
+
+    let mut buf = vec![];
+    Record::new().encode_with_checksum(&mut buf, false);
+    assert_eq!(Record::decode(&mut &buf[..], false), Some(Record::new()));
+}
+
+#[test]
+fn make_moves() {
+    let mut record = Record::new();
+    let (p, q, r) = (Point::new(0, 0), Point::new(1, 0), Point::new(2, 0));
+    assert_eq!(record.make_moves(&[]), Ok(0));
+    assert_eq!(
+        record.make_moves(&[Move::Place(p, None), Move::Place(q, Some(r))]),
+        Ok(2)
+    );
+
+    assert!(record.jump(1));
+    let before = record.clone();
+    assert_eq!(
+        record.make_moves(&[Move::Pass, Move::Place(p, None)]),
+        Err((1, MoveError::Occupied))
+    );
+    assert_eq!(record, before);
+
+    assert_eq!(record.make_moves(&[Move::Pass]), Ok(1));
+    assert_eq!(record.moves(), &[Move::Place(p, None), Move::Pass]);
+}
+
+#[test]
+fn push_move() {
+    let mut record = Record::new();
+    let (p, q, r) = (Point::new(0, 0), Point::new(1, 0), Point::new(2, 0));
+    assert_eq!(
+        record.make_moves(&[Move::Place(p, None), Move::Place(q, None)]),
+        Ok(2)
+    );
+    assert!(record.jump(1));
+
+    // A spectator browsing the history receives a move.
+    assert!(record.push_move(Move::Place(r, None)));
+    assert_eq!(
+        record.moves(),
+        &[
+            Move::Place(p, None),
+            Move::Place(q, None),
+            Move::Place(r, None)
+        ]
+    );
+    assert_eq!(record.move_index(), 1);
+    assert_eq!(record.stone_at(q), None);
+
+    assert!(!record.push_move(Move::Place(q, None)));
+    assert_eq!(record.moves().len(), 3);
+    assert_eq!(record.move_index(), 1);
+}
+
+#[test]
+fn result() {
+    let mut record = Record::new();
+    assert_eq!(record.result(), None);
+
+    let row: Vec<_> = (0..6).map(|x| Point::new(x, 0)).collect();
+    let others: Vec<_> = (0..6).map(|x| Point::new(x, 1)).collect();
+    assert!(record.make_move(Move::Place(row[0], None)));
+    assert!(record.make_move(Move::Place(others[0], Some(others[1]))));
+    assert!(record.make_move(Move::Place(row[1], Some(row[2]))));
+    assert!(record.make_move(Move::Place(others[2], Some(others[3]))));
+    assert!(record.make_move(Move::Place(row[3], Some(row[4]))));
+    assert!(record.make_move(Move::Place(others[4], None)));
+    assert!(record.make_move(Move::Place(row[5], None)));
+    assert_eq!(record.result(), None);
+
+    // A row claimed by White still wins for Black.
+    assert!(record.make_move(Move::Win(row[0], Direction::East)));
+    assert_eq!(
+        record.result(),
+        Some(GameResult::Win(Stone::Black, WinBy::Row))
+    );
+    assert_eq!(record.result().unwrap().winner(), Some(Stone::Black));
+
+    record.undo_move();
+    assert!(record.make_move(Move::Resign(Stone::White)));
+    assert_eq!(
+        record.result(),
+        Some(GameResult::Win(Stone::Black, WinBy::Resignation))
+    );
+
+    record.undo_move();
+    assert!(record.make_move(Move::Draw));
+    assert_eq!(record.result(), Some(GameResult::Draw));
+    assert_eq!(record.result().unwrap().winner(), None);
+}
+
+#[test]
+fn turns() {
+    let (p, q, r) = (Point::new(0, 0), Point::new(1, 0), Point::new(2, 0));
+    let mut record = Record::new();
+    let moves = [Move::Place(p, None), Move::Place(q, Some(r)), Move::Pass];
+    assert_eq!(record.make_moves(&moves), Ok(3));
+    assert!(record.jump(1));
+
+    let turns: Vec<Turn> = record.turns().collect();
+    assert_eq!(turns.len(), 3);
+    assert_eq!(
+        turns[1],
+        Turn {
+            index: 1,
+            stone: Stone::White,
+            mov: moves[1],
+        }
+    );
+    assert_eq!(turns[0].placed().collect::<Vec<_>>(), [p]);
+    assert_eq!(turns[1].placed().collect::<Vec<_>>(), [q, r]);
+    assert_eq!(turns[2].stone, Stone::Black);
+    assert_eq!(turns[2].placed().count(), 0);
+}
+
+#[test]
+fn from_position() {
+    let points: Vec<_> = (0..6).map(|x| Point::new(x, 0)).collect();
+
+    let record = Record::from_position(&points[..4], &points[4..]).unwrap();
+    assert_eq!(
+        record.moves(),
+        [
+            Move::Place(points[0], None),
+            Move::Place(points[4], Some(points[5])),
+            Move::Place(points[1], Some(points[2])),
+            Move::Pass,
+            Move::Place(points[3], None),
+        ]
+    );
+    for (i, &p) in points.iter().enumerate() {
+        let stone = if i < 4 { Stone::Black } else { Stone::White };
+        assert_eq!(record.stone_at(p), Some(stone));
+    }
+
+    // A row of six is placed without ending the game.
+    let record = Record::from_position(&[], &points).unwrap();
+    assert_eq!(record.moves().len(), 6);
+    assert_eq!(record.turn(), Some(Stone::Black));
+
+    assert_eq!(Record::from_position(&[], &[]), Some(Record::new()));
+    assert_eq!(Record::from_position(&points[..1], &points[..1]), None);
+}
+
+#[test]
+fn stone_values() {
+    for (i, stone) in Stone::VALUES.into_iter().enumerate() {
+        assert_eq!(stone.index(), i);
+        assert_eq!(Stone::from_u8(stone as u8), Some(stone));
+    }
+    assert_eq!(Stone::VALUES[0].opposite(), Stone::VALUES[1]);
+}
+
+#[test]
+fn normalized() {
+    let row: Vec<_> = (0..6).map(|x| Point::new(x, 0)).collect();
+    let (p, q) = (Point::new(0, 1), Point::new(0, 2));
+
+    let mut a = Record::new();
+    let moves = [
+        Move::Place(row[0], None),
+        Move::Place(p, Some(q)),
+        Move::Place(row[1], Some(row[2])),
+        Move::Pass,
+        Move::Place(row[3], Some(row[4])),
+        Move::Pass,
+        Move::Place(row[5], None),
+        Move::Win(row[0], Direction::East),
+    ];
+    assert_eq!(a.make_moves(&moves), Ok(8));
+
+    let mut b = Record::new();
+    let moves = [
+        Move::Place(row[0], None),
+        Move::Place(q, Some(p)),
+        Move::Place(row[2], Some(row[1])),
+        Move::Pass,
+        Move::Place(row[4], Some(row[3])),
+        Move::Pass,
+        Move::Place(row[5], None),
+        Move::Win(row[5], Direction::West),
+    ];
+    assert_eq!(b.make_moves(&moves), Ok(8));
+
+    assert_ne!(a, b);
+    assert_eq!(a.normalized(), b.normalized());
+    assert_eq!(a.normalized().normalized(), a.normalized());
+    assert_eq!(a.normalized().result(), a.result());
+
+    // Moves in the future are dropped.
+    assert!(b.jump(3));
+    let normalized = b.normalized();
+    assert!(!normalized.has_future());
+    assert_eq!(normalized.moves(), &a.normalized().moves()[..3]);
+    assert_eq!(normalized.stone_at(row[1]), Some(Stone::Black));
+    assert_eq!(normalized.stone_at(row[3]), None);
+
+    // Moves are normalized one by one in the same way.
+    for (mov_a, mov_b) in a.moves().iter().zip(b.moves()) {
+        assert_eq!(mov_a.normalized(), mov_b.normalized());
+    }
+}
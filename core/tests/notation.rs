@@ -0,0 +1,103 @@
+#![allow(missing_docs)]
+
+use c6ol_core::game::{Direction, Move, Point, Record, Stone};
+
+#[test]
+fn points() {
+    let cases = [
+        ((0, 0), "K10"),
+        ((-9, 9), "A1"),
+        ((9, -9), "T19"),
+        ((-1, 0), "J10"),
+        ((15, 0), "Z10"),
+        ((16, 0), "AA10"),
+        ((-10, 0), "a10"),
+        ((-34, 0), "z10"),
+        ((-35, 0), "aa10"),
+        ((0, 10), "K0"),
+        ((0, 11), "K-1"),
+    ];
+    for ((x, y), s) in cases {
+        let p = Point::new(x, y);
+        assert_eq!(p.to_notation(), s);
+        assert_eq!(Point::from_notation(s), Some(p));
+    }
+
+    for x in [i16::MIN, i16::MAX] {
+        for y in [i16::MIN, i16::MAX] {
+            let p = Point::new(x, y);
+            assert_eq!(Point::from_notation(&p.to_notation()), Some(p));
+        }
+    }
+
+    for s in [
+        "", "K", "10", "I10", "i10", "Ka10", "K+10", "K10x", "ZZZZ10", "K99999",
+    ] {
+        assert_eq!(Point::from_notation(s), None, "{s}");
+    }
+}
+
+#[test]
+fn moves() {
+    let p = Point::new(0, 0);
+    let q = Point::new(1, -1);
+    let cases = [
+        (Move::Place(p, None), "K10"),
+        (Move::Place(p, Some(q)), "K10,L11"),
+        (Move::Pass, "pass"),
+        (Move::Win(p, Direction::Northeast), "win:K10/NE"),
+        (Move::Draw, "draw"),
+        (Move::Resign(Stone::Black), "resign:B"),
+        (Move::Resign(Stone::White), "resign:W"),
+    ];
+    for (mov, s) in cases {
+        assert_eq!(mov.to_notation(), s);
+        assert_eq!(Move::from_notation(s), Some(mov));
+    }
+
+    for (fwd, bwd) in Direction::OPPOSITE_PAIRS {
+        for dir in [fwd, bwd] {
+            let mov = Move::Win(p, dir);
+            assert_eq!(Move::from_notation(&mov.to_notation()), Some(mov));
+        }
+    }
+
+    for s in [
+        "",
+        "K10,",
+        "K10,L11,M12",
+        "win:K10",
+        "win:K10/X",
+        "resign:X",
+        "Pass",
+    ] {
+        assert_eq!(Move::from_notation(s), None, "{s}");
+    }
+}
+
+#[test]
+fn records() {
+    let mut record = Record::new();
+    assert_eq!(record.to_notation_string(), "");
+    assert_eq!(Record::from_notation_string(" "), Some(Record::new()));
+
+    let moves = [
+        Move::Place(Point::new(0, 0), None),
+        Move::Place(Point::new(1, 0), Some(Point::new(-20, 3))),
+        Move::Pass,
+        Move::Resign(Stone::White),
+    ];
+    assert_eq!(record.make_moves(&moves), Ok(4));
+    let s = record.to_notation_string();
+    assert_eq!(s, "K10 L10,l7 pass resign:W");
+    assert_eq!(Record::from_notation_string(&s), Some(record.clone()));
+
+    // Only past moves are written.
+    assert!(record.jump(2));
+    assert_eq!(record.to_notation_string(), "K10 L10,l7");
+
+    // Malformed or illegal moves fail to parse.
+    assert_eq!(Record::from_notation_string("K10 K10"), None);
+    assert_eq!(Record::from_notation_string("K10,L10"), None);
+    assert_eq!(Record::from_notation_string("K10 bogus"), None);
+}
@@ -0,0 +1,110 @@
+#![allow(missing_docs)]
+
+use c6ol_core::game::{Move, Point, Record, Stone};
+
+/// A bounded board with its top-left corner at the origin.
+#[derive(Clone, Copy)]
+struct Bounds {
+    width: i16,
+    height: i16,
+}
+
+impl Bounds {
+    fn points(self) -> impl Iterator<Item = Point> {
+        (0..self.height).flat_map(move |y| (0..self.width).map(move |x| Point::new(x, y)))
+    }
+}
+
+/// Generates the legal moves within `bounds`.
+///
+/// A placement of two stones is generated once per unordered pair,
+/// and a placement of one stone only as the first move.
+/// Passes and draws are not generated.
+fn legal_moves(record: &Record, bounds: Bounds) -> Vec<Move> {
+    if record.is_ended() {
+        return vec![];
+    }
+
+    let mut moves: Vec<Move> = Stone::VALUES
+        .into_iter()
+        .flat_map(|stone| record.winning_rows(stone))
+        .map(|(p, dir)| Move::Win(p, dir))
+        .collect();
+
+    let empty: Vec<Point> = bounds
+        .points()
+        .filter(|&p| record.stone_at(p).is_none())
+        .collect();
+
+    if record.has_past() {
+        for (i, &p1) in empty.iter().enumerate() {
+            for &p2 in &empty[i + 1..] {
+                moves.push(Move::Place(p1, Some(p2)));
+            }
+        }
+    } else {
+        moves.extend(empty.into_iter().map(|p| Move::Place(p, None)));
+    }
+    moves
+}
+
+/// Counts the leaf nodes of the game tree of the given depth.
+fn perft(record: &mut Record, bounds: Bounds, depth: usize) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+
+    let mut nodes = 0;
+    for mov in legal_moves(record, bounds) {
+        let index = record.move_index();
+        assert_eq!(record.try_move(mov), Ok(()), "{mov:?}");
+        nodes += perft(record, bounds, depth - 1);
+        assert_eq!(record.undo_move(), Some(mov));
+        assert_eq!(record.move_index(), index);
+    }
+    nodes
+}
+
+fn place(record: &mut Record, p1: (i16, i16), p2: Option<(i16, i16)>) {
+    let mov = Move::Place(Point::new(p1.0, p1.1), p2.map(|(x, y)| Point::new(x, y)));
+    assert!(record.make_move(mov));
+}
+
+#[test]
+fn perft_empty() {
+    let bounds = Bounds {
+        width: 3,
+        height: 3,
+    };
+    let mut record = Record::new();
+
+    // No six-in-a-row fits, so the counts are products of binomials:
+    // 9, 9 * C(8, 2), 252 * C(6, 2), 3780 * C(4, 2), 22680 * C(2, 2).
+    let expected = [1, 9, 252, 3780, 22680, 22680];
+    for (depth, &nodes) in expected.iter().enumerate() {
+        assert_eq!(perft(&mut record, bounds, depth), nodes, "depth {depth}");
+    }
+    assert!(!record.has_past());
+}
+
+#[test]
+fn perft_near_win() {
+    let bounds = Bounds {
+        width: 8,
+        height: 2,
+    };
+    let mut record = Record::new();
+    place(&mut record, (0, 0), None);
+    place(&mut record, (0, 1), Some((1, 1)));
+    place(&mut record, (1, 0), Some((2, 0)));
+    place(&mut record, (2, 1), Some((7, 1)));
+    place(&mut record, (3, 0), Some((7, 0)));
+
+    // White to play with seven empty points. At depth 3, White either
+    // places 2 of the 3 points left, or claims the win Black has made
+    // in 10 of the 210 positions: 200 * 3 + 10 * (3 + 1) = 640.
+    let expected = [1, 21, 210, 640, 102, 0];
+    for (depth, &nodes) in expected.iter().enumerate() {
+        assert_eq!(perft(&mut record, bounds, depth), nodes, "depth {depth}");
+    }
+}
@@ -0,0 +1,148 @@
+#![allow(missing_docs)]
+
+use c6ol_core::{
+    game::{Direction, Move, Point, Stone},
+    protocol::{ClientMessage, ProtocolError, Request, ServerMessage, MAX_FAREWELL_LEN},
+};
+use std::time::Duration;
+
+#[test]
+fn decode_errors() {
+    let place = ClientMessage::Place(Point::new(1, 2), None).encode();
+    assert!(ClientMessage::decode_err(&place).is_ok());
+
+    let claim = ClientMessage::ClaimWin(Point::new(0, 0), Direction::East).encode();
+    let request = ClientMessage::Request(Request::Draw).encode();
+    let started = ServerMessage::Started(Stone::Black, Some(*b"0123456789")).encode();
+
+    let cases: [(&[u8], ProtocolError); 8] = [
+        (&[], ProtocolError::Truncated),
+        (&[0xff], ProtocolError::UnknownKind(0xff)),
+        (&[place[0]], ProtocolError::Truncated),
+        // The buffer ends in the middle of a point.
+        (&[&place[..], &[0x80]].concat(), ProtocolError::Truncated),
+        (
+            &[&place[..], &[0xff; 6], &[0]].concat(),
+            ProtocolError::InvalidValue("point"),
+        ),
+        (&claim[..claim.len() - 1], ProtocolError::Truncated),
+        (&[request[0], 9], ProtocolError::InvalidValue("request")),
+        (&[&request[..], &[0]].concat(), ProtocolError::TrailingBytes),
+    ];
+    for (buf, err) in cases {
+        assert_eq!(ClientMessage::decode_err(buf).err(), Some(err), "{buf:?}");
+        assert!(ClientMessage::decode(buf).is_none());
+    }
+
+    assert!(ServerMessage::decode_err(&started).is_ok());
+    assert_eq!(
+        ServerMessage::decode_err(&started[..started.len() - 1]).err(),
+        Some(ProtocolError::Truncated)
+    );
+    assert_eq!(
+        ServerMessage::decode_err(&[started[0], 3]).err(),
+        Some(ProtocolError::InvalidValue("stone"))
+    );
+
+    // The buffer ends in the middle of the second stone of a move.
+    let mov = Move::Place(Point::new(100, 100), Some(Point::new(-100, -100)));
+    let mov = ServerMessage::Move(mov).encode();
+    assert_eq!(
+        ServerMessage::decode_err(&mov[..mov.len() - 1]).err(),
+        Some(ProtocolError::Truncated)
+    );
+}
+
+#[test]
+fn farewell() {
+    let resign = |farewell: Option<&str>| ClientMessage::Resign(farewell.map(Box::from));
+
+    let buf = resign(Some("Well played!")).encode();
+    assert!(matches!(
+        ClientMessage::decode_err(&buf),
+        Ok(ClientMessage::Resign(Some(s))) if &*s == "Well played!"
+    ));
+
+    // An empty message is the same as none.
+    assert_eq!(resign(Some("")).encode(), resign(None).encode());
+    assert!(matches!(
+        ClientMessage::decode_err(&resign(None).encode()),
+        Ok(ClientMessage::Resign(None))
+    ));
+
+    let long = "a".repeat(MAX_FAREWELL_LEN + 1);
+    for buf in [resign(Some(&long)).encode(), vec![buf[0], 0xff]] {
+        assert_eq!(
+            ClientMessage::decode_err(&buf).err(),
+            Some(ProtocolError::InvalidValue("farewell"))
+        );
+    }
+
+    let buf = ServerMessage::Farewell(Stone::White, "gg".into()).encode();
+    assert!(matches!(
+        ServerMessage::decode_err(&buf),
+        Ok(ServerMessage::Farewell(Stone::White, s)) if &*s == "gg"
+    ));
+    assert_eq!(
+        ServerMessage::decode_err(&buf[..2]).err(),
+        Some(ProtocolError::Truncated)
+    );
+}
+
+#[test]
+fn start_reserved() {
+    let msg = ClientMessage::StartReserved(b"black".as_slice().into(), b"".as_slice().into());
+    let buf = msg.encode();
+    assert!(matches!(
+        ClientMessage::decode_err(&buf),
+        Ok(ClientMessage::StartReserved(black, white)) if &*black == b"black" && white.is_empty()
+    ));
+
+    assert_eq!(
+        ClientMessage::decode_err(&buf[..buf.len() - 1]).err(),
+        Some(ProtocolError::Truncated)
+    );
+    assert_eq!(
+        ClientMessage::decode_err(&buf[..1]).err(),
+        Some(ProtocolError::Truncated)
+    );
+}
+
+#[test]
+fn transfer() {
+    let buf = ClientMessage::Transfer.encode();
+    assert!(matches!(
+        ClientMessage::decode_err(&buf),
+        Ok(ClientMessage::Transfer)
+    ));
+
+    let buf = ServerMessage::TransferCode(b"abcd1234".as_slice().into()).encode();
+    assert!(matches!(
+        ServerMessage::decode_err(&buf),
+        Ok(ServerMessage::TransferCode(code)) if &*code == b"abcd1234"
+    ));
+
+    let buf = ServerMessage::Passcode(b"black".as_slice().into()).encode();
+    assert!(matches!(
+        ServerMessage::decode_err(&buf),
+        Ok(ServerMessage::Passcode(passcode)) if &*passcode == b"black"
+    ));
+}
+
+#[test]
+fn retract_cooldown() {
+    let cooldown = Duration::from_millis(12_345);
+    let buf = ServerMessage::RetractCooldown(cooldown).encode();
+    assert!(matches!(
+        ServerMessage::decode_err(&buf),
+        Ok(ServerMessage::RetractCooldown(d)) if d == cooldown
+    ));
+    assert_eq!(
+        ServerMessage::decode_err(&buf[..buf.len() - 1]).err(),
+        Some(ProtocolError::Truncated)
+    );
+    assert_eq!(
+        ServerMessage::decode_err(&buf[..1]).err(),
+        Some(ProtocolError::Truncated)
+    );
+}
@@ -0,0 +1,55 @@
+#![allow(missing_docs)]
+
+use c6ol_core::{
+    game::{Move, Record},
+    random::{NearStones, Policy, Uniform},
+};
+use rand::{rngs::StdRng, SeedableRng};
+
+fn check_playouts(policy: &mut dyn Policy) {
+    let mut rng = StdRng::seed_from_u64(0);
+    for _ in 0..20 {
+        let mut record = Record::new();
+        record.random_playout(&mut rng, policy);
+
+        // The playout ends in a win or on a full area.
+        match record.prev_move() {
+            Some(Move::Win(p, dir)) => assert!(record.test_winning_row(p, dir).is_some()),
+            Some(Move::Place(..)) => {}
+            mov => panic!("unexpected last move: {mov:?}"),
+        }
+
+        for all in [false, true] {
+            let mut buf = vec![];
+            record.encode(&mut buf, all);
+            assert_eq!(Record::decode(&mut &buf[..], all), Some(record.clone()));
+        }
+    }
+}
+
+#[test]
+fn uniform_playouts() {
+    check_playouts(&mut Uniform { radius: 4 });
+}
+
+#[test]
+fn boxed_policies() {
+    let policies: [Box<dyn Policy>; 2] = [
+        Box::new(Uniform { radius: 4 }),
+        Box::new(NearStones {
+            radius: 4,
+            distance: 1,
+        }),
+    ];
+    for mut policy in policies {
+        check_playouts(&mut *policy);
+    }
+}
+
+#[test]
+fn near_stones_playouts() {
+    check_playouts(&mut NearStones {
+        radius: 7,
+        distance: 1,
+    });
+}
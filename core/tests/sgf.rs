@@ -0,0 +1,177 @@
+#![allow(missing_docs)]
+
+use c6ol_core::game::{Direction, Move, MoveError, Point, Record, SgfError, Stone};
+
+#[test]
+fn to_sgf() {
+    let mut record = Record::new();
+    assert_eq!(record.to_sgf().as_deref(), Some("(;FF[4]GM[20]SZ[19])"));
+
+    let moves = [
+        Move::Place(Point::new(0, 0), None),
+        Move::Place(Point::new(1, 0), Some(Point::new(-9, 9))),
+        Move::Pass,
+        Move::Resign(Stone::White),
+    ];
+    assert_eq!(record.make_moves(&moves), Ok(4));
+    assert_eq!(
+        record.to_sgf().as_deref(),
+        Some("(;FF[4]GM[20]SZ[19]RE[B+R];B[jj];W[kj][as];B[])")
+    );
+
+    // Only past moves are written.
+    assert!(record.jump(2));
+    assert_eq!(
+        record.to_sgf().as_deref(),
+        Some("(;FF[4]GM[20]SZ[19];B[jj];W[kj][as])")
+    );
+
+    // The board grows to hold all stones.
+    record.clear();
+    assert!(record.make_move(Move::Place(Point::new(0, -12), None)));
+    assert_eq!(
+        record.to_sgf().as_deref(),
+        Some("(;FF[4]GM[20]SZ[25];B[ma])")
+    );
+
+    // A win by row and a draw.
+    record.clear();
+    let row: Vec<_> = (0..6).map(|x| Point::new(x, 0)).collect();
+    let moves = [
+        Move::Place(row[0], None),
+        Move::Pass,
+        Move::Place(row[1], Some(row[2])),
+        Move::Pass,
+        Move::Place(row[3], Some(row[4])),
+        Move::Pass,
+        Move::Place(row[5], None),
+        Move::Win(row[0], Direction::East),
+    ];
+    assert_eq!(record.make_moves(&moves), Ok(8));
+    assert!(record
+        .to_sgf()
+        .unwrap()
+        .starts_with("(;FF[4]GM[20]SZ[19]RE[B+];"));
+    record.undo_move();
+    assert!(record.make_move(Move::Draw));
+    assert!(record
+        .to_sgf()
+        .unwrap()
+        .starts_with("(;FF[4]GM[20]SZ[19]RE[0];"));
+}
+
+#[test]
+fn to_sgf_far_from_origin() {
+    // Stones far from the origin are centered on.
+    let mut record = Record::new();
+    assert!(record.make_move(Move::Place(Point::new(1000, 1000), None)));
+    assert!(record.make_move(Move::Place(
+        Point::new(1010, 1000),
+        Some(Point::new(990, 1000))
+    )));
+    assert_eq!(
+        record.to_sgf().as_deref(),
+        Some("(;FF[4]GM[20]SZ[21];B[kk];W[uk][ak])")
+    );
+
+    // Stones too far apart do not fit.
+    assert!(record.make_move(Move::Place(Point::new(1000, 1060), None)));
+    assert_eq!(record.to_sgf(), None);
+}
+
+#[test]
+fn from_sgf_round_trip() {
+    let row: Vec<_> = (0..6).map(|x| Point::new(x, 0)).collect();
+    let moves = [
+        Move::Place(row[0], None),
+        Move::Place(Point::new(-9, 9), Some(Point::new(9, -9))),
+        Move::Place(row[1], Some(row[2])),
+        Move::Pass,
+        Move::Place(row[3], Some(row[4])),
+        Move::Place(Point::new(0, 1), None),
+        Move::Place(row[5], None),
+    ];
+    let mut record = Record::new();
+    assert_eq!(record.make_moves(&moves), Ok(7));
+    for ending in [
+        Move::Win(row[0], Direction::East),
+        Move::Resign(Stone::White),
+        Move::Draw,
+    ] {
+        assert!(record.make_move(ending));
+        let sgf = record.to_sgf().unwrap();
+        assert_eq!(Record::from_sgf(&sgf), Ok(record.clone()));
+        record.undo_move();
+    }
+
+    assert_eq!(Record::from_sgf("(;FF[4]GM[20]SZ[19])"), Ok(Record::new()));
+}
+
+#[test]
+fn from_sgf_other_clients() {
+    // One stone per node, with comments, variations and escapes.
+    let sgf = "(;FF[4]SZ[19]PB[Alice]PW[Bob \\[x\\]]RE[W+Time]C[Good game]
+        ;B[jj];W[ki]C[Hmm];W[ik](;B[ll];B[hh];W[tt])(;B[aa]))";
+    let record = Record::from_sgf(sgf).unwrap();
+    assert_eq!(
+        record.moves(),
+        [
+            Move::Place(Point::new(0, 0), None),
+            Move::Place(Point::new(1, -1), Some(Point::new(-1, 1))),
+            Move::Place(Point::new(2, 2), Some(Point::new(-2, -2))),
+            Move::Pass,
+        ]
+    );
+    // A win on time is not recorded.
+    assert_eq!(record.result(), None);
+
+    // Lowercase letters in identifiers are ignored.
+    let record = Record::from_sgf("(;GaMe[20]SiZe[15:15];Black[hh])").unwrap();
+    assert_eq!(record.moves(), [Move::Place(Point::new(0, 0), None)]);
+}
+
+#[test]
+fn from_sgf_errors() {
+    for sgf in ["", "(;B[jj]", "(;B)", "(;C[unclosed)"] {
+        assert_eq!(Record::from_sgf(sgf), Err(SgfError::Syntax), "{sgf}");
+    }
+    assert_eq!(
+        Record::from_sgf("(;GM[1];B[jj])"),
+        Err(SgfError::Unsupported)
+    );
+    assert_eq!(
+        Record::from_sgf("(;AB[jj];W[ii])"),
+        Err(SgfError::Unsupported)
+    );
+    for sgf in ["(;SZ[53])", "(;SZ[9];B[jk])", "(;B[j])", "(;B[j!])"] {
+        assert_eq!(Record::from_sgf(sgf), Err(SgfError::InvalidPoint), "{sgf}");
+    }
+    assert_eq!(Record::from_sgf("(;W[jj])"), Err(SgfError::InvalidTurn(0)));
+    assert_eq!(
+        Record::from_sgf("(;B[jj];W[aa][bb][cc])"),
+        Err(SgfError::InvalidTurn(1))
+    );
+    assert_eq!(
+        Record::from_sgf("(;B[jj];W[aa][])"),
+        Err(SgfError::InvalidTurn(1))
+    );
+    assert_eq!(
+        Record::from_sgf("(;B[jj];W[aa][jj])"),
+        Err(SgfError::IllegalMove(1, MoveError::Occupied))
+    );
+    assert_eq!(
+        Record::from_sgf("(;B[jj][kk])"),
+        Err(SgfError::IllegalMove(0, MoveError::FirstMoveTwoStones))
+    );
+}
+
+#[test]
+fn from_sgf_deep_nesting() {
+    let depth = 100_000;
+    let sgf = format!("{};B[jj]{}", "(".repeat(depth), ")".repeat(depth));
+    let record = Record::from_sgf(&sgf).unwrap();
+    assert_eq!(record.moves(), [Move::Place(Point::new(0, 0), None)]);
+
+    let sgf = format!("(;B[jj]{})", "(;W[aa]".repeat(depth));
+    assert_eq!(Record::from_sgf(&sgf), Err(SgfError::Syntax));
+}
@@ -0,0 +1,29 @@
+#![allow(missing_docs)]
+
+use c6ol_core::game::{Direction, Move, Point, Record, Stone};
+
+fn place(record: &mut Record, (x1, y1): (i16, i16), (x2, y2): (i16, i16)) {
+    let mov = Move::Place(Point::new(x1, y1), Some(Point::new(x2, y2)));
+    assert!(record.make_move(mov));
+}
+
+#[test]
+fn find_all_winning_rows() {
+    let mut record = Record::new();
+    assert!(record.make_move(Move::Place(Point::new(0, 0), None)));
+
+    for x in 0..3 {
+        place(&mut record, (x, 1), (x, 2));
+        if x < 2 {
+            place(&mut record, (2 * x + 1, 0), (2 * x + 2, 0));
+        }
+    }
+    // Black has five in a row.
+    assert_eq!(record.winning_rows(Stone::Black).count(), 0);
+
+    place(&mut record, (5, 0), (6, 0));
+    // Black has seven in a row.
+    let rows: Vec<_> = record.winning_rows(Stone::Black).collect();
+    assert_eq!(rows, [(Point::new(0, 0), Direction::East)]);
+    assert_eq!(record.winning_rows(Stone::White).count(), 0);
+}
@@ -0,0 +1,162 @@
+//! Load-testing harness for the server.
+//!
+//! Spins up pairs of bot clients that play random legal games against
+//! each other over WebSocket, and reports latency percentiles of moves,
+//! measured from sending a message to receiving the resulting move.
+
+#![allow(missing_docs)]
+
+use anyhow::bail;
+use c6ol_bot::Client;
+use c6ol_core::{
+    game::{Move, Stone},
+    protocol::Passcode,
+    random::{Policy, Uniform},
+};
+use clap::Parser;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::{
+    iter,
+    time::{Duration, Instant},
+};
+use tokio::task::JoinSet;
+
+/// Load-testing harness for the Connect6 Online server
+#[derive(Debug, Parser)]
+struct Args {
+    /// Connect to the WebSocket endpoint at the given URL
+    #[arg(long, default_value = "ws://localhost:8086/ws")]
+    url: String,
+
+    /// Number of games to play concurrently
+    #[arg(long, default_value_t = 16)]
+    games: usize,
+
+    /// Number of moves after which a game is resigned
+    #[arg(long, default_value_t = 100)]
+    max_moves: usize,
+
+    /// Place stones within this distance from the origin
+    #[arg(long, default_value_t = 7)]
+    radius: i16,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+
+    let cells = (2 * args.radius as usize + 1).pow(2);
+    if args.max_moves * 2 > cells {
+        bail!("too many moves for a radius of {}", args.radius);
+    }
+
+    let start = Instant::now();
+    let mut games = JoinSet::new();
+    for _ in 0..args.games {
+        let url = args.url.clone();
+        games.spawn(async move { play_game(&url, args.max_moves, args.radius).await });
+    }
+
+    let mut latencies = vec![];
+    let mut failed = 0;
+    while let Some(res) = games.join_next().await {
+        match res? {
+            Ok(game_latencies) => latencies.extend(game_latencies),
+            Err(err) => {
+                eprintln!("game failed: {err:#}");
+                failed += 1;
+            }
+        }
+    }
+    let elapsed = start.elapsed();
+
+    println!(
+        "{} games finished, {failed} failed in {elapsed:.2?}",
+        args.games - failed
+    );
+    if latencies.is_empty() {
+        return Ok(());
+    }
+
+    latencies.sort_unstable();
+    println!(
+        "{} moves, {:.1} moves/s",
+        latencies.len(),
+        latencies.len() as f64 / elapsed.as_secs_f64()
+    );
+    for p in [50, 90, 99] {
+        println!("p{p}: {:.2?}", percentile(&latencies, p));
+    }
+    println!("max: {:.2?}", latencies.last().unwrap());
+    Ok(())
+}
+
+/// Returns the `p`-th percentile of sorted samples.
+fn percentile(sorted: &[Duration], p: usize) -> Duration {
+    sorted[(sorted.len() - 1) * p / 100]
+}
+
+/// Generates a random alphanumeric passcode.
+fn rand_passcode(rng: &mut impl Rng) -> Passcode {
+    iter::repeat_with(|| rng.sample(rand::distributions::Alphanumeric))
+        .take(16)
+        .collect()
+}
+
+/// Plays a random game between two bots, returning the latencies of moves.
+async fn play_game(url: &str, max_moves: usize, radius: i16) -> anyhow::Result<Vec<Duration>> {
+    let mut rng = StdRng::from_entropy();
+    let mut policy = Uniform { radius };
+
+    let mut black = Client::connect(url).await?;
+    let id = black.start(rand_passcode(&mut rng)).await?;
+
+    let mut white = Client::connect(url).await?;
+    white.join(id).await?;
+    if white.authenticate(rand_passcode(&mut rng)).await? != Stone::White {
+        bail!("failed to join the game");
+    }
+
+    let mut latencies = vec![];
+    let mut win = None;
+
+    while !black.record().is_ended() {
+        let record = black.record();
+        let (mov, stone) = if let Some((p, dir)) = win {
+            (Move::Win(p, dir), record.stone_at(p).unwrap())
+        } else if record.move_index() < max_moves {
+            let mov = policy.choose(record, &mut rng).unwrap();
+            (mov, record.turn().unwrap())
+        } else {
+            let stone = record.turn().unwrap();
+            (Move::Resign(stone), stone)
+        };
+
+        let (mover, opponent) = match stone {
+            Stone::Black => (&mut black, &mut white),
+            Stone::White => (&mut white, &mut black),
+        };
+
+        // The bots only send legal moves, which the server must accept.
+        let start = Instant::now();
+        mover.play(mov).await?;
+        let mov = mover.on_move().await?;
+        latencies.push(start.elapsed());
+
+        if opponent.on_move().await? != mov {
+            bail!("players desynced");
+        }
+
+        if let Move::Place(p1, p2) = mov {
+            let record = black.record();
+            win = iter::once(p1)
+                .chain(p2)
+                .find_map(|p| record.find_winning_row(p));
+        }
+    }
+
+    for client in [black, white] {
+        client.close().await?;
+    }
+    Ok(latencies)
+}
@@ -0,0 +1,96 @@
+//! REST API handling.
+
+use crate::{
+    manager::{JoinCodeError, RecordedMessage, ResolveJoinCodeError, Stats},
+    server::AppState,
+};
+use axum::{
+    extract::{ConnectInfo, Path, State},
+    http::{header, HeaderMap, StatusCode},
+    Json,
+};
+use c6ol_core::protocol::JOIN_CODE_LEN;
+use std::net::SocketAddr;
+use subtle::ConstantTimeEq;
+
+/// Handles a request for game statistics.
+pub async fn handle_stats(State(state): State<AppState>) -> Json<Stats> {
+    Json(state.manager.stats().await)
+}
+
+/// Handles an admin request for the messages recorded in a game.
+///
+/// Responds with 404 if the admin API is disabled, the game is not found,
+/// or messages are not recorded.
+pub async fn handle_recorded_messages(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<RecordedMessage>>, StatusCode> {
+    let token = state.admin_token.as_deref().ok_or(StatusCode::NOT_FOUND)?;
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    let authorized =
+        bearer.is_some_and(|bearer| bool::from(bearer.as_bytes().ct_eq(token.as_bytes())));
+    if !authorized {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    let id = id
+        .as_bytes()
+        .try_into()
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    state
+        .manager
+        .recorded_messages(id)
+        .await
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+/// Handles a request to issue a join code for a game,
+/// responding with the code.
+///
+/// Responds with 404 if the game is not found,
+/// or 503 if too many join codes are live.
+pub async fn handle_new_join_code(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<String, StatusCode> {
+    let id = id
+        .as_bytes()
+        .try_into()
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    match state.manager.new_join_code(id).await {
+        Ok(code) => Ok(format!("{code:0JOIN_CODE_LEN$}")),
+        Err(JoinCodeError::GameNotFound) => Err(StatusCode::NOT_FOUND),
+        Err(JoinCodeError::TooManyCodes) => Err(StatusCode::SERVICE_UNAVAILABLE),
+    }
+}
+
+/// Handles a request to resolve a join code,
+/// responding with the ID of the game and consuming the code.
+///
+/// Responds with 404 if the code is malformed, unknown, expired, or used,
+/// or 429 if too many attempts from the address have failed recently.
+pub async fn handle_resolve_join_code(
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    State(state): State<AppState>,
+    Path(code): Path<String>,
+    headers: HeaderMap,
+) -> Result<String, StatusCode> {
+    let code = Some(code)
+        .filter(|code| code.len() == JOIN_CODE_LEN && code.bytes().all(|b| b.is_ascii_digit()))
+        .and_then(|code| code.parse().ok());
+    match state
+        .manager
+        .resolve_join_code(code, state.client_ip(addr, &headers))
+        .await
+    {
+        Ok(id) => Ok(String::from_utf8_lossy(&id).into_owned()),
+        Err(ResolveJoinCodeError::CodeNotFound) => Err(StatusCode::NOT_FOUND),
+        Err(ResolveJoinCodeError::TooManyFailures) => Err(StatusCode::TOO_MANY_REQUESTS),
+    }
+}
@@ -1,8 +1,10 @@
 //! The server library for [Connect6 Online](https://github.com/yescallop/c6ol).
 
+mod api;
 mod manager;
 mod server;
 mod shutdown;
 mod ws;
 
-pub use server::run;
+pub use manager::SeatPolicy;
+pub use server::{run, Options};
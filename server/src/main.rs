@@ -1,12 +1,17 @@
 #![allow(missing_docs)]
 
+mod systemd;
+
 use anyhow::Context;
+use c6ol_core::protocol::DEFAULT_RETRACT_COOLDOWN;
+use c6ol_server::{Options, SeatPolicy};
 use clap::Parser;
 use std::{
     future::Future,
     io,
     net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
     path::PathBuf,
+    time::Duration,
 };
 use tokio::{
     net::{TcpListener, TcpSocket},
@@ -25,13 +30,54 @@ const DEFAULT_LISTEN: [SocketAddr; 2] = [
 #[derive(Debug, Parser)]
 #[command(version, about, long_about = None)]
 struct Args {
-    /// Listen on the given socket addresses
+    /// Listen on the given socket addresses, unless sockets are passed by systemd
     #[arg(long, name = "ADDR", num_args = 1.., default_values_t = DEFAULT_LISTEN)]
     listen: Vec<SocketAddr>,
 
     /// Serve files from the given directory
     #[arg(long, name = "PATH")]
     serve_dir: Option<PathBuf>,
+
+    /// Log the given fraction of completed HTTP requests
+    #[arg(long, name = "RATE", default_value_t = 1.0, value_parser = parse_rate)]
+    access_log_sample_rate: f64,
+
+    /// Always log HTTP requests taking at least the given milliseconds
+    #[arg(long, name = "MS", default_value_t = 1000)]
+    slow_request_ms: u64,
+
+    /// Allow at most the given number of open games created from an IP address
+    #[arg(long, name = "N")]
+    max_open_games_per_ip: Option<usize>,
+
+    /// Reject retract requests made within the given seconds after one is declined
+    #[arg(long, name = "SECS", default_value_t = DEFAULT_RETRACT_COOLDOWN.as_secs())]
+    retract_cooldown_secs: u64,
+
+    /// Record the raw protocol messages of each game for debugging
+    #[arg(long)]
+    record_messages: bool,
+
+    /// Enable the admin API, authorized by the given bearer token
+    #[arg(long, name = "TOKEN")]
+    admin_token: Option<String>,
+
+    /// Choose what to do when a seat is authenticated from another connection
+    #[arg(long, name = "POLICY", value_enum, default_value_t)]
+    seat_policy: SeatPolicy,
+
+    /// Take client addresses from the X-Forwarded-For header set by a trusted reverse proxy
+    #[arg(long)]
+    trust_forwarded_for: bool,
+}
+
+fn parse_rate(s: &str) -> Result<f64, String> {
+    let rate: f64 = s.parse().map_err(|err| format!("{err}"))?;
+    if (0.0..=1.0).contains(&rate) {
+        Ok(rate)
+    } else {
+        Err("not between 0 and 1".into())
+    }
 }
 
 #[tokio::main(flavor = "current_thread")]
@@ -46,14 +92,28 @@ async fn main() -> anyhow::Result<()> {
 
     let args = Args::parse();
 
-    let mut listeners = vec![];
+    let mut listeners = systemd::take_listeners().context("failed to take passed sockets")?;
 
-    for addr in args.listen {
-        listeners.push(listen(addr).with_context(|| format!("failed to listen on {addr}"))?);
-        tracing::info!("listening on {addr}");
+    if listeners.is_empty() {
+        for addr in args.listen {
+            listeners.push(listen(addr).with_context(|| format!("failed to listen on {addr}"))?);
+            tracing::info!("listening on {addr}");
+        }
+    } else {
+        for listener in &listeners {
+            tracing::info!(
+                "listening on {} (passed by systemd)",
+                listener.local_addr()?
+            );
+        }
     }
 
     let shutdown_signal = shutdown_signal().context("failed to listen for shutdown signals")?;
+    #[cfg(unix)]
+    let shutdown_signal = async {
+        shutdown_signal.await;
+        systemd::notify_stopping();
+    };
 
     let serve_dir = if let Some(path) = &args.serve_dir {
         let path = path
@@ -67,7 +127,22 @@ async fn main() -> anyhow::Result<()> {
         None
     };
 
-    c6ol_server::run(listeners, serve_dir.as_deref(), shutdown_signal).await;
+    let options = Options {
+        serve_dir,
+        access_log_sample_rate: args.access_log_sample_rate,
+        slow_request_threshold: Duration::from_millis(args.slow_request_ms),
+        max_open_games_per_ip: args.max_open_games_per_ip,
+        retract_cooldown: Duration::from_secs(args.retract_cooldown_secs),
+        record_messages: args.record_messages,
+        admin_token: args.admin_token,
+        seat_policy: args.seat_policy,
+        trust_forwarded_for: args.trust_forwarded_for,
+    };
+
+    #[cfg(unix)]
+    systemd::notify_ready();
+
+    c6ol_server::run(listeners, options, shutdown_signal).await;
     Ok(())
 }
 
@@ -1,20 +1,53 @@
 //! Game manager.
 
 use c6ol_core::{
-    game::{Move, Record, Stone},
-    protocol::{ClientMessage, GameId, Passcode, Request, ServerMessage},
+    game::{Move, Point, Record, Stone},
+    protocol::{
+        ClientMessage, GameId, Passcode, Request, ServerMessage, JOIN_CODE_LEN, JOIN_CODE_TTL,
+        TRANSFER_CODE_TTL,
+    },
 };
 use rand::{distributions::Alphanumeric, Rng};
-use std::{array, collections::HashMap, future::Future, iter};
+use serde::Serialize;
+use std::{
+    array,
+    collections::{HashMap, VecDeque},
+    future::{self, Future},
+    iter,
+    net::{IpAddr, Ipv6Addr},
+    time::{Duration, Instant},
+};
 use tokio::{
-    sync::{broadcast, mpsc, oneshot},
+    sync::{broadcast, mpsc, oneshot, watch},
     task::JoinSet,
+    time,
 };
 
 const CHANNEL_CAPACITY_MANAGE_CMD: usize = 64;
 const CHANNEL_CAPACITY_GAME_CMD: usize = 8;
 const CHANNEL_CAPACITY_GAME_MSG: usize = 8;
 
+const STATS_CACHE_TTL: Duration = Duration::from_secs(10);
+/// Cursor positions shared by a player more often than this are delayed,
+/// with only the latest one sent when the interval ends.
+const CURSOR_MIN_INTERVAL: Duration = Duration::from_millis(200);
+const STATS_ACTIVE_PERIOD: Duration = Duration::from_secs(24 * 60 * 60);
+const TRANSFER_CODE_LEN: usize = 8;
+/// Messages of a game beyond this number are not recorded.
+const MAX_RECORDED_MESSAGES: usize = 10_000;
+/// The number of recordings of finished games kept for download.
+const FINISHED_RECORDINGS_CAPACITY: usize = 64;
+/// The number of possible join codes.
+const JOIN_CODE_SPACE: u32 = 10u32.pow(JOIN_CODE_LEN as u32);
+/// The maximum number of join codes live at a time, kept well below
+/// the space so that a random code is likely to be free, and that
+/// a guess within the failure limit is unlikely to hit a live code.
+const MAX_JOIN_CODES: usize = 1_000;
+/// The maximum number of failed attempts to resolve join codes
+/// from an address within `JOIN_CODE_FAILURE_WINDOW`.
+const MAX_JOIN_CODE_FAILURES: u32 = 10;
+const JOIN_CODE_FAILURE_WINDOW: Duration = Duration::from_secs(10 * 60);
+
 /// Convenience macro for command execution.
 macro_rules! execute {
     ($cmd_tx:expr, $variant:path, $($args:expr),*) => {{
@@ -35,10 +68,63 @@ pub struct GameSubscription {
     pub msg_rx: broadcast::Receiver<ServerMessage>,
 }
 
+/// A raw protocol message recorded for debugging.
+#[derive(Clone, Debug, Serialize)]
+pub struct RecordedMessage {
+    /// The connection on which the message was sent or received,
+    /// numbered in the order of connecting to games on the server.
+    pub conn: u32,
+    /// Whether the message was sent by the client.
+    pub from_client: bool,
+    /// The encoded message.
+    pub data: Box<[u8]>,
+}
+
 enum GameCommand {
     Subscribe(oneshot::Sender<GameSubscription>),
-    Authenticate(oneshot::Sender<Option<Stone>>, Passcode),
+    Authenticate(oneshot::Sender<Result<Seat, AuthError>>, Passcode),
+    Reserve(Passcode),
+    Transfer(oneshot::Sender<Passcode>, Stone),
     Play(Stone, ClientMessage),
+    RecordMessage(RecordedMessage),
+    RecordedMessages(oneshot::Sender<Option<Vec<RecordedMessage>>>),
+}
+
+/// An error that occurs when authenticating.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AuthError {
+    /// The passcode is wrong.
+    WrongPasscode,
+    /// The seat is held by another connection.
+    SeatTaken,
+}
+
+/// An error that occurs when issuing a join code.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum JoinCodeError {
+    /// The game is not open.
+    GameNotFound,
+    /// Too many join codes are live.
+    TooManyCodes,
+}
+
+/// An error that occurs when resolving a join code.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ResolveJoinCodeError {
+    /// The code is malformed, unknown, expired, or used.
+    CodeNotFound,
+    /// Too many attempts from the address have failed recently.
+    TooManyFailures,
+}
+
+/// A seat held by a connection.
+struct Seat {
+    stone: Stone,
+    /// The receiver notified when the seat is taken over,
+    /// or `None` if seats are shared.
+    taken_over_rx: Option<oneshot::Receiver<()>>,
+    /// The passcode of the seat if authenticated by a transfer code.
+    passcode: Option<Passcode>,
 }
 
 /// A command handle to a game.
@@ -46,14 +132,19 @@ pub struct Game {
     id: GameId,
     cmd_tx: mpsc::Sender<GameCommand>,
     stone: Option<Stone>,
+    taken_over_rx: Option<oneshot::Receiver<()>>,
+    /// The connection number, or `None` if messages are not recorded.
+    conn: Option<u32>,
 }
 
 impl Game {
-    fn new(id: GameId, cmd_tx: mpsc::Sender<GameCommand>) -> Self {
+    fn new(id: GameId, cmd_tx: mpsc::Sender<GameCommand>, conn: Option<u32>) -> Self {
         Self {
             id,
             cmd_tx,
             stone: None,
+            taken_over_rx: None,
+            conn,
         }
     }
 
@@ -69,15 +160,54 @@ impl Game {
 
     /// Attempts to authenticate with the given passcode.
     ///
-    /// Returns the assigned stone, or `None` if authentication failed.
+    /// Returns the assigned stone on success, with the passcode of the seat
+    /// if authenticated by a transfer code.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the passcode is wrong, or if the seat is held
+    /// by another handle and the seat policy rejects new connections.
     ///
     /// # Panics
     ///
     /// Panics if the handle is already authenticated.
-    pub async fn authenticate(&mut self, passcode: Passcode) -> Option<Stone> {
+    pub async fn authenticate(
+        &mut self,
+        passcode: Passcode,
+    ) -> Result<(Stone, Option<Passcode>), AuthError> {
         assert!(self.stone.is_none(), "already authenticated");
-        self.stone = execute!(self.cmd_tx, GameCommand::Authenticate, passcode);
-        self.stone
+        let seat = execute!(self.cmd_tx, GameCommand::Authenticate, passcode)?;
+        self.stone = Some(seat.stone);
+        self.taken_over_rx = seat.taken_over_rx;
+        Ok((seat.stone, seat.passcode))
+    }
+
+    /// Waits until the seat is taken over by another handle,
+    /// which never happens if seats are shared.
+    pub async fn taken_over(&mut self) {
+        if let Some(rx) = &mut self.taken_over_rx {
+            if rx.await.is_ok() {
+                return;
+            }
+            // The receiver must not be polled again after completion.
+            self.taken_over_rx = None;
+        }
+        future::pending::<()>().await;
+    }
+
+    /// Reserves White's seat with the given passcode, if it is not taken yet.
+    pub async fn reserve(&self, passcode: Passcode) {
+        execute!(self.cmd_tx, GameCommand::Reserve(passcode));
+    }
+
+    /// Generates a one-time code for transferring the seat to another handle.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the handle is unauthenticated.
+    pub async fn transfer_code(&self) -> Passcode {
+        let stone = self.stone.expect("unauthenticated");
+        execute!(self.cmd_tx, GameCommand::Transfer, stone)
     }
 
     /// Returns the assigned stone, or `None` if the handle is unauthenticated.
@@ -94,11 +224,57 @@ impl Game {
         let stone = self.stone.expect("unauthenticated");
         execute!(self.cmd_tx, GameCommand::Play(stone, msg));
     }
+
+    /// Records a raw message sent or received by the handle's user,
+    /// if messages are recorded.
+    pub async fn record_message(&self, from_client: bool, data: &[u8]) {
+        if let Some(conn) = self.conn {
+            let msg = RecordedMessage {
+                conn,
+                from_client,
+                data: data.into(),
+            };
+            execute!(self.cmd_tx, GameCommand::RecordMessage(msg));
+        }
+    }
+
+    /// Returns the messages recorded so far, or `None` if not recorded.
+    pub async fn recorded_messages(&self) -> Option<Vec<RecordedMessage>> {
+        execute!(self.cmd_tx, GameCommand::RecordedMessages,)
+    }
 }
 
 enum ManageCommand {
-    New(oneshot::Sender<Game>),
+    New(oneshot::Sender<Option<Game>>, IpAddr),
     Find(oneshot::Sender<Option<Game>>, GameId),
+    Stats(oneshot::Sender<Stats>),
+    FinishedRecording(oneshot::Sender<Option<Vec<RecordedMessage>>>, GameId),
+    NewJoinCode(oneshot::Sender<Result<u32, JoinCodeError>>, GameId),
+    ResolveJoinCode(
+        oneshot::Sender<Result<GameId, ResolveJoinCodeError>>,
+        Option<u32>,
+        IpAddr,
+    ),
+}
+
+/// Statistics about the games hosted.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Stats {
+    /// Number of games in progress.
+    pub ongoing_games: usize,
+    /// Number of games ended but still hosted.
+    pub ended_games: usize,
+    /// Average number of moves in the games hosted.
+    pub average_moves: f64,
+    /// Number of games started in the past 24 hours.
+    pub daily_started_games: usize,
+}
+
+/// The status of a game, as observed by the game manager.
+#[derive(Clone, Copy, Default)]
+struct GameStatus {
+    ended: bool,
+    moves: usize,
 }
 
 /// Generates a random alphanumeric game ID.
@@ -107,12 +283,59 @@ fn rand_game_id() -> GameId {
     array::from_fn(|_| rng.sample(Alphanumeric))
 }
 
-/// Creates a game manager.
+/// Returns the key under which games created from an address are counted.
+///
+/// IPv6 addresses are grouped by their /64 prefix, which is commonly
+/// assigned to a single host.
+fn quota_key(addr: IpAddr) -> IpAddr {
+    match addr.to_canonical() {
+        IpAddr::V6(addr) => {
+            let prefix = addr.to_bits() & (u128::MAX << 64);
+            IpAddr::V6(Ipv6Addr::from_bits(prefix))
+        }
+        addr => addr,
+    }
+}
+
+/// What to do when a seat is authenticated from another connection
+/// while held by one.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, clap::ValueEnum)]
+pub enum SeatPolicy {
+    /// Let both connections control the player.
+    #[default]
+    Share,
+    /// Reject the new connection.
+    RejectNew,
+    /// Let the new connection take over the seat, closing the old one.
+    TakeOver,
+}
+
+/// Configuration applied to every game.
+#[derive(Clone, Copy, Debug)]
+pub struct GameConfig {
+    /// The time for which a player cannot request to retract again
+    /// after their request is declined.
+    pub retract_cooldown: Duration,
+    /// Whether to record the raw messages of the game.
+    pub record_messages: bool,
+    /// What to do when a held seat is authenticated from another connection.
+    pub seat_policy: SeatPolicy,
+}
+
+/// Creates a game manager, which allows at most `max_open_games_per_ip`
+/// games created from an IP address to be open at a time, if set,
+/// and hosts each game with `config`.
 ///
 /// Returns a command handle to it and a future to run it.
-pub fn create() -> (GameManager, impl Future<Output = ()>) {
+pub fn create(
+    max_open_games_per_ip: Option<usize>,
+    config: GameConfig,
+) -> (GameManager, impl Future<Output = ()>) {
     let (cmd_tx, cmd_rx) = mpsc::channel(CHANNEL_CAPACITY_MANAGE_CMD);
-    (GameManager { cmd_tx }, manage_games(cmd_rx))
+    (
+        GameManager { cmd_tx },
+        manage_games(cmd_rx, max_open_games_per_ip, config),
+    )
 }
 
 /// A command handle to a game manager.
@@ -122,23 +345,127 @@ pub struct GameManager {
 }
 
 impl GameManager {
-    /// Creates a new game.
-    pub async fn new_game(&self) -> Game {
-        execute!(self.cmd_tx, ManageCommand::New,)
+    /// Creates a new game from the given address.
+    ///
+    /// Returns `None` if too many games created from the address are open.
+    pub async fn new_game(&self, addr: IpAddr) -> Option<Game> {
+        execute!(self.cmd_tx, ManageCommand::New, addr)
     }
 
     /// Searches for a game with the given ID.
     pub async fn find_game(&self, id: GameId) -> Option<Game> {
         execute!(self.cmd_tx, ManageCommand::Find, id)
     }
+
+    /// Returns statistics about the games hosted.
+    ///
+    /// The statistics are cached for a short period of time.
+    pub async fn stats(&self) -> Stats {
+        execute!(self.cmd_tx, ManageCommand::Stats,)
+    }
+
+    /// Returns the messages recorded in the game with the given ID, or `None`
+    /// if not recorded or the game is neither open nor recently finished.
+    pub async fn recorded_messages(&self, id: GameId) -> Option<Vec<RecordedMessage>> {
+        if let Some(game) = self.find_game(id).await {
+            return game.recorded_messages().await;
+        }
+        execute!(self.cmd_tx, ManageCommand::FinishedRecording, id)
+    }
+
+    /// Issues a join code for the game with the given ID,
+    /// replacing the one previously issued for it (if any).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the game is not open or too many codes are live.
+    pub async fn new_join_code(&self, id: GameId) -> Result<u32, JoinCodeError> {
+        execute!(self.cmd_tx, ManageCommand::NewJoinCode, id)
+    }
+
+    /// Returns the ID of the game that a join code is issued for,
+    /// consuming the code.
+    ///
+    /// A malformed code is passed as `None`, and fails like an unknown one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the code is not found, or too many attempts
+    /// from the address have failed recently.
+    pub async fn resolve_join_code(
+        &self,
+        code: Option<u32>,
+        addr: IpAddr,
+    ) -> Result<GameId, ResolveJoinCodeError> {
+        execute!(self.cmd_tx, ManageCommand::ResolveJoinCode, code, addr)
+    }
+}
+
+/// Removes the start times older than `STATS_ACTIVE_PERIOD`.
+fn prune_start_times(start_times: &mut VecDeque<Instant>) {
+    while start_times
+        .front()
+        .is_some_and(|t| t.elapsed() > STATS_ACTIVE_PERIOD)
+    {
+        start_times.pop_front();
+    }
 }
 
-async fn manage_games(mut cmd_rx: mpsc::Receiver<ManageCommand>) {
+/// Computes statistics from the statuses of games hosted
+/// and the start times of recent games.
+fn compute_stats<'a>(
+    statuses: impl Iterator<Item = &'a watch::Receiver<GameStatus>>,
+    start_times: &mut VecDeque<Instant>,
+) -> Stats {
+    let mut stats = Stats::default();
+    let mut total_moves = 0;
+
+    for status in statuses {
+        let status = *status.borrow();
+        if status.ended {
+            stats.ended_games += 1;
+        } else {
+            stats.ongoing_games += 1;
+        }
+        total_moves += status.moves;
+    }
+
+    let games = stats.ongoing_games + stats.ended_games;
+    if games != 0 {
+        stats.average_moves = total_moves as f64 / games as f64;
+    }
+
+    prune_start_times(start_times);
+    stats.daily_started_games = start_times.len();
+    stats
+}
+
+async fn manage_games(
+    mut cmd_rx: mpsc::Receiver<ManageCommand>,
+    max_open_games_per_ip: Option<usize>,
+    config: GameConfig,
+) {
     tracing::info!("game manager started");
 
     let mut game_cmd_txs = HashMap::new();
+    let mut game_status_rxs = HashMap::new();
     let mut game_tasks = JoinSet::new();
     let mut game_ids_by_task_id = HashMap::new();
+    let mut open_games_by_ip = HashMap::<IpAddr, usize>::new();
+
+    let mut start_times = VecDeque::new();
+    let mut stats_cache = None::<(Instant, Stats)>;
+
+    let mut next_conn = 0u32;
+    let mut new_conn = move || {
+        config.record_messages.then(|| {
+            next_conn = next_conn.wrapping_add(1);
+            next_conn
+        })
+    };
+    let mut finished_recordings = VecDeque::<(GameId, Vec<RecordedMessage>)>::new();
+    let mut join_codes = HashMap::<u32, (GameId, Instant)>::new();
+    let mut join_code_failures_by_ip = HashMap::<IpAddr, (u32, Instant)>::new();
 
     loop {
         tokio::select! {
@@ -148,7 +475,15 @@ async fn manage_games(mut cmd_rx: mpsc::Receiver<ManageCommand>) {
                     break;
                 };
                 match cmd {
-                    ManageCommand::New(resp_tx) => loop {
+                    ManageCommand::New(resp_tx, addr) => loop {
+                        let key = quota_key(addr);
+                        let open_games = open_games_by_ip.get(&key).copied().unwrap_or(0);
+                        if max_open_games_per_ip.is_some_and(|max| open_games >= max) {
+                            tracing::debug!("too many open games from {key}");
+                            _ = resp_tx.send(None);
+                            break;
+                        }
+
                         let id = rand_game_id();
                         if game_cmd_txs.contains_key(&id) {
                             continue;
@@ -157,10 +492,19 @@ async fn manage_games(mut cmd_rx: mpsc::Receiver<ManageCommand>) {
                         let (game_cmd_tx, game_cmd_rx) = mpsc::channel(CHANNEL_CAPACITY_GAME_CMD);
                         game_cmd_txs.insert(id, game_cmd_tx.downgrade());
 
-                        let task_id = game_tasks.spawn(host_game(id, game_cmd_rx)).id();
-                        game_ids_by_task_id.insert(task_id, id);
+                        let (status_tx, status_rx) = watch::channel(GameStatus::default());
+                        game_status_rxs.insert(id, status_rx);
+                        // Prune here too, in case stats are never requested.
+                        prune_start_times(&mut start_times);
+                        start_times.push_back(Instant::now());
+
+                        let task_id = game_tasks
+                            .spawn(host_game(id, game_cmd_rx, status_tx, config))
+                            .id();
+                        game_ids_by_task_id.insert(task_id, (id, key));
+                        *open_games_by_ip.entry(key).or_default() += 1;
 
-                        _ = resp_tx.send(Game::new(id, game_cmd_tx));
+                        _ = resp_tx.send(Some(Game::new(id, game_cmd_tx, new_conn())));
                         break;
                     },
                     ManageCommand::Find(resp_tx, id) => {
@@ -168,7 +512,69 @@ async fn manage_games(mut cmd_rx: mpsc::Receiver<ManageCommand>) {
                         // but the game task has not finished yet.
                         let resp = game_cmd_txs
                             .get(&id)
-                            .and_then(|tx| tx.upgrade().map(|tx| Game::new(id, tx)));
+                            .and_then(|tx| tx.upgrade())
+                            // Number a connection only if it is still waiting for the game.
+                            .filter(|_| !resp_tx.is_closed())
+                            .map(|tx| Game::new(id, tx, new_conn()));
+                        _ = resp_tx.send(resp);
+                    }
+                    ManageCommand::Stats(resp_tx) => {
+                        if stats_cache
+                            .as_ref()
+                            .is_none_or(|(time, _)| time.elapsed() >= STATS_CACHE_TTL)
+                        {
+                            let stats = compute_stats(game_status_rxs.values(), &mut start_times);
+                            stats_cache = Some((Instant::now(), stats));
+                        }
+                        _ = resp_tx.send(stats_cache.as_ref().unwrap().1.clone());
+                    }
+                    ManageCommand::FinishedRecording(resp_tx, id) => {
+                        let resp = finished_recordings
+                            .iter()
+                            .find(|(game_id, _)| *game_id == id)
+                            .map(|(_, msgs)| msgs.clone());
+                        _ = resp_tx.send(resp);
+                    }
+                    ManageCommand::NewJoinCode(resp_tx, id) => {
+                        join_codes.retain(|_, (game_id, time)| {
+                            *game_id != id && time.elapsed() < JOIN_CODE_TTL
+                        });
+                        let resp = if !game_cmd_txs.contains_key(&id) {
+                            Err(JoinCodeError::GameNotFound)
+                        } else if join_codes.len() >= MAX_JOIN_CODES {
+                            Err(JoinCodeError::TooManyCodes)
+                        } else {
+                            let mut rng = rand::thread_rng();
+                            let code = iter::repeat_with(|| rng.gen_range(0..JOIN_CODE_SPACE))
+                                .find(|code| !join_codes.contains_key(code))
+                                .unwrap();
+                            join_codes.insert(code, (id, Instant::now()));
+                            Ok(code)
+                        };
+                        _ = resp_tx.send(resp);
+                    }
+                    ManageCommand::ResolveJoinCode(resp_tx, code, addr) => {
+                        let key = quota_key(addr);
+                        join_code_failures_by_ip
+                            .retain(|_, (_, time)| time.elapsed() < JOIN_CODE_FAILURE_WINDOW);
+                        let failures = join_code_failures_by_ip.get(&key).map_or(0, |&(n, _)| n);
+
+                        let resp = if failures >= MAX_JOIN_CODE_FAILURES {
+                            tracing::debug!("too many failed join codes from {key}");
+                            Err(ResolveJoinCodeError::TooManyFailures)
+                        } else if let Some((id, _)) = code
+                            .and_then(|code| join_codes.remove(&code))
+                            .filter(|(_, time)| time.elapsed() < JOIN_CODE_TTL)
+                        {
+                            Ok(id)
+                        } else {
+                            // The window starts at the first failure.
+                            join_code_failures_by_ip
+                                .entry(key)
+                                .or_insert((0, Instant::now()))
+                                .0 += 1;
+                            Err(ResolveJoinCodeError::CodeNotFound)
+                        };
                         _ = resp_tx.send(resp);
                     }
                 }
@@ -176,15 +582,29 @@ async fn manage_games(mut cmd_rx: mpsc::Receiver<ManageCommand>) {
             // When `join_next` returns `None`, `select!` will disable
             // this branch and still wait on the other branch.
             Some(res) = game_tasks.join_next_with_id() => {
-                let task_id = match res {
-                    Ok((id, ())) => id,
+                let (task_id, recording) = match res {
+                    Ok((id, recording)) => (id, recording),
                     Err(err) => {
                         tracing::error!("game task panicked: {err}");
-                        err.id()
+                        (err.id(), None)
                     },
                 };
-                let game_id = game_ids_by_task_id.remove(&task_id).unwrap();
+                let (game_id, key) = game_ids_by_task_id.remove(&task_id).unwrap();
+                if let Some(recording) = recording {
+                    if finished_recordings.len() == FINISHED_RECORDINGS_CAPACITY {
+                        finished_recordings.pop_front();
+                    }
+                    finished_recordings.push_back((game_id, recording));
+                }
                 game_cmd_txs.remove(&game_id);
+                game_status_rxs.remove(&game_id);
+                join_codes.retain(|_, (id, _)| *id != game_id);
+
+                let open_games = open_games_by_ip.get_mut(&key).unwrap();
+                *open_games -= 1;
+                if *open_games == 0 {
+                    open_games_by_ip.remove(&key);
+                }
             }
         }
     }
@@ -201,31 +621,117 @@ struct GameState {
     passcode_black: Option<Passcode>,
     passcode_white: Option<Passcode>,
     requests: [Option<Stone>; Request::VALUES.len()],
+    last_cursor_times: [Option<Instant>; 2],
+    /// The latest cursor positions of the black and white players
+    /// delayed by `CURSOR_MIN_INTERVAL`, if any.
+    pending_cursors: [Option<Option<Point>>; 2],
+    retract_cooldown: Duration,
+    seat_policy: SeatPolicy,
+    /// The senders for closing the connections holding the seats of
+    /// the black and white players, unless seats are shared.
+    seat_holders: [Option<oneshot::Sender<()>>; 2],
+    /// When the retract requests by the black and white players were last declined.
+    retract_declined_times: [Option<Instant>; 2],
+    /// The seat transfer codes of the black and white players,
+    /// with when they were generated.
+    transfer_codes: [Option<(Passcode, Instant)>; 2],
 }
 
 impl GameState {
-    fn new() -> Self {
+    fn new(config: GameConfig) -> Self {
         Self {
             msg_tx: broadcast::channel(CHANNEL_CAPACITY_GAME_MSG).0,
             record: Record::new(),
             passcode_black: None,
             passcode_white: None,
             requests: [None; Request::VALUES.len()],
+            last_cursor_times: [None; 2],
+            pending_cursors: [None; 2],
+            retract_cooldown: config.retract_cooldown,
+            seat_policy: config.seat_policy,
+            seat_holders: [None, None],
+            retract_declined_times: [None; 2],
+            transfer_codes: [None, None],
         }
     }
 
     fn subscribe(&self) -> GameSubscription {
         GameSubscription {
-            init_msgs: iter::once(ServerMessage::Record(Box::new(self.record.clone())))
-                .chain(Request::VALUES.into_iter().filter_map(|req| {
-                    self.requests[req as usize].map(|stone| ServerMessage::Request(stone, req))
-                }))
-                .collect(),
+            init_msgs: [
+                ServerMessage::RetractCooldown(self.retract_cooldown),
+                ServerMessage::Record(Box::new(self.record.clone())),
+            ]
+            .into_iter()
+            .chain(Request::VALUES.into_iter().filter_map(|req| {
+                self.requests[req as usize].map(|stone| ServerMessage::Request(stone, req))
+            }))
+            .collect(),
             msg_rx: self.msg_tx.subscribe(),
         }
     }
 
-    fn authenticate(&mut self, passcode: Passcode) -> Option<Stone> {
+    fn authenticate(&mut self, passcode: Passcode) -> Result<Seat, AuthError> {
+        // A transfer code is checked first and works only once.
+        // Under `SeatPolicy::Share` the old connection keeps the seat too,
+        // while under the other policies it is taken over.
+        for stone in Stone::VALUES {
+            let slot = &mut self.transfer_codes[stone.index()];
+            if slot
+                .as_ref()
+                .is_some_and(|(code, time)| *code == passcode && time.elapsed() < TRANSFER_CODE_TTL)
+            {
+                *slot = None;
+                let passcode = match stone {
+                    Stone::Black => self.passcode_black.clone(),
+                    Stone::White => self.passcode_white.clone(),
+                };
+                return Ok(Seat {
+                    passcode,
+                    ..self.hold_seat(stone, true)
+                });
+            }
+        }
+
+        let stone = self
+            .check_passcode(passcode)
+            .ok_or(AuthError::WrongPasscode)?;
+        let holder = &self.seat_holders[stone.index()];
+        if self.seat_policy == SeatPolicy::RejectNew
+            && holder.as_ref().is_some_and(|tx| !tx.is_closed())
+        {
+            return Err(AuthError::SeatTaken);
+        }
+        Ok(self.hold_seat(stone, false))
+    }
+
+    /// Makes a new connection hold the seat of `stone`, closing the old one
+    /// if `take_over` is set or required by the seat policy.
+    fn hold_seat(&mut self, stone: Stone, take_over: bool) -> Seat {
+        if self.seat_policy == SeatPolicy::Share {
+            return Seat {
+                stone,
+                taken_over_rx: None,
+                passcode: None,
+            };
+        }
+
+        let (tx, rx) = oneshot::channel();
+        let old_tx = self.seat_holders[stone.index()].replace(tx);
+        if take_over || self.seat_policy == SeatPolicy::TakeOver {
+            if let Some(old_tx) = old_tx {
+                _ = old_tx.send(());
+            }
+        }
+        Seat {
+            stone,
+            taken_over_rx: Some(rx),
+            passcode: None,
+        }
+    }
+
+    /// Returns the stone the passcode authenticates as,
+    /// taking a vacant seat if the passcode is new.
+    fn check_passcode(&mut self, passcode: Passcode) -> Option<Stone> {
         if let Some(passcode_black) = &self.passcode_black {
             if passcode == *passcode_black {
                 Some(Stone::Black)
@@ -245,6 +751,48 @@ impl GameState {
         }
     }
 
+    fn transfer_code(&mut self, stone: Stone) -> Passcode {
+        let code: Passcode = rand::thread_rng()
+            .sample_iter(Alphanumeric)
+            .take(TRANSFER_CODE_LEN)
+            .collect();
+        self.transfer_codes[stone.index()] = Some((code.clone(), Instant::now()));
+        code
+    }
+
+    fn reserve(&mut self, passcode: Passcode) {
+        self.passcode_white.get_or_insert(passcode);
+    }
+
+    fn send_cursor(&mut self, stone: Stone, p: Option<Point>) {
+        self.last_cursor_times[stone.index()] = Some(Instant::now());
+        self.pending_cursors[stone.index()] = None;
+        _ = self.msg_tx.send(ServerMessage::Cursor(stone, p));
+    }
+
+    /// Returns when the next delayed cursor position is due, if any.
+    fn cursor_due_time(&self) -> Option<Instant> {
+        Stone::VALUES
+            .into_iter()
+            .filter(|stone| self.pending_cursors[stone.index()].is_some())
+            .filter_map(|stone| self.last_cursor_times[stone.index()])
+            .map(|t| t + CURSOR_MIN_INTERVAL)
+            .min()
+    }
+
+    /// Sends the delayed cursor positions that are due.
+    fn flush_cursors(&mut self) {
+        for stone in Stone::VALUES {
+            let last_time = self.last_cursor_times[stone.index()];
+            if last_time.is_some_and(|t| t.elapsed() < CURSOR_MIN_INTERVAL) {
+                continue;
+            }
+            if let Some(p) = self.pending_cursors[stone.index()] {
+                self.send_cursor(stone, p);
+            }
+        }
+    }
+
     fn play(&mut self, stone: Stone, msg: ClientMessage) {
         use ClientMessage as Msg;
 
@@ -255,7 +803,17 @@ impl GameState {
         }
 
         let action = match msg {
-            Msg::Start(_) | Msg::Join(_) => return,
+            Msg::Start(_) | Msg::Join(_) | Msg::StartReserved(..) | Msg::Transfer => return,
+            Msg::Cursor(p) => {
+                // Keep the rate low so that the message channel doesn't lag.
+                let last_time = self.last_cursor_times[stone.index()];
+                if last_time.is_some_and(|t| t.elapsed() < CURSOR_MIN_INTERVAL) {
+                    self.pending_cursors[stone.index()] = Some(p);
+                    return;
+                }
+                self.send_cursor(stone, p);
+                return;
+            }
             Msg::Place(p1, p2) => {
                 if self.record.turn() != Some(stone) {
                     // Not their turn.
@@ -270,8 +828,17 @@ impl GameState {
                 }
                 Action::Move(Move::Pass)
             }
-            Msg::ClaimWin(p, dir) => Action::Move(Move::Win(p, dir)),
-            Msg::Resign => Action::Move(Move::Resign(stone)),
+            Msg::ClaimWin(p, dir) => Action::Move(Move::Win(p, dir).normalize_win()),
+            Msg::Resign(farewell) => {
+                // Relay the farewell before the resignation,
+                // which is legal unless the game has ended.
+                if let Some(farewell) = farewell {
+                    if !self.record.is_ended() {
+                        _ = self.msg_tx.send(ServerMessage::Farewell(stone, farewell));
+                    }
+                }
+                Action::Move(Move::Resign(stone))
+            }
             Msg::Request(req) => {
                 let req_stone = &mut self.requests[req as usize];
                 if *req_stone == Some(stone) {
@@ -284,6 +851,15 @@ impl GameState {
                     return;
                 }
 
+                if req == Request::Retract
+                    && req_stone.is_none()
+                    && self.retract_declined_times[stone.index()]
+                        .is_some_and(|t| t.elapsed() < self.retract_cooldown)
+                {
+                    // Requested again too soon after being declined.
+                    return;
+                }
+
                 if req_stone.is_none() {
                     // No request present, make one.
                     *req_stone = Some(stone);
@@ -299,6 +875,9 @@ impl GameState {
             }
         };
 
+        // A retract request is declined if the opponent makes a move instead.
+        let declining = matches!(action, Action::Move(_))
+            && self.requests[Request::Retract as usize] == Some(stone.opposite());
         let msg = match action {
             Action::Move(mov) => {
                 if !self.record.make_move(mov) {
@@ -319,17 +898,47 @@ impl GameState {
             }
         };
 
+        if declining {
+            self.retract_declined_times[stone.opposite().index()] = Some(Instant::now());
+        }
+
         // Clear the requests.
         self.requests.fill(None);
         _ = self.msg_tx.send(msg);
     }
 }
 
-async fn host_game(id: GameId, mut cmd_rx: mpsc::Receiver<GameCommand>) {
+async fn host_game(
+    id: GameId,
+    mut cmd_rx: mpsc::Receiver<GameCommand>,
+    status_tx: watch::Sender<GameStatus>,
+    config: GameConfig,
+) -> Option<Vec<RecordedMessage>> {
     tracing::debug!("game started: {}", id.escape_ascii());
 
-    let mut state = GameState::new();
-    while let Some(cmd) = cmd_rx.recv().await {
+    let mut state = GameState::new(config);
+    let mut recording = config.record_messages.then(Vec::new);
+    loop {
+        let due_time = state.cursor_due_time();
+        let cmd = tokio::select! {
+            opt = cmd_rx.recv() => {
+                let Some(cmd) = opt else {
+                    // All command senders are dropped.
+                    break;
+                };
+                cmd
+            }
+            () = async {
+                match due_time {
+                    Some(time) => time::sleep_until(time.into()).await,
+                    None => future::pending().await,
+                }
+            } => {
+                state.flush_cursors();
+                continue;
+            }
+        };
+
         match cmd {
             GameCommand::Subscribe(resp_tx) => {
                 _ = resp_tx.send(state.subscribe());
@@ -337,10 +946,30 @@ async fn host_game(id: GameId, mut cmd_rx: mpsc::Receiver<GameCommand>) {
             GameCommand::Authenticate(resp_tx, pass) => {
                 _ = resp_tx.send(state.authenticate(pass));
             }
-            GameCommand::Play(stone, msg) => state.play(stone, msg),
+            GameCommand::Reserve(pass) => state.reserve(pass),
+            GameCommand::Transfer(resp_tx, stone) => {
+                _ = resp_tx.send(state.transfer_code(stone));
+            }
+            GameCommand::Play(stone, msg) => {
+                state.play(stone, msg);
+                status_tx.send_replace(GameStatus {
+                    ended: state.record.is_ended(),
+                    moves: state.record.move_index(),
+                });
+            }
+            GameCommand::RecordMessage(msg) => {
+                if let Some(recording) = &mut recording {
+                    if recording.len() < MAX_RECORDED_MESSAGES {
+                        recording.push(msg);
+                    }
+                }
+            }
+            GameCommand::RecordedMessages(resp_tx) => {
+                _ = resp_tx.send(recording.clone());
+            }
         }
     }
 
-    // All command senders are dropped.
     tracing::debug!("game ended: {}", id.escape_ascii());
+    recording
 }
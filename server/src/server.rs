@@ -1,24 +1,113 @@
-use crate::{manager, shutdown, ws};
-use axum::{routing::get, Router};
+use crate::{
+    api,
+    manager::{self, GameConfig, SeatPolicy},
+    shutdown, ws,
+};
+use axum::{
+    extract::Request,
+    http::{HeaderMap, Response},
+    routing::{get, post},
+    Router,
+};
+use c6ol_core::protocol::DEFAULT_RETRACT_COOLDOWN;
 use std::{
     future::{Future, IntoFuture},
     iter,
-    path::Path,
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+    time::Duration,
 };
 use tokio::{net::TcpListener, task::JoinSet};
-use tower_http::services::ServeDir;
+use tower_http::{classify::ServerErrorsFailureClass, services::ServeDir, trace::TraceLayer};
+use tracing::Span;
+
+/// Options for running the server.
+#[derive(Clone, Debug)]
+pub struct Options {
+    /// The directory to serve files from, if any.
+    pub serve_dir: Option<PathBuf>,
+    /// The fraction of completed requests to log, between 0 and 1.
+    pub access_log_sample_rate: f64,
+    /// The latency from which a request is always logged as slow.
+    pub slow_request_threshold: Duration,
+    /// The maximum number of open games created from an IP address, if any.
+    pub max_open_games_per_ip: Option<usize>,
+    /// The time for which a player cannot request to retract again
+    /// after their request is declined.
+    pub retract_cooldown: Duration,
+    /// Whether to record the raw protocol messages of each game for debugging.
+    pub record_messages: bool,
+    /// The bearer token required by the admin API, which is disabled if `None`.
+    pub admin_token: Option<String>,
+    /// What to do when a held seat is authenticated from another connection.
+    pub seat_policy: SeatPolicy,
+    /// Whether to take client addresses from the `X-Forwarded-For` header,
+    /// which must then be set by a trusted reverse proxy.
+    pub trust_forwarded_for: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            serve_dir: None,
+            access_log_sample_rate: 1.0,
+            slow_request_threshold: Duration::from_secs(1),
+            max_open_games_per_ip: None,
+            retract_cooldown: DEFAULT_RETRACT_COOLDOWN,
+            record_messages: false,
+            admin_token: None,
+            seat_policy: SeatPolicy::default(),
+            trust_forwarded_for: false,
+        }
+    }
+}
 
 /// Shared state for WebSocket handlers.
 #[derive(Clone)]
 pub struct AppState {
     pub shutdown_rx: shutdown::Receiver,
     pub manager: manager::GameManager,
+    pub admin_token: Option<String>,
+    pub trust_forwarded_for: bool,
+}
+
+impl AppState {
+    /// Returns the IP address of the client.
+    ///
+    /// If `X-Forwarded-For` is trusted, takes its last entry, which is
+    /// appended by the reverse proxy. Otherwise takes the peer address.
+    pub fn client_ip(&self, addr: SocketAddr, headers: &HeaderMap) -> IpAddr {
+        if self.trust_forwarded_for {
+            let forwarded = headers
+                .get_all("x-forwarded-for")
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(|value| value.split(','))
+                .last()
+                .and_then(|ip| ip.trim().parse().ok());
+            if let Some(ip) = forwarded {
+                return ip;
+            }
+        }
+        addr.ip()
+    }
+}
+
+/// Returns a request path for logging, with join codes masked.
+///
+/// Queries are left out, as they are not used by the server.
+fn redacted_path(path: &str) -> &str {
+    if path.starts_with("/api/join-codes/") {
+        "/api/join-codes/<redacted>"
+    } else {
+        path
+    }
 }
 
 /// Runs the server.
 pub async fn run(
     listeners: Vec<TcpListener>,
-    serve_dir: Option<&Path>,
+    options: Options,
     shutdown_signal: impl Future<Output = ()> + Send + 'static,
 ) {
     // Set up graceful shutdown, on which the following events happen:
@@ -36,29 +125,83 @@ pub async fn run(
         shutdown_tx.request();
     });
 
-    let (manager, manager_fut) = manager::create();
+    let (manager, manager_fut) = manager::create(
+        options.max_open_games_per_ip,
+        GameConfig {
+            retract_cooldown: options.retract_cooldown,
+            record_messages: options.record_messages,
+            seat_policy: options.seat_policy,
+        },
+    );
     let manager_task = tokio::spawn(manager_fut);
 
     let app_state = AppState {
         shutdown_rx: shutdown_rx.clone(),
         manager,
+        admin_token: options.admin_token.clone(),
+        trust_forwarded_for: options.trust_forwarded_for,
     };
 
     let mut app = Router::new()
         .route("/ws", get(ws::handle_websocket_upgrade))
+        .route("/api/stats", get(api::handle_stats))
+        .route(
+            "/api/admin/games/{id}/messages",
+            get(api::handle_recorded_messages),
+        )
+        .route("/api/games/{id}/join-code", post(api::handle_new_join_code))
+        .route(
+            "/api/join-codes/{code}",
+            post(api::handle_resolve_join_code),
+        )
         .with_state(app_state);
 
-    if let Some(path) = serve_dir {
+    if let Some(path) = &options.serve_dir {
         app = app.fallback_service(ServeDir::new(path));
     }
 
+    // Failed and slow requests are always logged, the rest are sampled.
+    let Options {
+        access_log_sample_rate: sample_rate,
+        slow_request_threshold: slow_threshold,
+        ..
+    } = options;
+    let app = app.layer(
+        TraceLayer::new_for_http()
+            .make_span_with(|req: &Request| {
+                let path = redacted_path(req.uri().path());
+                tracing::info_span!("request", method = %req.method(), path)
+            })
+            .on_request(())
+            .on_response(move |res: &Response<_>, latency: Duration, _: &Span| {
+                // Server errors are logged on failure.
+                if res.status().is_server_error() {
+                    return;
+                }
+                let status = res.status().as_u16();
+                if latency >= slow_threshold {
+                    tracing::warn!(status, ?latency, "slow request");
+                } else if rand::random::<f64>() < sample_rate {
+                    tracing::info!(status, ?latency, "request completed");
+                }
+            })
+            .on_failure(
+                |class: ServerErrorsFailureClass, latency: Duration, _: &Span| {
+                    tracing::error!(%class, ?latency, "request failed");
+                },
+            ),
+    );
+
     let mut server_tasks = JoinSet::new();
 
     for ((app, shutdown_rx), listener) in iter::repeat((app, shutdown_rx)).zip(listeners) {
         server_tasks.spawn(
-            axum::serve(listener, app)
-                .with_graceful_shutdown(shutdown_rx.requested())
-                .into_future(),
+            axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .with_graceful_shutdown(shutdown_rx.requested())
+            .into_future(),
         );
     }
 
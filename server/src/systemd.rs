@@ -0,0 +1,52 @@
+//! Integration with systemd: socket activation and service notifications.
+
+use listenfd::ListenFd;
+use std::io;
+use tokio::net::TcpListener;
+
+/// Takes the TCP listeners passed by socket activation (`LISTEN_FDS`), if any.
+pub fn take_listeners() -> io::Result<Vec<TcpListener>> {
+    let mut fds = ListenFd::from_env();
+    (0..fds.len())
+        .filter_map(|i| fds.take_tcp_listener(i).transpose())
+        .map(|res| {
+            let listener = res?;
+            listener.set_nonblocking(true)?;
+            TcpListener::from_std(listener)
+        })
+        .collect()
+}
+
+/// Notifies the service manager that the server is ready, and starts
+/// sending keep-alives if the watchdog is enabled.
+///
+/// Keep-alives are sent from a task on the same runtime as the server,
+/// so they stop as soon as the runtime hangs.
+///
+/// Does nothing when not running under systemd.
+#[cfg(unix)]
+pub fn notify_ready() {
+    use sd_notify::NotifyState;
+
+    _ = sd_notify::notify(false, &[NotifyState::Ready]);
+
+    let mut usec = 0;
+    if sd_notify::watchdog_enabled(false, &mut usec) {
+        tracing::info!("systemd watchdog enabled, timeout {usec}us");
+        // Ping at half the timeout, as recommended by systemd.
+        let mut interval =
+            tokio::time::interval(std::time::Duration::from_micros((usec / 2).max(1)));
+        tokio::spawn(async move {
+            loop {
+                interval.tick().await;
+                _ = sd_notify::notify(false, &[NotifyState::Watchdog]);
+            }
+        });
+    }
+}
+
+/// Notifies the service manager that the server is shutting down.
+#[cfg(unix)]
+pub fn notify_stopping() {
+    _ = sd_notify::notify(false, &[sd_notify::NotifyState::Stopping]);
+}
@@ -1,27 +1,38 @@
 //! WebSocket handling.
 
-use crate::{manager::GameManager, server::AppState};
+use crate::{
+    manager::{AuthError, Game, GameManager},
+    server::AppState,
+};
 use axum::{
     extract::{
         ws::{close_code, CloseFrame, Message, WebSocket},
-        State, WebSocketUpgrade,
+        ConnectInfo, State, WebSocketUpgrade,
     },
+    http::HeaderMap,
     response::Response,
 };
-use c6ol_core::protocol::{ClientMessage, ServerMessage};
-use futures_util::{future, SinkExt, StreamExt};
-use std::convert::Infallible;
+use c6ol_core::protocol::{ClientMessage, Passcode, ProtocolError, ServerMessage};
+use futures_util::{future, Sink, SinkExt, StreamExt};
+use std::{
+    borrow::Cow,
+    convert::Infallible,
+    net::{IpAddr, SocketAddr},
+};
 use tokio::sync::broadcast::error::RecvError;
 
 /// Handles a WebSocket upgrade.
 #[remain::check]
 pub async fn handle_websocket_upgrade(
     upgrade: WebSocketUpgrade,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
     State(state): State<AppState>,
+    headers: HeaderMap,
 ) -> Response {
-    upgrade.on_upgrade(|mut socket| async move {
+    let ip = state.client_ip(addr, &headers);
+    upgrade.on_upgrade(move |mut socket| async move {
         let err = tokio::select! {
-            res = handle_websocket(&mut socket, state.manager) => {
+            res = handle_websocket(&mut socket, state.manager, ip) => {
                 let Err(err) = res;
                 err
             }
@@ -36,9 +47,13 @@ pub async fn handle_websocket_upgrade(
             Error::Closed => return,
             Error::GameNotFound => close_code::NORMAL,
             Error::Lagged => close_code::AGAIN,
-            Error::MalformedMessage => close_code::POLICY,
+            Error::MalformedMessage(_) => close_code::POLICY,
+            Error::PasscodesEqual => close_code::POLICY,
+            Error::SeatTaken => close_code::NORMAL,
             Error::Shutdown => close_code::AWAY,
+            Error::SignedInElsewhere => close_code::NORMAL,
             Error::TextMessage => close_code::UNSUPPORTED,
+            Error::TooManyGames => close_code::POLICY,
             Error::UnexpectedMessage => close_code::POLICY,
             Error::WrongPasscode => close_code::NORMAL,
         };
@@ -61,63 +76,131 @@ enum Error {
     GameNotFound,
     #[error("Game desynced due to server lag.")]
     Lagged,
-    #[error("Malformed message.")]
-    MalformedMessage,
+    #[error("Malformed message: {0}.")]
+    MalformedMessage(ProtocolError),
+    #[error("Passcodes must differ.")]
+    PasscodesEqual,
+    #[error("Seat taken by another connection.")]
+    SeatTaken,
     #[error("The server is going down.")]
     Shutdown,
+    #[error("Signed in elsewhere.")]
+    SignedInElsewhere,
     #[error("Text message not supported.")]
     TextMessage,
+    #[error("Too many open games from your address.")]
+    TooManyGames,
     #[error("Unexpected message.")]
     UnexpectedMessage,
     #[error("Wrong passcode.")]
     WrongPasscode,
 }
 
+/// Placeholder for passcodes in recorded messages.
+const REDACTED_PASSCODE: &[u8] = b"<redacted>";
+
+fn redacted_passcode() -> Passcode {
+    REDACTED_PASSCODE.into()
+}
+
+/// Returns the data of a client message to record, with passcodes redacted.
+fn redact_client_message<'a>(msg: &ClientMessage, data: &'a [u8]) -> Cow<'a, [u8]> {
+    match msg {
+        ClientMessage::Start(_) => ClientMessage::Start(redacted_passcode()).encode().into(),
+        ClientMessage::StartReserved(..) => {
+            ClientMessage::StartReserved(redacted_passcode(), redacted_passcode())
+                .encode()
+                .into()
+        }
+        _ => data.into(),
+    }
+}
+
+/// Sends a message to the client, recording it in the game if enabled.
+///
+/// Transfer codes and passcodes are redacted in the record.
+async fn send<S>(socket: &mut S, game: &Game, msg: ServerMessage) -> Result<(), Error>
+where
+    S: Sink<Vec<u8>, Error = axum::Error> + Unpin,
+{
+    let redacted = match msg {
+        ServerMessage::TransferCode(_) => {
+            Some(ServerMessage::TransferCode(redacted_passcode()).encode())
+        }
+        ServerMessage::Passcode(_) => Some(ServerMessage::Passcode(redacted_passcode()).encode()),
+        _ => None,
+    };
+    let data = msg.encode();
+    game.record_message(false, redacted.as_deref().unwrap_or(&data))
+        .await;
+    socket.send(data).await?;
+    Ok(())
+}
+
 // Handles a WebSocket connection.
 async fn handle_websocket(
     socket: &mut WebSocket,
     manager: GameManager,
+    ip: IpAddr,
 ) -> Result<Infallible, Error> {
     let mut socket = socket
         .filter_map(|res| {
             future::ready(match res {
-                Ok(Message::Binary(data)) => match ClientMessage::decode(&data) {
-                    Some(msg) => Some(Ok(msg)),
-                    None => Some(Err(Error::MalformedMessage)),
+                Ok(Message::Binary(data)) => match ClientMessage::decode_err(&data) {
+                    Ok(msg) => Some(Ok((msg, data))),
+                    Err(err) => {
+                        tracing::debug!("malformed message {data:02x?}: {err}");
+                        Some(Err(Error::MalformedMessage(err)))
+                    }
                 },
                 Ok(Message::Text(_)) => Some(Err(Error::TextMessage)),
                 Ok(_) => None,
                 Err(err) => Some(Err(err.into())),
             })
         })
-        .with(|msg: ServerMessage| {
-            future::ok::<_, axum::Error>(Message::Binary(msg.encode().into()))
-        });
+        .with(|data: Vec<u8>| future::ok::<_, axum::Error>(Message::Binary(data.into())));
 
     let mut game;
 
-    match socket.next().await.ok_or(Error::Closed)?? {
-        ClientMessage::Start(passcode) => {
-            game = manager.new_game().await;
+    let (msg, data) = socket.next().await.ok_or(Error::Closed)??;
+    match msg {
+        ClientMessage::Join(id) => {
+            game = manager.find_game(id).await.ok_or(Error::GameNotFound)?;
+            game.record_message(true, &data).await;
+        }
+        msg => {
+            let recorded = redact_client_message(&msg, &data);
+            let (passcode, passcode_white) = match msg {
+                ClientMessage::Start(passcode) => (passcode, None),
+                ClientMessage::StartReserved(passcode, passcode_white) => {
+                    if passcode == passcode_white {
+                        return Err(Error::PasscodesEqual);
+                    }
+                    (passcode, Some(passcode_white))
+                }
+                _ => return Err(Error::UnexpectedMessage),
+            };
+
+            game = manager.new_game(ip).await.ok_or(Error::TooManyGames)?;
+            game.record_message(true, &recorded).await;
             game.authenticate(passcode)
                 .await
                 .expect("should be able to authenticate");
+            if let Some(passcode) = passcode_white {
+                game.reserve(passcode).await;
+            }
 
             let msg = ServerMessage::Started(
                 game.stone().expect("should be authenticated"),
                 Some(game.id()),
             );
-            socket.send(msg).await?;
-        }
-        ClientMessage::Join(id) => {
-            game = manager.find_game(id).await.ok_or(Error::GameNotFound)?;
+            send(&mut socket, &game, msg).await?;
         }
-        _ => return Err(Error::UnexpectedMessage),
     }
 
     let mut sub = game.subscribe().await;
     for msg in sub.init_msgs {
-        socket.send(msg).await?;
+        send(&mut socket, &game, msg).await?;
     }
 
     loop {
@@ -127,22 +210,36 @@ async fn handle_websocket(
                     RecvError::Closed => panic!("sender should be alive"),
                     RecvError::Lagged(_) => Error::Lagged,
                 })?;
-                socket.send(msg).await?;
+                send(&mut socket, &game, msg).await?;
             }
+            () = game.taken_over() => return Err(Error::SignedInElsewhere),
             opt = socket.next() => {
-                let msg = opt.ok_or(Error::Closed)??;
+                let (msg, data) = opt.ok_or(Error::Closed)??;
+                game.record_message(true, &redact_client_message(&msg, &data))
+                    .await;
                 match msg {
                     ClientMessage::Start(passcode) if game.stone().is_none() => {
-                        game.authenticate(passcode).await.ok_or(Error::WrongPasscode)?;
+                        let (stone, passcode) =
+                            game.authenticate(passcode).await.map_err(|err| match err {
+                                AuthError::WrongPasscode => Error::WrongPasscode,
+                                AuthError::SeatTaken => Error::SeatTaken,
+                            })?;
 
-                        let msg = ServerMessage::Started(
-                            game.stone().expect("should be authenticated"),
-                            None,
-                        );
-                        socket.send(msg).await?;
+                        send(&mut socket, &game, ServerMessage::Started(stone, None)).await?;
+                        if let Some(passcode) = passcode {
+                            send(&mut socket, &game, ServerMessage::Passcode(passcode)).await?;
+                        }
+                        continue;
+                    }
+                    ClientMessage::Transfer if game.stone().is_some() => {
+                        let code = game.transfer_code().await;
+                        send(&mut socket, &game, ServerMessage::TransferCode(code)).await?;
                         continue;
                     }
-                    ClientMessage::Start(_) | ClientMessage::Join(_) => {
+                    ClientMessage::Start(_)
+                    | ClientMessage::Join(_)
+                    | ClientMessage::StartReserved(..)
+                    | ClientMessage::Transfer => {
                         return Err(Error::UnexpectedMessage);
                     }
                     _ => {}
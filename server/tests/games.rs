@@ -0,0 +1,663 @@
+#![allow(missing_docs)]
+
+use c6ol_bot::{Client, Error};
+use c6ol_core::{
+    game::{Direction, Move, Point, Stone},
+    protocol::{ClientMessage, Passcode, Request, ServerMessage},
+};
+use c6ol_server::{Options, SeatPolicy};
+use std::{net::SocketAddr, time::Duration};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::oneshot,
+    task::JoinHandle,
+};
+
+/// A server running on an ephemeral port, shut down on drop.
+struct TestServer {
+    addr: SocketAddr,
+    url: String,
+    shutdown_tx: Option<oneshot::Sender<()>>,
+    task: JoinHandle<()>,
+}
+
+impl TestServer {
+    async fn spawn(options: Options) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let url = format!("ws://{addr}/ws");
+
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let task = tokio::spawn(c6ol_server::run(vec![listener], options, async {
+            _ = shutdown_rx.await;
+        }));
+        Self {
+            addr,
+            url,
+            shutdown_tx: Some(shutdown_tx),
+            task,
+        }
+    }
+
+    async fn connect(&self) -> Client {
+        Client::connect(&self.url).await.unwrap()
+    }
+
+    /// Sends a GET request with an optional bearer token,
+    /// returning the status code and the body.
+    async fn get(&self, path: &str, token: Option<&str>) -> (u16, String) {
+        self.request("GET", path, token).await
+    }
+
+    /// Sends a POST request with an empty body,
+    /// returning the status code and the body.
+    async fn post(&self, path: &str) -> (u16, String) {
+        self.request("POST", path, None).await
+    }
+
+    async fn request(&self, method: &str, path: &str, token: Option<&str>) -> (u16, String) {
+        let mut stream = TcpStream::connect(self.addr).await.unwrap();
+        let auth = token.map_or(String::new(), |token| {
+            format!("Authorization: Bearer {token}\r\n")
+        });
+        let req = format!(
+            "{method} {path} HTTP/1.1\r\nHost: localhost\r\n{auth}\
+             Content-Length: 0\r\nConnection: close\r\n\r\n"
+        );
+        stream.write_all(req.as_bytes()).await.unwrap();
+
+        let mut res = String::new();
+        stream.read_to_string(&mut res).await.unwrap();
+        let (head, body) = res.split_once("\r\n\r\n").unwrap();
+        let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+        (status, body.into())
+    }
+
+    /// Requests the server to shut down and waits for it to finish.
+    async fn shutdown(mut self) {
+        _ = self.shutdown_tx.take().unwrap().send(());
+        (&mut self.task).await.unwrap();
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+fn passcode(s: &str) -> Passcode {
+    s.as_bytes().into()
+}
+
+fn place(p1: (i16, i16), p2: Option<(i16, i16)>) -> Move {
+    Move::Place(Point::new(p1.0, p1.1), p2.map(|(x, y)| Point::new(x, y)))
+}
+
+/// Starts a game between two clients, returning them as Black and White.
+async fn start_game(server: &TestServer) -> (Client, Client) {
+    let mut black = server.connect().await;
+    let id = black.start(passcode("black")).await.unwrap();
+    assert_eq!(black.stone(), Some(Stone::Black));
+
+    let mut white = server.connect().await;
+    white.join(id).await.unwrap();
+    assert_eq!(
+        white.authenticate(passcode("white")).await.unwrap(),
+        Stone::White
+    );
+    (black, white)
+}
+
+/// Plays a move from `player`, checking that every client receives it.
+async fn play(player: usize, clients: &mut [&mut Client], mov: Move) {
+    clients[player].play(mov).await.unwrap();
+    for client in clients.iter_mut() {
+        assert_eq!(client.on_move().await.unwrap(), mov);
+    }
+}
+
+/// Receives messages until one satisfying `f` arrives.
+async fn recv_until(client: &mut Client, f: impl Fn(&ServerMessage) -> bool) -> ServerMessage {
+    loop {
+        let msg = client.recv().await.unwrap();
+        if f(&msg) {
+            return msg;
+        }
+    }
+}
+
+#[tokio::test]
+async fn full_game() {
+    let server = TestServer::spawn(Options::default()).await;
+    let (mut black, mut white) = start_game(&server).await;
+
+    play(0, &mut [&mut black, &mut white], place((0, 0), None)).await;
+    play(
+        1,
+        &mut [&mut black, &mut white],
+        place((1, 1), Some((2, 2))),
+    )
+    .await;
+
+    // A spectator receives the record so far.
+    let mut spectator = server.connect().await;
+    spectator.join(black.game_id().unwrap()).await.unwrap();
+    assert_eq!(spectator.record(), black.record());
+
+    // White asks to retract their move, and Black agrees.
+    let req = || ClientMessage::Request(Request::Retract);
+    white.send(req()).await.unwrap();
+    for client in [&mut black, &mut white, &mut spectator] {
+        let msg = recv_until(client, |msg| matches!(msg, ServerMessage::Request(..))).await;
+        assert!(matches!(
+            msg,
+            ServerMessage::Request(Stone::White, Request::Retract)
+        ));
+    }
+    black.send(req()).await.unwrap();
+    for client in [&mut black, &mut white, &mut spectator] {
+        recv_until(client, |msg| matches!(msg, ServerMessage::Retract)).await;
+        assert_eq!(client.record().move_index(), 1);
+    }
+
+    let clients = &mut [&mut black, &mut white, &mut spectator];
+    play(1, clients, place((3, 3), None)).await;
+    play(0, clients, Move::Resign(Stone::Black)).await;
+    for client in clients {
+        assert!(client.record().is_ended());
+    }
+
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn win_claim_is_normalized() {
+    let server = TestServer::spawn(Options::default()).await;
+    let (mut black, mut white) = start_game(&server).await;
+    let clients = &mut [&mut black, &mut white];
+
+    play(0, clients, place((0, 0), None)).await;
+    play(1, clients, place((0, 5), Some((1, 5)))).await;
+    play(0, clients, place((1, 0), Some((2, 0)))).await;
+    play(1, clients, place((2, 5), Some((3, 5)))).await;
+    play(0, clients, place((3, 0), Some((4, 0)))).await;
+    play(1, clients, place((4, 5), Some((6, 5)))).await;
+    play(0, clients, place((5, 0), Some((9, 9)))).await;
+
+    // Claimed from the eastern end, the win is broadcast from the western end.
+    clients[1]
+        .play(Move::Win(Point::new(5, 0), Direction::West))
+        .await
+        .unwrap();
+    for client in clients {
+        assert_eq!(
+            client.on_move().await.unwrap(),
+            Move::Win(Point::new(0, 0), Direction::East)
+        );
+    }
+
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn reconnect() {
+    let server = TestServer::spawn(Options::default()).await;
+    let (mut black, mut white) = start_game(&server).await;
+    let id = black.game_id().unwrap();
+
+    play(0, &mut [&mut black, &mut white], place((0, 0), None)).await;
+    let record = black.record().clone();
+    black.close().await.unwrap();
+
+    // The game stays alive while White is connected.
+    let mut black = server.connect().await;
+    black.join(id).await.unwrap();
+    assert_eq!(black.record(), &record);
+    assert_eq!(
+        black.authenticate(passcode("black")).await.unwrap(),
+        Stone::Black
+    );
+
+    play(
+        1,
+        &mut [&mut black, &mut white],
+        place((1, 0), Some((2, 0))),
+    )
+    .await;
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn rejections() {
+    let server = TestServer::spawn(Options {
+        max_open_games_per_ip: Some(1),
+        ..Options::default()
+    })
+    .await;
+    let (black, _white) = start_game(&server).await;
+
+    let mut client = server.connect().await;
+    client.join(black.game_id().unwrap()).await.unwrap();
+    assert!(matches!(
+        client.authenticate(passcode("other")).await,
+        Err(Error::Closed(reason)) if reason == "Wrong passcode."
+    ));
+
+    let mut client = server.connect().await;
+    assert!(matches!(
+        client.join(*b"0123456789").await,
+        Err(Error::Closed(reason)) if reason == "Game not found."
+    ));
+
+    let mut client = server.connect().await;
+    assert!(matches!(
+        client.start(passcode("black")).await,
+        Err(Error::Closed(reason)) if reason == "Too many open games from your address."
+    ));
+
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn farewell() {
+    let server = TestServer::spawn(Options::default()).await;
+    let (mut black, mut white) = start_game(&server).await;
+
+    let farewell = Some("Well played!".into());
+    black.send(ClientMessage::Resign(farewell)).await.unwrap();
+    assert!(matches!(
+        white.recv().await.unwrap(),
+        ServerMessage::Farewell(Stone::Black, s) if &*s == "Well played!"
+    ));
+    assert_eq!(white.on_move().await.unwrap(), Move::Resign(Stone::Black));
+
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn retract_cooldown() {
+    let server = TestServer::spawn(Options::default()).await;
+    let (mut black, mut white) = start_game(&server).await;
+    let req = || ClientMessage::Request(Request::Retract);
+
+    play(0, &mut [&mut black, &mut white], place((0, 0), None)).await;
+
+    // White declines Black's request by making a move instead.
+    black.send(req()).await.unwrap();
+    for client in [&mut black, &mut white] {
+        let msg = client.recv().await.unwrap();
+        assert!(matches!(
+            msg,
+            ServerMessage::Request(Stone::Black, Request::Retract)
+        ));
+    }
+    play(
+        1,
+        &mut [&mut black, &mut white],
+        place((1, 0), Some((2, 0))),
+    )
+    .await;
+
+    // Requesting again right away is ignored.
+    black.send(req()).await.unwrap();
+    play(
+        0,
+        &mut [&mut black, &mut white],
+        place((3, 0), Some((4, 0))),
+    )
+    .await;
+
+    // White has not been declined and can still request.
+    white.send(req()).await.unwrap();
+    for client in [&mut black, &mut white] {
+        let msg = client.recv().await.unwrap();
+        assert!(matches!(
+            msg,
+            ServerMessage::Request(Stone::White, Request::Retract)
+        ));
+    }
+
+    // White's own move does not decline White's request.
+    play(
+        1,
+        &mut [&mut black, &mut white],
+        place((5, 0), Some((6, 0))),
+    )
+    .await;
+    white.send(req()).await.unwrap();
+    for client in [&mut black, &mut white] {
+        let msg = client.recv().await.unwrap();
+        assert!(matches!(
+            msg,
+            ServerMessage::Request(Stone::White, Request::Retract)
+        ));
+    }
+
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn retract_cooldown_sent() {
+    let cooldown = Duration::from_secs(5);
+    let server = TestServer::spawn(Options {
+        retract_cooldown: cooldown,
+        ..Options::default()
+    })
+    .await;
+    let (black, _white) = start_game(&server).await;
+
+    // The configured cooldown comes first on joining.
+    let mut spectator = server.connect().await;
+    spectator
+        .send(ClientMessage::Join(black.game_id().unwrap()))
+        .await
+        .unwrap();
+    assert!(matches!(
+        spectator.recv().await.unwrap(),
+        ServerMessage::RetractCooldown(d) if d == cooldown
+    ));
+    assert!(matches!(
+        spectator.recv().await.unwrap(),
+        ServerMessage::Record(_)
+    ));
+
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn cursor_rate_limit() {
+    let server = TestServer::spawn(Options::default()).await;
+    let (mut black, mut white) = start_game(&server).await;
+
+    // Positions shared too often are delayed, keeping only the latest one.
+    for (x, y) in [(0, 0), (1, 1), (2, 2)] {
+        let msg = ClientMessage::Cursor(Some(Point::new(x, y)));
+        black.send(msg).await.unwrap();
+    }
+    for expected in [(0, 0), (2, 2)] {
+        let msg = recv_until(&mut white, |msg| matches!(msg, ServerMessage::Cursor(..))).await;
+        assert!(matches!(
+            msg,
+            ServerMessage::Cursor(Stone::Black, Some(p)) if p == Point::new(expected.0, expected.1)
+        ));
+    }
+
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn start_reserved() {
+    let server = TestServer::spawn(Options::default()).await;
+
+    let mut black = server.connect().await;
+    let id = black
+        .start_reserved(passcode("black"), passcode("white"))
+        .await
+        .unwrap();
+
+    // Only the reserved passcode takes White's seat.
+    let mut client = server.connect().await;
+    client.join(id).await.unwrap();
+    assert!(matches!(
+        client.authenticate(passcode("other")).await,
+        Err(Error::Closed(reason)) if reason == "Wrong passcode."
+    ));
+
+    let mut white = server.connect().await;
+    white.join(id).await.unwrap();
+    assert_eq!(
+        white.authenticate(passcode("white")).await.unwrap(),
+        Stone::White
+    );
+    play(0, &mut [&mut black, &mut white], place((0, 0), None)).await;
+
+    // Reserving with Black's passcode is rejected.
+    let mut client = server.connect().await;
+    assert!(matches!(
+        client.start_reserved(passcode("same"), passcode("same")).await,
+        Err(Error::Closed(reason)) if reason == "Passcodes must differ."
+    ));
+
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn transfer_seat() {
+    let server = TestServer::spawn(Options::default()).await;
+    let (mut black, mut white) = start_game(&server).await;
+    let id = black.game_id().unwrap();
+
+    // A new code replaces the previous one.
+    let old_code = black.transfer_code().await.unwrap();
+    let code = black.transfer_code().await.unwrap();
+    assert_ne!(code, old_code);
+
+    let mut client = server.connect().await;
+    client.join(id).await.unwrap();
+    assert!(matches!(
+        client.authenticate(old_code).await,
+        Err(Error::Closed(reason)) if reason == "Wrong passcode."
+    ));
+
+    let mut new_black = server.connect().await;
+    new_black.join(id).await.unwrap();
+    assert_eq!(
+        new_black.authenticate(code.clone()).await.unwrap(),
+        Stone::Black
+    );
+    play(
+        0,
+        &mut [&mut new_black, &mut black, &mut white],
+        place((0, 0), None),
+    )
+    .await;
+
+    // The code works only once.
+    let mut client = server.connect().await;
+    client.join(id).await.unwrap();
+    assert!(matches!(
+        client.authenticate(code).await,
+        Err(Error::Closed(reason)) if reason == "Wrong passcode."
+    ));
+
+    // Spectators cannot get a code.
+    let mut spectator = server.connect().await;
+    spectator.join(id).await.unwrap();
+    spectator.send(ClientMessage::Transfer).await.unwrap();
+    assert!(matches!(
+        spectator.recv().await,
+        Err(Error::Closed(reason)) if reason == "Unexpected message."
+    ));
+
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn record_messages() {
+    let server = TestServer::spawn(Options {
+        record_messages: true,
+        admin_token: Some("secret".into()),
+        ..Options::default()
+    })
+    .await;
+    let (mut black, mut white) = start_game(&server).await;
+    let mov = place((0, 0), None);
+    play(0, &mut [&mut black, &mut white], mov).await;
+
+    let id = black.game_id().unwrap();
+    let path = format!("/api/admin/games/{}/messages", id.escape_ascii());
+    assert_eq!(server.get(&path, None).await.0, 401);
+    assert_eq!(server.get(&path, Some("wrong")).await.0, 401);
+    assert_eq!(
+        server
+            .get("/api/admin/games/0123456789/messages", Some("secret"))
+            .await
+            .0,
+        404
+    );
+
+    let (status, body) = server.get(&path, Some("secret")).await;
+    assert_eq!(status, 200);
+    let msgs: Vec<serde_json::Value> = serde_json::from_str(&body).unwrap();
+    let data = |msg: &serde_json::Value| -> Vec<u8> {
+        serde_json::from_value(msg["data"].clone()).unwrap()
+    };
+
+    // The first message is Black starting the game, with the passcode redacted.
+    assert_eq!(msgs[0]["from_client"], true);
+    assert_eq!(
+        data(&msgs[0]),
+        ClientMessage::Start(passcode("<redacted>")).encode()
+    );
+
+    // The move is received from Black and sent to both players.
+    let place_msg = ClientMessage::Place(Point::new(0, 0), None).encode();
+    assert!(msgs
+        .iter()
+        .any(|msg| msg["from_client"] == true && data(msg) == place_msg));
+    let move_msg = ServerMessage::Move(mov).encode();
+    let conns: Vec<_> = msgs
+        .iter()
+        .filter(|msg| msg["from_client"] == false && data(msg) == move_msg)
+        .map(|msg| msg["conn"].as_u64().unwrap())
+        .collect();
+    assert_eq!(conns.len(), 2);
+    assert_ne!(conns[0], conns[1]);
+
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn admin_api_disabled() {
+    let server = TestServer::spawn(Options {
+        record_messages: true,
+        ..Options::default()
+    })
+    .await;
+    let (black, _white) = start_game(&server).await;
+
+    let path = format!(
+        "/api/admin/games/{}/messages",
+        black.game_id().unwrap().escape_ascii()
+    );
+    assert_eq!(server.get(&path, Some("")).await.0, 404);
+
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn join_codes() {
+    let server = TestServer::spawn(Options::default()).await;
+    let (black, _white) = start_game(&server).await;
+    let id = black.game_id().unwrap();
+    let path = format!("/api/games/{}/join-code", id.escape_ascii());
+    let resolve = |code: &str| {
+        let server = &server;
+        let path = format!("/api/join-codes/{code}");
+        async move { server.post(&path).await }
+    };
+
+    let (status, code) = server.post(&path).await;
+    assert_eq!(status, 200);
+    assert_eq!(code.len(), 6);
+    assert!(code.bytes().all(|b| b.is_ascii_digit()));
+
+    // A new code replaces the old one.
+    let (status, new_code) = server.post(&path).await;
+    assert_eq!(status, 200);
+    if new_code != code {
+        assert_eq!(resolve(&code).await.0, 404);
+    }
+
+    // A code works only once.
+    assert_eq!(
+        resolve(&new_code).await,
+        (200, String::from_utf8(id.to_vec()).unwrap())
+    );
+    assert_eq!(resolve(&new_code).await.0, 404);
+
+    // Resolving consumes the code, so it takes a POST.
+    let (_, code) = server.post(&path).await;
+    let get_path = format!("/api/join-codes/{code}");
+    assert_eq!(server.get(&get_path, None).await.0, 405);
+    assert_eq!(resolve(&code).await.0, 200);
+
+    assert_eq!(server.post("/api/games/0123456789/join-code").await.0, 404);
+    assert_eq!(resolve("12345").await.0, 404);
+    assert_eq!(resolve("+12345").await.0, 404);
+
+    // Too many failures block even a valid code.
+    let (_, code) = server.post(&path).await;
+    let mut status = 404;
+    for _ in 0..10 {
+        status = resolve("000000").await.0;
+        if status == 429 {
+            break;
+        }
+    }
+    assert_eq!(status, 429);
+    assert_eq!(resolve(&code).await.0, 429);
+
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn seat_policy_reject_new() {
+    let server = TestServer::spawn(Options {
+        seat_policy: SeatPolicy::RejectNew,
+        ..Options::default()
+    })
+    .await;
+    let (mut black, _white) = start_game(&server).await;
+    let id = black.game_id().unwrap();
+
+    let mut client = server.connect().await;
+    client.join(id).await.unwrap();
+    assert!(matches!(
+        client.authenticate(passcode("black")).await,
+        Err(Error::Closed(reason)) if reason == "Seat taken by another connection."
+    ));
+
+    // A transfer code still moves the seat.
+    let code = black.transfer_code().await.unwrap();
+    let mut new_black = server.connect().await;
+    new_black.join(id).await.unwrap();
+    assert_eq!(new_black.authenticate(code).await.unwrap(), Stone::Black);
+    // The passcode is sent for signing in again.
+    assert!(matches!(
+        new_black.recv().await.unwrap(),
+        ServerMessage::Passcode(p) if p == passcode("black")
+    ));
+    assert!(matches!(
+        black.recv().await,
+        Err(Error::Closed(reason)) if reason == "Signed in elsewhere."
+    ));
+
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn seat_policy_take_over() {
+    let server = TestServer::spawn(Options {
+        seat_policy: SeatPolicy::TakeOver,
+        ..Options::default()
+    })
+    .await;
+    let (mut black, mut white) = start_game(&server).await;
+
+    let mut new_black = server.connect().await;
+    new_black.join(black.game_id().unwrap()).await.unwrap();
+    assert_eq!(
+        new_black.authenticate(passcode("black")).await.unwrap(),
+        Stone::Black
+    );
+    assert!(matches!(
+        black.recv().await,
+        Err(Error::Closed(reason)) if reason == "Signed in elsewhere."
+    ));
+    play(0, &mut [&mut new_black, &mut white], place((0, 0), None)).await;
+
+    server.shutdown().await;
+}
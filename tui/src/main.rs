@@ -0,0 +1,386 @@
+#![allow(missing_docs)]
+
+use anyhow::{bail, Context};
+use c6ol_bot::Client;
+use c6ol_core::{
+    game::{GameResult, Move, Point, Record, Stone, WinBy},
+    protocol::{ClientMessage, GameId, Request, ServerMessage, TRANSFER_CODE_TTL},
+};
+use clap::Parser;
+use crossterm::event::{Event, EventStream, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use futures_util::StreamExt;
+use ratatui::{
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Paragraph, Wrap},
+    DefaultTerminal, Frame,
+};
+
+/// Width of a cell on the board, in columns.
+const CELL_WIDTH: u16 = 2;
+
+/// Width of the side panel, in columns.
+const PANEL_WIDTH: u16 = 30;
+
+const KEYS_HELP: &str = "\
+Arrows/HJKL  Move cursor
+Space        Toggle stone
+Enter        Submit stones
+P            Pass
+W            Claim win at cursor
+D            Offer/accept draw
+Shift+R      Resign
+T            Get seat transfer code
+Q/Esc        Quit";
+
+/// Terminal client for Connect6 Online
+#[derive(Debug, Parser)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Connect to the WebSocket endpoint at the given URL
+    #[arg(long, default_value = "ws://localhost:8086/ws")]
+    url: String,
+
+    /// Join the game with the given ID instead of starting a new one
+    #[arg(long, name = "ID")]
+    join: Option<String>,
+
+    /// Authenticate with the given passcode, or watch only if omitted when joining
+    #[arg(long)]
+    passcode: Option<String>,
+}
+
+struct App {
+    client: Client,
+    cursor: Point,
+    tentatives: Vec<Point>,
+    /// The message shown in the status line.
+    status: String,
+    /// The farewell message left by a resigning player.
+    farewell: Option<String>,
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+
+    let mut client = Client::connect(&args.url)
+        .await
+        .context("failed to connect")?;
+
+    let status = if let Some(id) = &args.join {
+        let id = GameId::try_from(id.as_bytes()).context("invalid game ID")?;
+        client.join(id).await.context("failed to join the game")?;
+        if let Some(passcode) = args.passcode {
+            let stone = client
+                .authenticate(passcode.into_bytes().into())
+                .await
+                .context("failed to authenticate")?;
+            format!("Playing {stone:?}.")
+        } else {
+            "Watching.".into()
+        }
+    } else {
+        let Some(passcode) = args.passcode else {
+            bail!("a passcode is required to start a game");
+        };
+        client
+            .start(passcode.into_bytes().into())
+            .await
+            .context("failed to start a game")?;
+        "Started a game. Share the ID with your opponent.".into()
+    };
+
+    let mut app = App {
+        client,
+        cursor: Point::new(0, 0),
+        tentatives: vec![],
+        status,
+        farewell: None,
+    };
+
+    let mut terminal = ratatui::init();
+    let res = app.run(&mut terminal).await;
+    ratatui::restore();
+    res
+}
+
+impl App {
+    async fn run(&mut self, terminal: &mut DefaultTerminal) -> anyhow::Result<()> {
+        let mut events = EventStream::new();
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+
+            // Both futures are cancel-safe.
+            tokio::select! {
+                msg = self.client.recv() => match msg {
+                    Ok(msg) => self.on_message(&msg),
+                    Err(err) => {
+                        self.status = format!("{err}. Press any key to quit.");
+                        terminal.draw(|frame| self.draw(frame))?;
+                        while let Some(Ok(ev)) = events.next().await {
+                            if matches!(ev, Event::Key(key) if key.kind == KeyEventKind::Press) {
+                                break;
+                            }
+                        }
+                        return Ok(());
+                    }
+                },
+                ev = events.next() => match ev {
+                    Some(Ok(Event::Key(key))) if key.kind == KeyEventKind::Press => {
+                        if !self.on_key(key).await? {
+                            return Ok(());
+                        }
+                    }
+                    Some(Ok(_)) => {}
+                    Some(Err(err)) => return Err(err.into()),
+                    None => return Ok(()),
+                },
+            }
+        }
+    }
+
+    fn record(&self) -> &Record {
+        self.client.record()
+    }
+
+    /// Tests if it is our turn to place stones.
+    fn our_turn(&self) -> bool {
+        let stone = self.client.stone();
+        stone.is_some() && self.record().turn() == stone
+    }
+
+    fn on_message(&mut self, msg: &ServerMessage) {
+        match *msg {
+            ServerMessage::Started(stone, _) => self.status = format!("Playing {stone:?}."),
+            ServerMessage::Record(_) | ServerMessage::Retract => {
+                self.tentatives.clear();
+                self.status = "Record updated.".into();
+            }
+            ServerMessage::Move(mov) => {
+                self.tentatives.clear();
+                self.status = match self.record().turn() {
+                    _ if mov.is_ending() => match self.farewell.take() {
+                        Some(farewell) => format!("Game ended. {farewell}"),
+                        None => "Game ended.".into(),
+                    },
+                    Some(stone) if Some(stone) == self.client.stone() => "Your turn.".into(),
+                    Some(stone) => format!("{stone:?} to play."),
+                    None => unreachable!(),
+                };
+            }
+            ServerMessage::Request(stone, req) => {
+                let req = match req {
+                    Request::Draw => "offers a draw (D to accept)",
+                    Request::Retract => "requests to retract the previous move",
+                    Request::Reset => "requests to reset the game",
+                };
+                self.status = format!("{stone:?} {req}.");
+            }
+            ServerMessage::Cursor(..)
+            | ServerMessage::Passcode(_)
+            | ServerMessage::RetractCooldown(_) => {}
+            ServerMessage::Farewell(stone, ref farewell) => {
+                self.farewell = Some(format!("{stone:?} says: \"{farewell}\""));
+            }
+            ServerMessage::TransferCode(ref code) => {
+                self.status = format!(
+                    "Transfer code: {} (one-time, valid for {} min).",
+                    code.escape_ascii(),
+                    TRANSFER_CODE_TTL.as_secs() / 60
+                );
+            }
+        }
+    }
+
+    /// Handles a key press, returning whether to continue.
+    async fn on_key(&mut self, key: KeyEvent) -> anyhow::Result<bool> {
+        let (dx, dy) = match key.code {
+            KeyCode::Left | KeyCode::Char('h') => (-1, 0),
+            KeyCode::Right | KeyCode::Char('l') => (1, 0),
+            KeyCode::Up | KeyCode::Char('k') => (0, -1),
+            KeyCode::Down | KeyCode::Char('j') => (0, 1),
+            _ => (0, 0),
+        };
+        if (dx, dy) != (0, 0) {
+            let p = self.cursor;
+            self.cursor = Point::new(p.x.saturating_add(dx), p.y.saturating_add(dy));
+            return Ok(true);
+        }
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(false),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return Ok(false);
+            }
+            KeyCode::Char(' ') => self.toggle_tentative(),
+            KeyCode::Enter => {
+                let max = self.record().max_stones_to_play();
+                if !self.our_turn() {
+                    self.status = "Not your turn.".into();
+                } else if self.tentatives.len() < max {
+                    self.status = format!("Place {max} stone(s) first, or press P to pass.");
+                } else {
+                    self.play(Move::Place(
+                        self.tentatives[0],
+                        self.tentatives.get(1).copied(),
+                    ))
+                    .await?;
+                }
+            }
+            KeyCode::Char('p') => {
+                if !self.our_turn() {
+                    self.status = "Not your turn.".into();
+                } else if let Some(&p) = self.tentatives.first() {
+                    self.play(Move::Place(p, None)).await?;
+                } else {
+                    self.play(Move::Pass).await?;
+                }
+            }
+            KeyCode::Char('w') => {
+                if let Some((p, dir)) = self.record().find_winning_row(self.cursor) {
+                    self.play(Move::Win(p, dir)).await?;
+                } else {
+                    self.status = "No six-in-a-row at the cursor.".into();
+                }
+            }
+            KeyCode::Char('d') if self.client.stone().is_some() => {
+                self.status = "Offered a draw.".into();
+                self.play(Move::Draw).await?;
+            }
+            KeyCode::Char('R') => {
+                if let Some(stone) = self.client.stone() {
+                    self.play(Move::Resign(stone)).await?;
+                }
+            }
+            KeyCode::Char('t') if self.client.stone().is_some() => {
+                self.client.send(ClientMessage::Transfer).await?;
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    async fn play(&mut self, mov: Move) -> anyhow::Result<()> {
+        if self.client.stone().is_none() {
+            self.status = "Watching only.".into();
+            return Ok(());
+        }
+        if let Err(err) = self.record().check_move(mov) {
+            self.status = format!("{err}.");
+            return Ok(());
+        }
+        self.client.play(mov).await?;
+        Ok(())
+    }
+
+    fn toggle_tentative(&mut self) {
+        let p = self.cursor;
+        if let Some(i) = self.tentatives.iter().position(|&q| q == p) {
+            self.tentatives.remove(i);
+        } else if !self.our_turn() {
+            self.status = "Not your turn.".into();
+        } else if self.record().stone_at(p).is_some() {
+            self.status = "Occupied.".into();
+        } else if self.tentatives.len() < self.record().max_stones_to_play() {
+            self.tentatives.push(p);
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame<'_>) {
+        let [board_area, panel_area] =
+            Layout::horizontal([Constraint::Min(0), Constraint::Length(PANEL_WIDTH)])
+                .areas(frame.area());
+
+        let block = Block::bordered().title(" Connect6 Online ");
+        let inner = block.inner(board_area);
+        frame.render_widget(block, board_area);
+        frame.render_widget(Paragraph::new(self.board_lines(inner)), inner);
+
+        let [info_area, keys_area] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(11)]).areas(panel_area);
+        frame.render_widget(
+            Paragraph::new(self.info_lines())
+                .wrap(Wrap { trim: false })
+                .block(Block::bordered().title(" Game ")),
+            info_area,
+        );
+        frame.render_widget(
+            Paragraph::new(KEYS_HELP).block(Block::bordered().title(" Keys ")),
+            keys_area,
+        );
+    }
+
+    fn info_lines(&self) -> Vec<Line<'_>> {
+        let record = self.record();
+        let id = self
+            .client
+            .game_id()
+            .map(|id| String::from_utf8_lossy(&id).into_owned())
+            .unwrap_or_default();
+        let role = match self.client.stone() {
+            Some(stone) => format!("Playing {stone:?}"),
+            None => "View Only".into(),
+        };
+        let status = match record.result() {
+            None => format!("{:?} to Play", record.turn().unwrap()),
+            Some(GameResult::Draw) => "Game Drawn".into(),
+            Some(GameResult::Win(stone, WinBy::Resignation)) => {
+                format!("{:?} Resigned", stone.opposite())
+            }
+            Some(GameResult::Win(stone, WinBy::Row)) => format!("{stone:?} Won"),
+        };
+
+        vec![
+            Line::from(format!("ID: {id}")),
+            Line::from(role),
+            Line::from(status),
+            Line::from(format!("Move {}", record.move_index())),
+            Line::from(format!("Cursor ({}, {})", self.cursor.x, self.cursor.y)),
+            Line::default(),
+            Line::from(self.status.as_str()),
+        ]
+    }
+
+    /// Renders the board centered on the cursor.
+    fn board_lines(&self, area: Rect) -> Vec<Line<'_>> {
+        let record = self.record();
+        let cols = i32::from(area.width / CELL_WIDTH);
+        let rows = i32::from(area.height);
+
+        let prev: Vec<Point> = match record.prev_move() {
+            Some(Move::Place(p1, p2)) => [Some(p1), p2].into_iter().flatten().collect(),
+            _ => vec![],
+        };
+
+        (0..rows)
+            .map(|row| {
+                let spans = (0..cols)
+                    .filter_map(|col| {
+                        let x = i32::from(self.cursor.x) + col - cols / 2;
+                        let y = i32::from(self.cursor.y) + row - rows / 2;
+                        let p = Point::new(x.try_into().ok()?, y.try_into().ok()?);
+
+                        let (symbol, mut style) = match record.stone_at(p) {
+                            Some(Stone::Black) => ("X", Style::new().fg(Color::Red)),
+                            Some(Stone::White) => ("O", Style::new().fg(Color::Cyan)),
+                            None if self.tentatives.contains(&p) => {
+                                ("+", Style::new().fg(Color::Yellow))
+                            }
+                            None => (".", Style::new().fg(Color::DarkGray)),
+                        };
+                        if prev.contains(&p) {
+                            style = style.add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+                        }
+                        if p == self.cursor {
+                            style = style.add_modifier(Modifier::REVERSED);
+                        }
+                        Some(Span::styled(format!("{symbol} "), style))
+                    })
+                    .collect::<Vec<_>>();
+                Line::from(spans)
+            })
+            .collect()
+    }
+}